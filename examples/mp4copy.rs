@@ -5,8 +5,8 @@ use std::io::{self, BufReader, BufWriter};
 use std::path::Path;
 
 use mp4::{
-    AacConfig, AvcConfig, HevcConfig, MediaConfig, MediaType, Mp4Config, Result, TrackConfig,
-    TtxtConfig, Vp9Config,
+    AacConfig, AvcConfig, HevcConfig, MediaConfig, MediaType, Mp4Config, Result, SampleOrder,
+    TrackConfig, TtxtConfig, Vp9Config,
 };
 
 fn main() {
@@ -41,7 +41,6 @@ fn copy<P: AsRef<Path>>(src_filename: &P, dst_filename: &P) -> Result<()> {
         },
     )?;
 
-    // TODO interleaving
     for track in mp4_reader.tracks().values() {
         let media_conf = match track.media_type()? {
             MediaType::H264 => MediaConfig::AvcConfig(AvcConfig {
@@ -77,14 +76,9 @@ fn copy<P: AsRef<Path>>(src_filename: &P, dst_filename: &P) -> Result<()> {
         mp4_writer.add_track(&track_conf)?;
     }
 
-    for track_id in mp4_reader.tracks().keys().copied().collect::<Vec<u32>>() {
-        let sample_count = mp4_reader.sample_count(track_id)?;
-        for sample_idx in 0..sample_count {
-            let sample_id = sample_idx + 1;
-            let sample = mp4_reader.read_sample(track_id, sample_id)?.unwrap();
-            mp4_writer.write_sample(track_id, &sample)?;
-            // println!("copy {}:({})", sample_id, sample);
-        }
+    for result in mp4_reader.samples(SampleOrder::DecodeTime)? {
+        let (track_id, sample) = result?;
+        mp4_writer.write_sample(track_id, &sample)?;
     }
 
     mp4_writer.write_end()?;
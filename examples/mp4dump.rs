@@ -3,8 +3,8 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io::{self, BufReader};
 use std::path::Path;
-use std::sync::Arc;
-use mp4::{Mp4Box, Result, TrackType};
+
+use mp4::{BoxNode, BoxStatus, Result};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -21,142 +21,31 @@ fn main() {
 
 fn dump<P: AsRef<Path>>(filename: &P) -> Result<()> {
     let f = File::open(filename)?;
-    let boxes = get_boxes(f)?;
+    let size = f.metadata()?.len();
+    let mut reader = BufReader::new(f);
+    let boxes = mp4::read_box_tree(&mut reader, size)?;
 
-    // print out boxes
     for b in boxes.iter() {
-        match b.name.as_str() {
-            // "ftyp" => println!("[{}] size={} major_brand={} compatible_brands={}",
-            //                   b.name, b.size, b.summary, b.summary),
-            // "moov" => println!("[{}] size={} {}", b.name, b.size, b.summary),
-            // "mvhd" => println!("[{}] size={} timescale={} duration={}",
-            //                   b.name, b.size, b.summary, b.summary),
-            // "trak" => println!("[{}] size={} {}", b.name, b.size, b.summary),
-            // "mdia" => println!("[{}] size={} {}", b.name, b.size, b.summary),
-            "tfhd" => {},
-            "traf" => {},
-            "trun" => {},
-            "mfhd" => {}
-            "sidx" => println!("[{}] size={} {}", b.name, b.size, b.summary),
-            _ => {},
-        }
-    }
-    
-    Ok(())
-}
-
-#[derive(Debug, Clone, PartialEq, Default)]
-pub struct Box {
-    name: String,
-    byte_offset: u64,
-    size: u64,
-    summary: String,
-    indent: u32,
-}
-
-fn get_boxes(file: File) -> Result<Vec<Box>> {
-    let size = file.metadata()?.len();
-    let reader = BufReader::new(file);
-    let mp4 = mp4::Mp4Reader::read_header(reader, size)?;
-
-    // collect known boxes
-    let mut boxes = vec![
-        build_box(&mp4.ftyp),
-        build_box(&mp4.moov),
-        build_box(&mp4.moov.mvhd),
-    ];
-    
-    for sidx in mp4.sidx.iter() {
-        boxes.push(build_box(&sidx.0));
-    }
-
-    if let Some(mvex) = &mp4.moov.mvex {
-        boxes.push(build_box(mvex));
-        if let Some(mehd) = &mvex.mehd {
-            boxes.push(build_box(mehd));
-        }
-        boxes.push(build_box(&mvex.trex));
+        b.walk(&mut print_box);
     }
 
-    // trak.
-    for track in mp4.tracks().values() {
-        boxes.push(build_box(&track.trak));
-        boxes.push(build_box(&track.trak.tkhd));
-        if let Some(edts) = &track.trak.edts {
-            boxes.push(build_box(edts));
-            if let Some(elst) = &edts.elst {
-                boxes.push(build_box(elst));
-            }
-        }
-
-        // trak.mdia
-        let mdia = &track.trak.mdia;
-        boxes.push(build_box(mdia));
-        boxes.push(build_box(&mdia.mdhd));
-        boxes.push(build_box(&mdia.hdlr));
-        boxes.push(build_box(&track.trak.mdia.minf));
-
-        // trak.mdia.minf
-        let minf = &track.trak.mdia.minf;
-        if let Some(vmhd) = &minf.vmhd {
-            boxes.push(build_box(vmhd));
-        }
-        if let Some(smhd) = &minf.smhd {
-            boxes.push(build_box(smhd));
-        }
-
-        // trak.mdia.minf.stbl
-        let stbl = &track.trak.mdia.minf.stbl;
-        boxes.push(build_box(stbl));
-        boxes.push(build_box(&stbl.stsd));
-        if let Some(avc1) = &stbl.stsd.avc1 {
-            boxes.push(build_box(avc1));
-        }
-        if let Some(hev1) = &stbl.stsd.hev1 {
-            boxes.push(build_box(hev1));
-        }
-        if let Some(mp4a) = &stbl.stsd.mp4a {
-            boxes.push(build_box(mp4a));
-        }
-        boxes.push(build_box(&stbl.stts));
-        if let Some(ctts) = &stbl.ctts {
-            boxes.push(build_box(ctts));
-        }
-        if let Some(stss) = &stbl.stss {
-            boxes.push(build_box(stss));
-        }
-        boxes.push(build_box(&stbl.stsc));
-        boxes.push(build_box(&stbl.stsz));
-        if let Some(stco) = &stbl.stco {
-            boxes.push(build_box(stco));
-        }
-        if let Some(co64) = &stbl.co64 {
-            boxes.push(build_box(co64));
-        }
-    }
-
-    // If fragmented, add moof boxes.
-    for moof in mp4.moofs.iter() {
-        boxes.push(build_box(moof));
-        boxes.push(build_box(&moof.mfhd));
-        for traf in moof.trafs.iter() {
-            boxes.push(build_box(traf));
-            boxes.push(build_box(&traf.tfhd));
-            if let Some(trun) = &traf.trun {
-                boxes.push(build_box(trun));
-            }
-        }
-    }
-
-    Ok(boxes)
+    Ok(())
 }
 
-fn build_box<M: Mp4Box + std::fmt::Debug>(m: &M) -> Box {
-    Box {
-        name: m.box_type().to_string(),
-        byte_offset: m.byte_offset().unwrap_or(0),
-        size: m.box_size(),
-        summary: m.summary().unwrap(),
-        indent: 0,
-    }
+fn print_box(b: &BoxNode) {
+    let status = match &b.status {
+        BoxStatus::Parsed => String::new(),
+        BoxStatus::Unknown => " (not parsed)".to_string(),
+        BoxStatus::Invalid(err) => format!(" (invalid: {err})"),
+        BoxStatus::Truncated => " (truncated)".to_string(),
+    };
+    println!(
+        "{:indent$}[{}] offset={} size={}{}",
+        "",
+        b.box_type,
+        b.offset,
+        b.size,
+        status,
+        indent = b.depth * 4
+    );
 }
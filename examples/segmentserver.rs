@@ -1,3 +1,6 @@
+// The imports are for the routes of the server, which is not written yet.
+#![allow(unused_imports)]
+
 use std::{env, io};
 
 use axum::{extract::Path, response::IntoResponse, routing::get, Router};
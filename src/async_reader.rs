@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::meta::MetaBox;
+use crate::reader::build_tracks;
+use crate::*;
+
+/// Asynchronous counterpart of [Mp4Reader].
+///
+/// Boxes are read into memory one at a time and parsed with the same code
+/// as [Mp4Reader]; `mdat` is skipped over and samples are read on demand.
+#[derive(Debug)]
+pub struct AsyncMp4Reader<R> {
+    reader: R,
+    pub ftyp: FtypBox,
+    pub moov: MoovBox,
+    pub moofs: Vec<MoofBox>,
+    pub emsgs: Vec<EmsgBox>,
+    pub sidx: Vec<(SidxBox, usize)>, // (SidxBox, offset)
+    pub mfra: Option<MfraBox>,
+
+    segment_index: SegmentIndex,
+    tracks: HashMap<u32, Mp4Track>,
+    size: u64,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncMp4Reader<R> {
+    pub async fn read_header(mut reader: R, size: u64) -> Result<Self> {
+        let start = reader.stream_position().await?;
+
+        let mut ftyp = None;
+        let mut moov = None;
+        let mut moofs = Vec::new();
+        let mut moof_offsets = Vec::new();
+        let mut emsgs = Vec::new();
+        let mut sidx = Vec::new();
+        let mut mfra = None;
+
+        let mut current = start;
+        while current < size {
+            // Get box header.
+            let mut raw = [0u8; 16];
+            reader.read_exact(&mut raw[..8]).await?;
+            let header_len = if raw[0..4] == [0, 0, 0, 1] {
+                reader.read_exact(&mut raw[8..16]).await?;
+                16
+            } else {
+                8
+            };
+            let header = BoxHeader::read(&mut &raw[..header_len])?;
+            let Some(BoxHeader { name, size: s }) = top_level_header(header, size)? else {
+                break;
+            };
+
+            // Match and parse the atom boxes.
+            match name {
+                BoxType::FtypBox
+                | BoxType::MoovBox
+                | BoxType::MoofBox
+                | BoxType::EmsgBox
+                | BoxType::SidxBox
+                | BoxType::MfraBox => {
+                    let box_offset = current;
+                    let mut buf = raw[..header_len].to_vec();
+                    buf.resize(header_len + (s - HEADER_SIZE) as usize, 0);
+                    reader.read_exact(&mut buf[header_len..]).await?;
+                    let mut data = BufferedBox::new(box_offset, buf, header_len as u64);
+
+                    match name {
+                        BoxType::FtypBox => ftyp = Some(FtypBox::read_box(&mut data, s)?),
+                        BoxType::MoovBox => moov = Some(MoovBox::read_box(&mut data, s)?),
+                        BoxType::MoofBox => {
+                            moofs.push(MoofBox::read_box(&mut data, s)?);
+                            moof_offsets.push(box_offset);
+                        }
+                        BoxType::EmsgBox => emsgs.push(EmsgBox::read_box(&mut data, s)?),
+                        BoxType::MfraBox => mfra = Some(MfraBox::read_box(&mut data, s)?),
+                        _ => sidx.push((SidxBox::read_box(&mut data, s)?, box_offset as usize)),
+                    }
+                }
+                _ => {
+                    // XXX warn!()
+                    reader
+                        .seek(SeekFrom::Current((s - HEADER_SIZE) as i64))
+                        .await?;
+                }
+            }
+            current = reader.stream_position().await?;
+        }
+
+        let Some(ftyp) = ftyp else {
+            return Err(Error::BoxNotFound(BoxType::FtypBox));
+        };
+        let Some(moov) = moov else {
+            return Err(Error::BoxNotFound(BoxType::MoovBox));
+        };
+        if moov.traks.iter().any(|trak| trak.tkhd.track_id == 0) {
+            return Err(Error::InvalidData("illegal track id 0"));
+        }
+        let tracks = build_tracks(&moov, &moofs, &moof_offsets)?;
+        let segment_index = SegmentIndex::new(&sidx, &moof_offsets);
+
+        Ok(AsyncMp4Reader {
+            reader,
+            ftyp,
+            moov,
+            moofs,
+            emsgs,
+            sidx,
+            mfra,
+            segment_index,
+            tracks,
+            size: current - start,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn major_brand(&self) -> &FourCC {
+        &self.ftyp.major_brand
+    }
+
+    pub fn minor_version(&self) -> u32 {
+        self.ftyp.minor_version
+    }
+
+    pub fn compatible_brands(&self) -> &[FourCC] {
+        &self.ftyp.compatible_brands
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.moov.mvhd.duration * 1000 / self.moov.mvhd.timescale as u64)
+    }
+
+    pub fn timescale(&self) -> u32 {
+        self.moov.mvhd.timescale
+    }
+
+    pub fn is_fragmented(&self) -> bool {
+        !self.moofs.is_empty()
+    }
+
+    pub fn tracks(&self) -> &HashMap<u32, Mp4Track> {
+        &self.tracks
+    }
+
+    pub fn sample_count(&self, track_id: u32) -> Result<u32> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            track.sample_count()
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    pub async fn read_sample(
+        &mut self,
+        track_id: u32,
+        sample_id: u32,
+    ) -> Result<Option<Mp4Sample>> {
+        let Some(track) = self.tracks.get(&track_id) else {
+            return Err(Error::TrakNotFound(track_id));
+        };
+        let Some((sample_offset, sample_size, mut sample)) = track.sample_location(sample_id)?
+        else {
+            return Ok(None);
+        };
+
+        let mut buffer = vec![0x0u8; sample_size as usize];
+        self.reader.seek(SeekFrom::Start(sample_offset)).await?;
+        self.reader.read_exact(&mut buffer).await?;
+        sample.bytes = Bytes::from(buffer);
+
+        Ok(Some(sample))
+    }
+
+    pub fn sample_offset(&self, track_id: u32, sample_id: u32) -> Result<u64> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            track.sample_offset(sample_id)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// See [Mp4Reader::segment_index].
+    pub fn segment_index(&self) -> &SegmentIndex {
+        &self.segment_index
+    }
+
+    /// See [Mp4Reader::segment_at].
+    pub fn segment_at(&self, track_id: u32, time: Duration) -> Option<&Segment> {
+        self.segment_index.find(track_id, time)
+    }
+}
+
+impl<R> AsyncMp4Reader<R> {
+    pub fn metadata(&self) -> impl Metadata<'_> {
+        self.moov.udta.as_ref().and_then(|udta| {
+            udta.meta.as_ref().and_then(|meta| match meta {
+                MetaBox::Mdir { ilst, .. } => ilst.as_ref(),
+                _ => None,
+            })
+        })
+    }
+
+    /// The value of a QuickTime `mdta` key, such as
+    /// `com.apple.quicktime.model`.
+    pub fn quicktime_value(&self, key: &str) -> Option<DataValue<'_>> {
+        let meta = self.moov.quicktime_metadata()?;
+        meta.mdta_value(key).map(DataBox::value)
+    }
+
+    /// The recording location, from `com.apple.quicktime.location.ISO6709`.
+    pub fn location(&self) -> Option<Location> {
+        match self.quicktime_value(QUICKTIME_LOCATION)? {
+            DataValue::Text(text) => Location::from_iso6709(&text).ok(),
+            _ => None,
+        }
+    }
+
+    /// Consume self, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
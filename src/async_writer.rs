@@ -0,0 +1,232 @@
+use std::io::{self, Seek, SeekFrom, Write};
+use tokio::io::{AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+use crate::meta::MetaBox;
+use crate::mp4box::*;
+use crate::retag::set_moov_metadata;
+use crate::track::{ITUNES_MEAN, ITUNSMPB, Mp4TrackWriter};
+use crate::*;
+
+/// In-memory staging area for bytes bound for the async writer.
+///
+/// It reports positions relative to the underlying writer, so the
+/// synchronous track writer can record chunk offsets as usual.
+struct StagedWrites {
+    position: u64,
+    buf: Vec<u8>,
+}
+
+impl StagedWrites {
+    fn new(position: u64) -> Self {
+        Self {
+            position,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl Write for StagedWrites {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(&mut self.buf, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for StagedWrites {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let end = self.position + self.buf.len() as u64;
+        match pos {
+            SeekFrom::Current(0) => Ok(end),
+            SeekFrom::Start(offset) if offset == end => Ok(end),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "staged writes can only be appended",
+            )),
+        }
+    }
+}
+
+/// Asynchronous counterpart of [Mp4Writer].
+#[derive(Debug)]
+pub struct AsyncMp4Writer<W> {
+    writer: W,
+    tracks: Vec<Mp4TrackWriter>,
+    position: u64,
+    mdat_pos: u64,
+    timescale: u32,
+    duration: u64,
+    metadata: IlstBox,
+    quicktime_metadata: MetaBox,
+}
+
+impl<W> AsyncMp4Writer<W> {
+    /// Consume self, returning the inner writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> AsyncMp4Writer<W> {
+    pub async fn write_start(mut writer: W, config: &Mp4Config) -> Result<Self> {
+        let ftyp = FtypBox {
+            major_brand: config.major_brand,
+            minor_version: config.minor_version,
+            compatible_brands: config.compatible_brands.clone(),
+            ..Default::default()
+        };
+
+        let start = writer.stream_position().await?;
+        let mut staged = StagedWrites::new(start);
+        ftyp.write_box(&mut staged)?;
+
+        // TODO largesize
+        let mdat_pos = staged.stream_position()?;
+        BoxHeader::new(BoxType::MdatBox, HEADER_SIZE).write(&mut staged)?;
+        BoxHeader::new(BoxType::WideBox, HEADER_SIZE).write(&mut staged)?;
+        writer.write_all(&staged.buf).await?;
+
+        Ok(Self {
+            writer,
+            tracks: Vec::new(),
+            position: staged.stream_position()?,
+            mdat_pos,
+            timescale: config.timescale,
+            duration: 0,
+            metadata: IlstBox::default(),
+            quicktime_metadata: MetaBox::new_mdta(),
+        })
+    }
+
+    /// The metadata written to `moov/udta/meta/ilst` at the end, see
+    /// [MetadataMut].
+    pub fn metadata_mut(&mut self) -> &mut IlstBox {
+        &mut self.metadata
+    }
+
+    /// The QuickTime `mdta` metadata written to `moov/meta` at the end, if
+    /// it has any values.
+    pub fn quicktime_metadata_mut(&mut self) -> &mut MetaBox {
+        &mut self.quicktime_metadata
+    }
+
+    pub fn add_track(&mut self, config: &TrackConfig) -> Result<()> {
+        let track_id = self.tracks.len() as u32 + 1;
+        let track = Mp4TrackWriter::new(track_id, config)?;
+        self.tracks.push(track);
+        Ok(())
+    }
+
+    pub fn add_sample_description(&mut self, track_id: u32, config: &TrackConfig) -> Result<u32> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        if let Some(track) = self.tracks.get_mut(track_id as usize - 1) {
+            track.add_sample_description(config)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    pub fn set_edit_list(&mut self, track_id: u32, edits: &[Edit]) -> Result<()> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        if let Some(track) = self.tracks.get_mut(track_id as usize - 1) {
+            track.set_edit_list(edits)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// Record the gapless playback parameters of a track, both as an
+    /// `iTunSMPB` tag and as an edit list that skips the delay and padding.
+    pub fn set_gapless_info(&mut self, track_id: u32, info: &GaplessInfo) -> Result<()> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        let Some(track) = self.tracks.get_mut(track_id as usize - 1) else {
+            return Err(Error::TrakNotFound(track_id));
+        };
+        track.set_gapless_info(info)?;
+        self.metadata
+            .set_freeform_text(ITUNES_MEAN, ITUNSMPB, &info.to_itunsmpb());
+        Ok(())
+    }
+
+    pub async fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+
+        let mut staged = StagedWrites::new(self.position);
+        let track_dur = if let Some(track) = self.tracks.get_mut(track_id as usize - 1) {
+            track.write_sample(&mut staged, sample, self.timescale)?
+        } else {
+            return Err(Error::TrakNotFound(track_id));
+        };
+        self.write_staged(staged).await?;
+
+        if track_dur > self.duration {
+            self.duration = track_dur;
+        }
+
+        Ok(())
+    }
+
+    async fn write_staged(&mut self, staged: StagedWrites) -> Result<()> {
+        self.writer.write_all(&staged.buf).await?;
+        self.position += staged.buf.len() as u64;
+        Ok(())
+    }
+
+    async fn update_mdat_size(&mut self) -> Result<()> {
+        let mdat_end = self.position;
+        let mdat_size = mdat_end - self.mdat_pos;
+        self.writer.seek(SeekFrom::Start(self.mdat_pos)).await?;
+        if mdat_size > u32::MAX as u64 {
+            self.writer.write_u32(1).await?;
+            self.writer.seek(SeekFrom::Start(self.mdat_pos + 8)).await?;
+            self.writer.write_u64(mdat_size).await?;
+        } else {
+            self.writer.write_u32(mdat_size as u32).await?;
+        }
+        self.writer.seek(SeekFrom::Start(mdat_end)).await?;
+        Ok(())
+    }
+
+    pub async fn write_end(&mut self) -> Result<()> {
+        let mut moov = MoovBox::default();
+
+        let mut staged = StagedWrites::new(self.position);
+        for track in self.tracks.iter_mut() {
+            moov.traks
+                .push(track.write_end(&mut staged, self.timescale)?);
+        }
+        self.write_staged(staged).await?;
+        self.update_mdat_size().await?;
+
+        self.duration = moov
+            .traks
+            .iter()
+            .map(|trak| trak.tkhd.duration)
+            .max()
+            .unwrap_or(0);
+        moov.mvhd.timescale = self.timescale;
+        moov.mvhd.duration = self.duration;
+        if moov.mvhd.duration > (u32::MAX as u64) {
+            moov.mvhd.version = 1
+        }
+        set_moov_metadata(&mut moov, &self.metadata)?;
+        if !self.quicktime_metadata.mdta_entries().is_empty() {
+            moov.meta = Some(self.quicktime_metadata.clone());
+        }
+        let mut staged = StagedWrites::new(self.position);
+        moov.write_box(&mut staged)?;
+        self.write_staged(staged).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::*;
+
+/// How far the crate got with a box found by [read_box_tree].
+#[derive(Debug)]
+pub enum BoxStatus {
+    /// The box is modelled by the crate and its contents parsed, or it is a
+    /// container whose children were walked.
+    Parsed,
+
+    /// The crate has no parser for this box, so its payload was skipped.
+    /// Child boxes of a known layout are still walked.
+    Unknown,
+
+    /// The box is modelled by the crate but its contents failed to parse.
+    Invalid(Error),
+
+    /// The box extends past the end of its parent or of the file. Children
+    /// are walked up to where the data ends.
+    Truncated,
+}
+
+/// A box and its position in the file, as found by [read_box_tree].
+#[derive(Debug)]
+pub struct BoxNode {
+    pub box_type: BoxType,
+
+    /// Offset of the first byte of the box header.
+    pub offset: u64,
+
+    /// Size of the header, including a largesize.
+    pub header_size: u64,
+
+    /// Size of the whole box as declared in its header. A box whose size is
+    /// zero extends to the end of its parent, and is given that size here.
+    pub size: u64,
+
+    /// Nesting level, zero for top-level boxes.
+    pub depth: usize,
+    pub status: BoxStatus,
+    pub children: Vec<BoxNode>,
+}
+
+impl BoxNode {
+    /// Offset of the first byte after the header.
+    pub fn payload_offset(&self) -> u64 {
+        self.offset + self.header_size
+    }
+
+    pub fn payload_size(&self) -> u64 {
+        self.size.saturating_sub(self.header_size)
+    }
+
+    /// Offset of the first byte after the box.
+    pub fn end(&self) -> u64 {
+        self.offset + self.size
+    }
+
+    /// Find a box below this one by a path of box types separated by `/`,
+    /// such as `mdia/minf/stbl`. The first match is taken at each level.
+    pub fn find(&self, path: &str) -> Option<&BoxNode> {
+        find_box(&self.children, path)
+    }
+
+    /// Visit this box and all the boxes below it in file order.
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a BoxNode)) {
+        visit(self);
+        for child in self.children.iter() {
+            child.walk(visit);
+        }
+    }
+}
+
+/// Walk every box from the current position of `reader` up to `size`,
+/// including boxes the crate doesn't model.
+///
+/// Known containers are descended into, and boxes the crate models are run
+/// through their parsers to determine their [BoxStatus]. Walking stops at a
+/// header that can't be valid, since the boxes after it can't be found.
+pub fn read_box_tree<R: Read + Seek>(reader: &mut R, size: u64) -> Result<Vec<BoxNode>> {
+    let start = reader.stream_position()?;
+    read_boxes(reader, start, size, 0, None)
+}
+
+/// Find a box in a tree returned by [read_box_tree] by a path of box types
+/// separated by `/`, such as `moov/trak/tkhd`. The first match is taken at
+/// each level.
+pub fn find_box<'a>(nodes: &'a [BoxNode], path: &str) -> Option<&'a BoxNode> {
+    let mut nodes = nodes;
+    let mut found = None;
+    for name in path.split('/') {
+        let node = nodes
+            .iter()
+            .find(|node| node.box_type.to_string() == name)?;
+        nodes = &node.children;
+        found = Some(node);
+    }
+    found
+}
+
+fn read_boxes<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    depth: usize,
+    parent: Option<BoxType>,
+) -> Result<Vec<BoxNode>> {
+    let mut nodes = Vec::new();
+    let mut current = start;
+    while current + HEADER_SIZE <= end {
+        reader.seek(SeekFrom::Start(current))?;
+        let mut raw = [0u8; 8];
+        reader.read_exact(&mut raw)?;
+        let box_type = BoxType::from(u32::from_be_bytes(raw[4..8].try_into().unwrap()));
+        let mut header_size = HEADER_SIZE;
+        let mut size = u32::from_be_bytes(raw[0..4].try_into().unwrap()) as u64;
+        if size == 1 {
+            if current + 16 > end {
+                break;
+            }
+            reader.read_exact(&mut raw)?;
+            header_size += 8;
+            size = u64::from_be_bytes(raw);
+        }
+        if size == 0 {
+            size = end - current;
+        }
+
+        let mut node = BoxNode {
+            box_type,
+            offset: current,
+            header_size,
+            size,
+            depth,
+            status: BoxStatus::Parsed,
+            children: Vec::new(),
+        };
+        if size < header_size {
+            node.status = BoxStatus::Invalid(Error::InvalidData("box size too small"));
+            nodes.push(node);
+            break;
+        }
+
+        let box_end = if current + size > end {
+            node.status = BoxStatus::Truncated;
+            end
+        } else {
+            current + size
+        };
+        let payload = current + header_size;
+        if let Some(skip) = children_offset(reader, box_type, parent, payload, box_end)? {
+            node.children = read_boxes(reader, payload + skip, box_end, depth + 1, Some(box_type))?;
+        }
+        if box_end == current + size {
+            // The parsers take the size the way BoxHeader reports it, less
+            // the largesize.
+            reader.seek(SeekFrom::Start(payload))?;
+            match parse_box(reader, box_type, size - (header_size - HEADER_SIZE)) {
+                Some(Ok(())) => {}
+                Some(Err(err)) => node.status = BoxStatus::Invalid(err),
+                None if !is_container(box_type, parent) => node.status = BoxStatus::Unknown,
+                None => {}
+            }
+        }
+
+        nodes.push(node);
+        current = box_end;
+    }
+    Ok(nodes)
+}
+
+fn is_container(box_type: BoxType, parent: Option<BoxType>) -> bool {
+    matches!(
+        box_type,
+        BoxType::MoovBox
+            | BoxType::TrakBox
+            | BoxType::EdtsBox
+            | BoxType::MdiaBox
+            | BoxType::MinfBox
+            | BoxType::DinfBox
+            | BoxType::StblBox
+            | BoxType::MvexBox
+            | BoxType::MoofBox
+            | BoxType::TrafBox
+            | BoxType::UdtaBox
+            | BoxType::MetaBox
+            | BoxType::IlstBox
+            | BoxType::MfraBox
+    ) || parent == Some(BoxType::IlstBox)
+}
+
+/// Return how far into the payload of a box its child boxes start, or `None`
+/// if it has no child boxes.
+fn children_offset<R: Read + Seek>(
+    reader: &mut R,
+    box_type: BoxType,
+    parent: Option<BoxType>,
+    payload: u64,
+    end: u64,
+) -> Result<Option<u64>> {
+    if is_container(box_type, parent) {
+        if box_type != BoxType::MetaBox {
+            return Ok(Some(0));
+        }
+        // QuickTime leaves out the version and flags of meta.
+        if payload + 8 <= end {
+            let mut raw = [0u8; 8];
+            reader.seek(SeekFrom::Start(payload))?;
+            reader.read_exact(&mut raw)?;
+            if &raw[4..8] == b"hdlr" {
+                return Ok(Some(0));
+            }
+        }
+        return Ok(Some(HEADER_EXT_SIZE));
+    }
+
+    let offset = match &u32::from(box_type).to_be_bytes() {
+        // Version and flags, then the entry count.
+        b"stsd" | b"dref" => 8,
+        // Visual sample entries.
+        b"avc1" | b"avc3" | b"hev1" | b"hvc1" | b"vp09" | b"av01" => 78,
+        b"tx3g" => 38,
+        b"mp4a" => {
+            // Sound sample entry version, as extended by QuickTime.
+            let mut raw = [0u8; 2];
+            if payload + 10 > end {
+                return Ok(None);
+            }
+            reader.seek(SeekFrom::Start(payload + 8))?;
+            reader.read_exact(&mut raw)?;
+            match u16::from_be_bytes(raw) {
+                1 => 28 + 16,
+                2 => 28 + 36,
+                _ => 28,
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok((payload + offset <= end).then_some(offset))
+}
+
+/// Run the parser of a box the crate models, with `reader` positioned just
+/// past its header.
+fn parse_box<R: Read + Seek>(reader: &mut R, box_type: BoxType, size: u64) -> Option<Result<()>> {
+    let result = match box_type {
+        BoxType::FtypBox => FtypBox::read_box(reader, size).map(drop),
+        BoxType::MvhdBox => MvhdBox::read_box(reader, size).map(drop),
+        BoxType::MfhdBox => MfhdBox::read_box(reader, size).map(drop),
+        BoxType::MehdBox => MehdBox::read_box(reader, size).map(drop),
+        BoxType::TrexBox => TrexBox::read_box(reader, size).map(drop),
+        BoxType::EmsgBox => EmsgBox::read_box(reader, size).map(drop),
+        BoxType::SidxBox => SidxBox::read_box(reader, size).map(drop),
+        BoxType::TkhdBox => TkhdBox::read_box(reader, size).map(drop),
+        BoxType::TfhdBox => TfhdBox::read_box(reader, size).map(drop),
+        BoxType::TfdtBox => TfdtBox::read_box(reader, size).map(drop),
+        BoxType::TrunBox => TrunBox::read_box(reader, size).map(drop),
+        BoxType::TfraBox => TfraBox::read_box(reader, size).map(drop),
+        BoxType::MfroBox => MfroBox::read_box(reader, size).map(drop),
+        BoxType::ElstBox => ElstBox::read_box(reader, size).map(drop),
+        BoxType::MdhdBox => MdhdBox::read_box(reader, size).map(drop),
+        BoxType::HdlrBox => HdlrBox::read_box(reader, size).map(drop),
+        BoxType::VmhdBox => VmhdBox::read_box(reader, size).map(drop),
+        BoxType::SmhdBox => SmhdBox::read_box(reader, size).map(drop),
+        BoxType::DrefBox => dinf::DrefBox::read_box(reader, size).map(drop),
+        BoxType::UrlBox => dinf::UrlBox::read_box(reader, size).map(drop),
+        BoxType::StsdBox => StsdBox::read_box(reader, size).map(drop),
+        BoxType::SttsBox => SttsBox::read_box(reader, size).map(drop),
+        BoxType::CttsBox => CttsBox::read_box(reader, size).map(drop),
+        BoxType::StssBox => StssBox::read_box(reader, size).map(drop),
+        BoxType::SgpdBox => SgpdBox::read_box(reader, size).map(drop),
+        BoxType::StscBox => StscBox::read_box(reader, size).map(drop),
+        BoxType::StszBox => StszBox::read_box(reader, size).map(drop),
+        BoxType::StcoBox => StcoBox::read_box(reader, size).map(drop),
+        BoxType::Co64Box => Co64Box::read_box(reader, size).map(drop),
+        BoxType::Avc1Box => Avc1Box::read_box(reader, size).map(drop),
+        BoxType::AvcCBox => avc1::AvcCBox::read_box(reader, size).map(drop),
+        BoxType::Hev1Box => Hev1Box::read_box(reader, size).map(drop),
+        BoxType::HvcCBox => hev1::HvcCBox::read_box(reader, size).map(drop),
+        BoxType::Vp09Box => Vp09Box::read_box(reader, size).map(drop),
+        BoxType::VpccBox => VpccBox::read_box(reader, size).map(drop),
+        BoxType::Mp4aBox => Mp4aBox::read_box(reader, size).map(drop),
+        BoxType::EsdsBox => mp4a::EsdsBox::read_box(reader, size).map(drop),
+        BoxType::Tx3gBox => Tx3gBox::read_box(reader, size).map(drop),
+        BoxType::DataBox => DataBox::read_box(reader, size).map(drop),
+        BoxType::KeysBox => KeysBox::read_box(reader, size).map(drop),
+        _ => return None,
+    };
+    Some(result)
+}
@@ -0,0 +1,412 @@
+use bytes::BytesMut;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::mp4box::*;
+use crate::track::{add_sample_description, new_trak};
+use crate::*;
+
+// sample_depends_on = 2 (does not depend on others).
+const SYNC_SAMPLE_FLAGS: u32 = 0x0200_0000;
+// sample_depends_on = 1 (depends on others), sample_is_non_sync_sample = 1.
+const NON_SYNC_SAMPLE_FLAGS: u32 = 0x0101_0000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FragmentConfig {
+    pub major_brand: FourCC,
+    pub minor_version: u32,
+    pub compatible_brands: Vec<FourCC>,
+    pub timescale: u32,
+
+    /// Minimum duration of a fragment. Once the leading track (the first video
+    /// track, or the first track if there is no video) has buffered this much
+    /// media, the fragment is cut before its next sync sample.
+    pub fragment_duration: Duration,
+}
+
+#[derive(Debug)]
+struct FragmentTrackWriter {
+    trak: TrakBox,
+    fragment_duration: u64,
+    base_media_decode_time: u64,
+    buffered_duration: u64,
+    trun: TrunBox,
+    data: BytesMut,
+
+    // A traf only ever holds samples of a single description.
+    sample_description_index: u32,
+
+    // Decode time and 1-based trun position of the first sync sample buffered.
+    first_sync_sample: Option<(u64, u32)>,
+    random_access_points: Vec<TfraEntry>,
+}
+
+impl FragmentTrackWriter {
+    fn new(track_id: u32, config: &TrackConfig, fragment_duration: Duration) -> Self {
+        let fragment_duration =
+            fragment_duration.as_nanos() * config.timescale as u128 / 1_000_000_000;
+        FragmentTrackWriter {
+            trak: new_trak(track_id, config),
+            fragment_duration: fragment_duration as u64,
+            base_media_decode_time: 0,
+            buffered_duration: 0,
+            trun: TrunBox::default(),
+            data: BytesMut::new(),
+            sample_description_index: 1,
+            first_sync_sample: None,
+            random_access_points: Vec::new(),
+        }
+    }
+
+    fn track_id(&self) -> u32 {
+        self.trak.tkhd.track_id
+    }
+
+    fn is_fragment_full(&self) -> bool {
+        self.buffered_duration >= self.fragment_duration
+    }
+
+    fn add_sample(&mut self, sample: &Mp4Sample) {
+        let flags = match sample.flags {
+            Some(flags) => flags.to_bits(),
+            None if sample.is_sync => SYNC_SAMPLE_FLAGS,
+            None => NON_SYNC_SAMPLE_FLAGS,
+        };
+        if sample.is_sync && self.first_sync_sample.is_none() {
+            let time = self.base_media_decode_time + self.buffered_duration;
+            self.first_sync_sample = Some((time, self.trun.sample_count + 1));
+        }
+        self.sample_description_index = sample.sample_description_index;
+        self.trun.sample_count += 1;
+        self.trun.sample_durations.push(sample.duration);
+        self.trun.sample_sizes.push(sample.bytes.len() as u32);
+        self.trun.sample_flags.push(flags);
+        self.trun.sample_cts.push(sample.rendering_offset as u32);
+        self.buffered_duration += sample.duration as u64;
+        self.data.extend_from_slice(&sample.bytes);
+    }
+
+    fn traf(&self) -> TrafBox {
+        let mut trun = self.trun.clone();
+        trun.flags = TrunBox::FLAG_DATA_OFFSET
+            | TrunBox::FLAG_SAMPLE_DURATION
+            | TrunBox::FLAG_SAMPLE_SIZE
+            | TrunBox::FLAG_SAMPLE_FLAGS;
+        if trun.sample_cts.iter().any(|cts| *cts != 0) {
+            trun.flags |= TrunBox::FLAG_SAMPLE_CTS;
+            // Negative composition offsets need a version 1 trun.
+            if trun.sample_cts.iter().any(|cts| (*cts as i32) < 0) {
+                trun.version = 1;
+            }
+        } else {
+            trun.sample_cts.clear();
+        }
+        trun.data_offset = Some(0);
+
+        TrafBox {
+            tfhd: TfhdBox {
+                flags: TfhdBox::FLAG_SAMPLE_DESCRIPTION_INDEX | TfhdBox::FLAG_DEFAULT_BASE_IS_MOOF,
+                track_id: self.track_id(),
+                sample_description_index: Some(self.sample_description_index),
+                ..Default::default()
+            },
+            tfdt: Some(TfdtBox {
+                version: 1,
+                flags: 0,
+                base_media_decode_time: self.base_media_decode_time,
+                ..Default::default()
+            }),
+            truns: vec![trun],
+            ..Default::default()
+        }
+    }
+
+    fn end_fragment(&mut self, moof_offset: u64, traf_number: u32) {
+        if let Some((time, sample_number)) = self.first_sync_sample.take() {
+            self.random_access_points.push(TfraEntry {
+                time,
+                moof_offset,
+                traf_number,
+                trun_number: 1,
+                sample_number,
+            });
+        }
+        self.base_media_decode_time += self.buffered_duration;
+        self.buffered_duration = 0;
+        self.trun = TrunBox::default();
+        self.data.clear();
+    }
+}
+
+/// Writer for fragmented MP4 (fMP4/CMAF) output.
+///
+/// An init segment (`ftyp` + `moov` with `mvex`) is written before the first
+/// sample, followed by `moof` + `mdat` pairs, and an `mfra` pointing at the
+/// first sync sample of each track in every fragment. Unlike [Mp4Writer], the
+/// output never needs to be rewound, so any [Write] destination can be used.
+#[derive(Debug)]
+pub struct Mp4FragmentWriter<W> {
+    writer: W,
+    ftyp: FtypBox,
+    timescale: u32,
+    fragment_duration: Duration,
+    tracks: Vec<FragmentTrackWriter>,
+    leading_track: usize,
+    sequence_number: u32,
+    is_init_written: bool,
+    is_end_written: bool,
+
+    // Number of bytes written so far.
+    position: u64,
+}
+
+impl<W> Mp4FragmentWriter<W> {
+    /// Consume self, returning the inner writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Mp4FragmentWriter<W> {
+    /// Start a fragmented file. Nothing is written until the init segment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mp4::{FragmentConfig, Mp4FragmentWriter};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> mp4::Result<()> {
+    /// let config = FragmentConfig {
+    ///     major_brand: str::parse("iso6").unwrap(),
+    ///     minor_version: 0,
+    ///     compatible_brands: vec![str::parse("iso6").unwrap(), str::parse("cmfc").unwrap()],
+    ///     timescale: 1000,
+    ///     fragment_duration: Duration::from_secs(2),
+    /// };
+    ///
+    /// let mut writer = Mp4FragmentWriter::write_start(Vec::new(), &config)?;
+    /// writer.write_end()?;
+    ///
+    /// let data: Vec<u8> = writer.into_writer();
+    /// # Ok(()) }
+    /// ```
+    pub fn write_start(writer: W, config: &FragmentConfig) -> Result<Self> {
+        let ftyp = FtypBox {
+            major_brand: config.major_brand,
+            minor_version: config.minor_version,
+            compatible_brands: config.compatible_brands.clone(),
+            ..Default::default()
+        };
+        Ok(Self {
+            writer,
+            ftyp,
+            timescale: config.timescale,
+            fragment_duration: config.fragment_duration,
+            tracks: Vec::new(),
+            leading_track: 0,
+            sequence_number: 1,
+            is_init_written: false,
+            is_end_written: false,
+            position: 0,
+        })
+    }
+
+    pub fn add_track(&mut self, config: &TrackConfig) -> Result<()> {
+        if self.is_init_written {
+            return Err(Error::InvalidData(
+                "tracks must be added before the init segment is written",
+            ));
+        }
+        let track_id = self.tracks.len() as u32 + 1;
+        let track = FragmentTrackWriter::new(track_id, config, self.fragment_duration);
+        self.tracks.push(track);
+        Ok(())
+    }
+
+    /// Add a sample description to a track, such as a new resolution or
+    /// codec configuration. Samples written with the returned
+    /// `sample_description_index` use it.
+    ///
+    /// Like tracks, sample descriptions must be added before the init segment
+    /// is written. A fragment holds a single description per track, so a
+    /// sample that switches to another one starts a new fragment.
+    pub fn add_sample_description(&mut self, track_id: u32, config: &TrackConfig) -> Result<u32> {
+        if self.is_init_written {
+            return Err(Error::InvalidData(
+                "sample descriptions must be added before the init segment is written",
+            ));
+        }
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        if let Some(track) = self.tracks.get_mut(track_id as usize - 1) {
+            add_sample_description(&mut track.trak, config)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// Write the init segment (`ftyp` + `moov`).
+    ///
+    /// This is done automatically before the first sample if it was not
+    /// called explicitly.
+    pub fn write_init_segment(&mut self) -> Result<()> {
+        if self.is_init_written {
+            return Ok(());
+        }
+
+        let mut moov = MoovBox::default();
+        moov.mvhd.timescale = self.timescale;
+        moov.mvhd.next_track_id = self.tracks.len() as u32 + 1;
+        for track in self.tracks.iter() {
+            let mut trak = track.trak.clone();
+            trak.mdia.minf.stbl.stco = Some(StcoBox::default());
+            moov.traks.push(trak);
+        }
+
+        let trexs = moov
+            .traks
+            .iter()
+            .map(|trak| TrexBox {
+                track_id: trak.tkhd.track_id,
+                default_sample_description_index: 1,
+                ..Default::default()
+            })
+            .collect();
+        moov.mvex = Some(MvexBox {
+            trexs,
+            ..Default::default()
+        });
+
+        self.ftyp.write_box(&mut self.writer)?;
+        moov.write_box(&mut self.writer)?;
+        self.position += self.ftyp.box_size() + moov.box_size();
+
+        self.leading_track = self
+            .tracks
+            .iter()
+            .position(|track| track.trak.mdia.minf.vmhd.is_some())
+            .unwrap_or(0);
+        self.is_init_written = true;
+        Ok(())
+    }
+
+    pub fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        if self.is_end_written {
+            return Err(Error::InvalidData(
+                "samples must be written before the end is written",
+            ));
+        }
+        if track_id == 0 || track_id as usize > self.tracks.len() {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        let index = track_id as usize - 1;
+        let track = &self.tracks[index];
+        if track
+            .trak
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .entry(sample.sample_description_index)
+            .is_none()
+        {
+            return Err(Error::InvalidData("sample description index out of range"));
+        }
+        let is_description_changed = track.trun.sample_count > 0
+            && sample.sample_description_index != track.sample_description_index;
+        self.write_init_segment()?;
+
+        let track = &self.tracks[index];
+        if is_description_changed
+            || index == self.leading_track && sample.is_sync && track.is_fragment_full()
+        {
+            self.write_fragment()?;
+        }
+        self.tracks[index].add_sample(sample);
+
+        Ok(())
+    }
+
+    /// Write out all buffered samples as a fragment, regardless of its duration.
+    pub fn write_fragment(&mut self) -> Result<()> {
+        let tracks: Vec<&FragmentTrackWriter> = self
+            .tracks
+            .iter()
+            .filter(|track| track.trun.sample_count > 0)
+            .collect();
+        let moof_offset = self.position;
+        if tracks.is_empty() {
+            return Ok(());
+        }
+
+        let mut moof = MoofBox {
+            mfhd: MfhdBox {
+                sequence_number: self.sequence_number,
+                ..Default::default()
+            },
+            trafs: tracks.iter().map(|track| track.traf()).collect(),
+            ..Default::default()
+        };
+
+        let data_size: u64 = tracks.iter().map(|track| track.data.len() as u64).sum();
+        let mdat_header_size = if data_size + HEADER_SIZE > u32::MAX as u64 {
+            HEADER_SIZE + 8
+        } else {
+            HEADER_SIZE
+        };
+
+        // Sample data offsets are relative to the start of the moof.
+        let mut data_offset = moof.box_size() + mdat_header_size;
+        for (traf, track) in moof.trafs.iter_mut().zip(tracks.iter()) {
+            let offset = i32::try_from(data_offset)
+                .map_err(|_| Error::InvalidData("fragment data offset exceeds trun range"))?;
+            traf.truns[0].data_offset = Some(offset);
+            data_offset += track.data.len() as u64;
+        }
+
+        moof.write_box(&mut self.writer)?;
+        BoxHeader::new(BoxType::MdatBox, mdat_header_size + data_size).write(&mut self.writer)?;
+        for track in tracks {
+            self.writer.write_all(&track.data)?;
+        }
+        self.position += moof.box_size() + mdat_header_size + data_size;
+
+        let mut traf_number = 0;
+        for track in self.tracks.iter_mut() {
+            if track.trun.sample_count > 0 {
+                traf_number += 1;
+            }
+            track.end_fragment(moof_offset, traf_number);
+        }
+        self.sequence_number += 1;
+        Ok(())
+    }
+
+    /// Write out the remaining samples, followed by the `mfra`. Calling this
+    /// again does nothing.
+    pub fn write_end(&mut self) -> Result<()> {
+        if self.is_end_written {
+            return Ok(());
+        }
+        self.write_init_segment()?;
+        self.write_fragment()?;
+
+        let mfra = MfraBox {
+            tfras: self
+                .tracks
+                .iter()
+                .map(|track| TfraBox::new(track.track_id(), track.random_access_points.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        mfra.write_box(&mut self.writer)?;
+        self.position += mfra.box_size();
+        self.is_end_written = true;
+
+        self.writer.flush()?;
+        Ok(())
+    }
+}
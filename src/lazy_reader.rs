@@ -0,0 +1,390 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::reader::build_tracks;
+use crate::*;
+
+/// Location of a `moof` found by [Mp4LazyReader].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FragmentInfo {
+    pub moof_offset: u64,
+
+    // Decode time of the first sample of each track in the fragment, once it
+    // has been parsed.
+    start_times: Option<Vec<(u32, u64)>>,
+
+    // (track_id, time) of the tracks whose start the sidx or mfra give, in
+    // the track timescale. These are the times of a sync sample or the
+    // earliest presentation time, so they can be later than the decode time
+    // of the first sample.
+    estimated_times: Vec<(u32, u64)>,
+}
+
+impl FragmentInfo {
+    fn new(moof_offset: u64) -> Self {
+        FragmentInfo {
+            moof_offset,
+            start_times: None,
+            estimated_times: Vec::new(),
+        }
+    }
+
+    /// Best known start time of `track_id`, or `None` if the fragment has to
+    /// be parsed to find out. `Some(None)` means it has no samples of it.
+    fn start_time(&self, track_id: u32) -> Option<Option<u64>> {
+        let times = self.start_times.as_ref().unwrap_or(&self.estimated_times);
+        let start_time = times.iter().find(|(id, _)| *id == track_id);
+        match start_time {
+            Some((_, time)) => Some(Some(*time)),
+            None if self.start_times.is_some() => Some(None),
+            None => None,
+        }
+    }
+
+    fn estimate(&mut self, track_id: u32, time: u64) {
+        match self
+            .estimated_times
+            .iter_mut()
+            .find(|(id, _)| *id == track_id)
+        {
+            Some((_, estimate)) => *estimate = time.min(*estimate),
+            None => self.estimated_times.push((track_id, time)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LoadedFragment {
+    index: usize,
+    tracks: HashMap<u32, Mp4Track>,
+}
+
+/// Reader for large fragmented files that parses each `moof` only when its
+/// samples are needed.
+///
+/// [Mp4LazyReader::read_header] only records where the top-level boxes are,
+/// along with `ftyp`, `moov` and any `sidx`. The `sidx` and an `mfra` found
+/// through a trailing `mfro` give the start times of some fragments, so fewer
+/// have to be parsed to find the others. Fragments are found by decode time,
+/// which relies on every `traf` having a `tfdt`. At most
+/// `max_cached_fragments` parsed fragments are kept, least recently used
+/// first out.
+#[derive(Debug)]
+pub struct Mp4LazyReader<R> {
+    reader: R,
+    pub ftyp: FtypBox,
+    pub moov: MoovBox,
+    pub sidx: Vec<(SidxBox, usize)>, // (SidxBox, offset)
+    pub mfra: Option<MfraBox>,
+
+    fragments: Vec<FragmentInfo>,
+    cache: VecDeque<LoadedFragment>,
+    max_cached_fragments: usize,
+    size: u64,
+}
+
+impl<R: Read + Seek> Mp4LazyReader<R> {
+    pub fn read_header(mut reader: R, size: u64, max_cached_fragments: usize) -> Result<Self> {
+        let start = reader.stream_position()?;
+
+        // A broken mfra only costs the start times it gives.
+        let mfra = match MfraBox::read_from_end(&mut reader, size) {
+            Err(Error::IoError(err)) => return Err(Error::IoError(err)),
+            mfra => mfra.unwrap_or(None),
+        };
+        reader.seek(SeekFrom::Start(start))?;
+
+        let mut ftyp = None;
+        let mut moov = None;
+        let mut sidx = Vec::new();
+        let mut fragments = Vec::new();
+
+        let current = read_top_level_boxes(
+            &mut reader,
+            size,
+            &mut ReadContext::default(),
+            |reader, header, offset, _| {
+                // Match and parse the atom boxes.
+                let BoxHeader { name, size: s } = header;
+                match name {
+                    BoxType::FtypBox => {
+                        ftyp = Some(FtypBox::read_box(reader, s)?);
+                    }
+                    BoxType::MoovBox => {
+                        moov = Some(MoovBox::read_box(reader, s)?);
+                    }
+                    BoxType::SidxBox => {
+                        let s = SidxBox::read_box(reader, s)?;
+                        sidx.push((s, offset as usize));
+                    }
+                    BoxType::MoofBox => {
+                        fragments.push(FragmentInfo::new(offset));
+                    }
+                    _ => {}
+                }
+                Ok(true)
+            },
+        )?;
+
+        let Some(ftyp) = ftyp else {
+            return Err(Error::BoxNotFound(BoxType::FtypBox));
+        };
+        let Some(moov) = moov else {
+            return Err(Error::BoxNotFound(BoxType::MoovBox));
+        };
+        if moov.traks.iter().any(|trak| trak.tkhd.track_id == 0) {
+            return Err(Error::InvalidData("illegal track id 0"));
+        }
+
+        let moof_offsets: Vec<u64> = fragments.iter().map(|f| f.moof_offset).collect();
+        for segment in SegmentIndex::new(&sidx, &moof_offsets).segments() {
+            let Some(trak) = moov
+                .traks
+                .iter()
+                .find(|trak| trak.tkhd.track_id == segment.track_id)
+            else {
+                continue;
+            };
+            if let Some(fragment) = fragments.get_mut(segment.moofs.start)
+                && !segment.moofs.is_empty()
+            {
+                let time = segment.start_time as u128 * trak.mdia.mdhd.timescale as u128
+                    / segment.timescale.max(1) as u128;
+                fragment.estimate(segment.track_id, time as u64);
+            }
+        }
+        // tfra only lists the fragments with a random access point.
+        for tfra in mfra.iter().flat_map(|mfra| mfra.tfras.iter()) {
+            for entry in tfra.entries.iter() {
+                if let Ok(index) =
+                    fragments.binary_search_by_key(&entry.moof_offset, |f| f.moof_offset)
+                {
+                    fragments[index].estimate(tfra.track_id, entry.time);
+                }
+            }
+        }
+
+        Ok(Mp4LazyReader {
+            reader,
+            ftyp,
+            moov,
+            sidx,
+            mfra,
+            fragments,
+            cache: VecDeque::new(),
+            max_cached_fragments: max_cached_fragments.max(1),
+            size: current - start,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn timescale(&self) -> u32 {
+        self.moov.mvhd.timescale
+    }
+
+    /// The fragments in file order.
+    pub fn fragments(&self) -> &[FragmentInfo] {
+        &self.fragments
+    }
+
+    /// Number of parsed fragments currently held.
+    pub fn cached_fragments(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Parse the `moof` of a fragment, returning it. Its samples are then
+    /// available until it drops out of the cache.
+    pub fn read_fragment(&mut self, index: usize) -> Result<MoofBox> {
+        let moof_offset = self.fragment_info(index)?.moof_offset;
+        self.reader.seek(SeekFrom::Start(moof_offset))?;
+        let header = BoxHeader::read(&mut self.reader)?;
+        if header.name != BoxType::MoofBox {
+            return Err(Error::BoxNotFound(BoxType::MoofBox));
+        }
+        let moof = MoofBox::read_box(&mut self.reader, header.size)?;
+
+        let tracks = build_tracks(&self.moov, std::slice::from_ref(&moof), &[moof_offset])?;
+        let mut start_times = Vec::new();
+        for track in tracks.values() {
+            if track.sample_count()? == 0 {
+                continue;
+            }
+            if let Some((_, _, sample)) = track.sample_location(1)? {
+                start_times.push((track.track_id(), sample.start_time));
+            }
+        }
+        self.fragments[index].start_times = Some(start_times);
+
+        self.cache.retain(|fragment| fragment.index != index);
+        if self.cache.len() == self.max_cached_fragments {
+            self.cache.pop_front();
+        }
+        self.cache.push_back(LoadedFragment { index, tracks });
+        Ok(moof)
+    }
+
+    /// Index of the fragment holding the sample of `track_id` that is being
+    /// decoded at `timestamp`, in the track timescale.
+    pub fn find_fragment(&mut self, track_id: u32, timestamp: u64) -> Result<Option<usize>> {
+        let Some(index) = self.fragment_before(track_id, timestamp)? else {
+            return Ok(None);
+        };
+        self.load(index)?;
+        let track = &self.cache.back().unwrap().tracks[&track_id];
+        let count = track.sample_count()?;
+        let end = match track.sample_location(count)? {
+            Some((_, _, sample)) => sample.start_time + sample.duration as u64,
+            None => return Ok(None),
+        };
+        Ok((timestamp < end).then_some(index))
+    }
+
+    /// Read the samples of `track_id` whose decode time falls in
+    /// `start..end`, in the track timescale, parsing only the fragments that
+    /// hold them.
+    pub fn read_samples(&mut self, track_id: u32, start: u64, end: u64) -> Result<Vec<Mp4Sample>> {
+        if !self
+            .moov
+            .traks
+            .iter()
+            .any(|trak| trak.tkhd.track_id == track_id)
+        {
+            return Err(Error::TrakNotFound(track_id));
+        }
+
+        let mut samples = Vec::new();
+        let first = self.fragment_before(track_id, start)?.unwrap_or(0);
+        for index in first..self.fragments.len() {
+            let info = &self.fragments[index];
+            if info.start_times.is_some()
+                && matches!(info.start_time(track_id), Some(Some(start_time)) if start_time >= end)
+            {
+                break;
+            }
+            self.load(index)?;
+            let fragment = self.cache.back().unwrap();
+            let Some(track) = fragment.tracks.get(&track_id) else {
+                continue;
+            };
+            for sample_id in 1..=track.sample_count()? {
+                let Some(sample) = track.read_sample(&mut self.reader, sample_id)? else {
+                    break;
+                };
+                if sample.start_time >= end {
+                    return Ok(samples);
+                }
+                if sample.start_time >= start {
+                    samples.push(sample);
+                }
+            }
+        }
+        Ok(samples)
+    }
+
+    fn fragment_info(&self, index: usize) -> Result<&FragmentInfo> {
+        self.fragments
+            .get(index)
+            .ok_or(Error::InvalidData("fragment index out of range"))
+    }
+
+    /// Move the parsed fragment at `index` to the back of the cache, parsing
+    /// it if it isn't cached.
+    fn load(&mut self, index: usize) -> Result<()> {
+        match self.cache.iter().position(|f| f.index == index) {
+            Some(i) => {
+                // Move it to the back, as the most recently used.
+                let fragment = self.cache.remove(i).unwrap();
+                self.cache.push_back(fragment);
+            }
+            None => {
+                self.read_fragment(index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode time of the first sample of `track_id` in a fragment, or `None`
+    /// if it has none. Estimates from `sidx` or `mfra` are used unless `exact`
+    /// is set.
+    fn start_time(&mut self, index: usize, track_id: u32, exact: bool) -> Result<Option<u64>> {
+        let info = &self.fragments[index];
+        if (info.start_times.is_some() || !exact)
+            && let Some(start_time) = info.start_time(track_id)
+        {
+            return Ok(start_time);
+        }
+        self.read_fragment(index)?;
+        Ok(self.fragments[index].start_time(track_id).flatten())
+    }
+
+    /// The last fragment holding samples of `track_id` that starts at or
+    /// before `timestamp`.
+    fn fragment_before(&mut self, track_id: u32, timestamp: u64) -> Result<Option<usize>> {
+        let mut found = None;
+        let (mut lo, mut hi) = (0, self.fragments.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            // Fragments without the track have no time of their own, so go by
+            // the next one that has it.
+            let mut next = None;
+            for index in mid..hi {
+                if let Some(start_time) = self.start_time(index, track_id, false)? {
+                    next = Some((index, start_time));
+                    break;
+                }
+            }
+            match next {
+                Some((index, start_time)) if start_time <= timestamp => {
+                    found = Some(index);
+                    lo = index + 1;
+                }
+                _ => hi = mid,
+            }
+        }
+
+        // An estimated time may be later than the actual decode time, so check the
+        // neighbours against their exact start times.
+        let mut candidate = self.next_with_track(found.unwrap_or(0), track_id)?;
+        while let Some(index) = candidate {
+            if self.start_time(index, track_id, true)? <= Some(timestamp) {
+                break;
+            }
+            candidate = None;
+            for earlier in (0..index).rev() {
+                if self.start_time(earlier, track_id, true)?.is_some() {
+                    candidate = Some(earlier);
+                    break;
+                }
+            }
+        }
+        while let Some(index) = candidate {
+            match self.next_with_track(index + 1, track_id)? {
+                Some(next) if self.start_time(next, track_id, true)? <= Some(timestamp) => {
+                    candidate = Some(next);
+                }
+                _ => break,
+            }
+        }
+        Ok(candidate)
+    }
+
+    /// The first fragment from `index` on that holds samples of `track_id`.
+    fn next_with_track(&mut self, index: usize, track_id: u32) -> Result<Option<usize>> {
+        for index in index..self.fragments.len() {
+            if self.start_time(index, track_id, true)?.is_some() {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R> Mp4LazyReader<R> {
+    /// Consume self, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
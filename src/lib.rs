@@ -80,14 +80,47 @@ pub mod mp4box;
 pub use mp4box::*;
 
 mod track;
-pub use track::{Mp4Track, TrackConfig};
+pub use track::{
+    Edit, EditSegment, GaplessInfo, Mp4Track, SamplePresentation, SeekPosition, TrackConfig,
+};
 
 mod reader;
-pub use reader::Mp4Reader;
+pub use reader::{Mp4Reader, Mp4Samples, SampleOrder};
+
+mod lazy_reader;
+pub use lazy_reader::{FragmentInfo, Mp4LazyReader};
+
+mod segment_index;
+pub use segment_index::{Segment, SegmentIndex};
+
+mod stream_reader;
+pub use stream_reader::Mp4StreamReader;
 
 mod writer;
 pub use writer::{Mp4Config, Mp4Writer};
 
+mod fragment_writer;
+pub use fragment_writer::{FragmentConfig, Mp4FragmentWriter};
+
+mod recovery;
+pub use recovery::{RecoveryConfig, RecoveryTrack, SampleFraming, recover_mp4};
+
+mod retag;
+pub use retag::retag_mp4;
+
+mod box_tree;
+pub use box_tree::{BoxNode, BoxStatus, find_box, read_box_tree};
+
+#[cfg(feature = "tokio")]
+mod async_reader;
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncMp4Reader;
+
+#[cfg(feature = "tokio")]
+mod async_writer;
+#[cfg(feature = "tokio")]
+pub use async_writer::AsyncMp4Writer;
+
 pub fn read_mp4(f: File) -> Result<Mp4Reader<BufReader<File>>> {
     let size = f.metadata()?.len();
     let reader = BufReader::new(f);
@@ -18,6 +18,9 @@ pub struct Avc1Box {
     pub frame_count: u16,
     pub depth: u16,
     pub avcc: AvcCBox,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Default for Avc1Box {
@@ -31,6 +34,7 @@ impl Default for Avc1Box {
             frame_count: 1,
             depth: 0x0018,
             avcc: AvcCBox::default(),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -46,6 +50,7 @@ impl Avc1Box {
             frame_count: 1,
             depth: 0x0018,
             avcc: AvcCBox::new(&config.seq_param_set, &config.pic_param_set),
+            offset: BoxOffset::default(),
         }
     }
 
@@ -78,6 +83,10 @@ impl Mp4Box for Avc1Box {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for Avc1Box {
@@ -128,6 +137,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for Avc1Box {
                     frame_count,
                     depth,
                     avcc,
+                    offset: BoxOffset(Some(start)),
                 });
             } else {
                 skip_bytes_to(reader, current + s)?;
@@ -174,6 +184,9 @@ pub struct AvcCBox {
     pub length_size_minus_one: u8,
     pub sequence_parameter_sets: Vec<NalUnit>,
     pub picture_parameter_sets: Vec<NalUnit>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl AvcCBox {
@@ -186,6 +199,7 @@ impl AvcCBox {
             length_size_minus_one: 0xff, // length_size = 4
             sequence_parameter_sets: vec![NalUnit::from(sps)],
             picture_parameter_sets: vec![NalUnit::from(pps)],
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -214,6 +228,10 @@ impl Mp4Box for AvcCBox {
         let s = format!("avc_profile_indication={}", self.avc_profile_indication);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for AvcCBox {
@@ -248,6 +266,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for AvcCBox {
             length_size_minus_one,
             sequence_parameter_sets,
             picture_parameter_sets,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -337,7 +356,9 @@ mod tests {
                 picture_parameter_sets: vec![NalUnit {
                     bytes: vec![0x68, 0xEB, 0xE3, 0xCB, 0x22, 0xC0],
                 }],
+                ..Default::default()
             },
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -12,6 +12,9 @@ pub struct Co64Box {
 
     #[serde(skip_serializing)]
     pub entries: Vec<u64>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Co64Box {
@@ -41,6 +44,10 @@ impl Mp4Box for Co64Box {
         let s = format!("entries_count={}", self.entries.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for Co64Box {
@@ -75,6 +82,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for Co64Box {
             version,
             flags,
             entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -95,6 +103,17 @@ impl<W: Write> WriteBox<&mut W> for Co64Box {
     }
 }
 
+impl From<&stco::StcoBox> for Co64Box {
+    fn from(stco: &stco::StcoBox) -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            entries: stco.entries.iter().map(|offset| *offset as u64).collect(),
+            offset: BoxOffset::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,6 +126,7 @@ mod tests {
             version: 0,
             flags: 0,
             entries: vec![267, 1970, 2535, 2803, 11843, 22223, 33584],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -12,6 +12,9 @@ pub struct CttsBox {
 
     #[serde(skip_serializing)]
     pub entries: Vec<CttsEntry>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl CttsBox {
@@ -47,6 +50,10 @@ impl Mp4Box for CttsBox {
         let s = format!("entries_count={}", self.entries.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for CttsBox {
@@ -85,6 +92,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for CttsBox {
             version,
             flags,
             entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -127,6 +135,7 @@ mod tests {
                     sample_offset: -100,
                 },
             ],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -1,8 +1,9 @@
 use std::{
-    convert::TryFrom,
+    borrow::Cow,
     io::{Read, Seek},
 };
 
+use byteorder::ByteOrder;
 use serde::Serialize;
 
 use crate::mp4box::*;
@@ -11,6 +12,9 @@ use crate::mp4box::*;
 pub struct DataBox {
     pub data: Vec<u8>,
     pub data_type: DataType,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl DataBox {
@@ -25,6 +29,52 @@ impl DataBox {
         size += self.data.len() as u64;
         size
     }
+
+    /// Decode the data according to its type.
+    pub fn value(&self) -> DataValue<'_> {
+        let data = self.data.as_slice();
+        let fixed_size = match self.data_type {
+            DataType::Int8 | DataType::Uint8 => 1,
+            DataType::Int16 | DataType::Uint16 => 2,
+            DataType::Int32 | DataType::Uint32 | DataType::Float32 => 4,
+            DataType::Int64 | DataType::Uint64 | DataType::Float64 => 8,
+            _ => data.len(),
+        };
+        let is_int = (1..=8).contains(&data.len()) && data.len() == fixed_size;
+        match self.data_type {
+            DataType::Text => DataValue::Text(String::from_utf8_lossy(data)),
+            DataType::Utf16 => {
+                let units = data.chunks_exact(2).map(BigEndian::read_u16);
+                let text = char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect();
+                DataValue::Text(Cow::Owned(text))
+            }
+            DataType::TempoCpil
+            | DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+                if is_int =>
+            {
+                DataValue::Integer(BigEndian::read_int(data, data.len()))
+            }
+            DataType::UnsignedInt
+            | DataType::Uint8
+            | DataType::Uint16
+            | DataType::Uint32
+            | DataType::Uint64
+                if is_int =>
+            {
+                DataValue::UnsignedInteger(BigEndian::read_uint(data, data.len()))
+            }
+            DataType::Float32 if data.len() == 4 => {
+                DataValue::Float(BigEndian::read_f32(data) as f64)
+            }
+            DataType::Float64 if data.len() == 8 => DataValue::Float(BigEndian::read_f64(data)),
+            _ => DataValue::Binary(data),
+        }
+    }
 }
 
 impl Mp4Box for DataBox {
@@ -44,13 +94,17 @@ impl Mp4Box for DataBox {
         let s = format!("type={:?} len={}", self.data_type, self.data.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for DataBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
         let start = box_start(reader)?;
 
-        let data_type = DataType::try_from(reader.read_u32::<BigEndian>()?)?;
+        let data_type = DataType::from(reader.read_u32::<BigEndian>()?);
 
         reader.read_u32::<BigEndian>()?; // reserved = 0
 
@@ -58,7 +112,11 @@ impl<R: Read + Seek> ReadBox<&mut R> for DataBox {
         let mut data = vec![0u8; (start + size - current) as usize];
         reader.read_exact(&mut data)?;
 
-        Ok(DataBox { data, data_type })
+        Ok(DataBox {
+            data,
+            data_type,
+            offset: BoxOffset(Some(start)),
+        })
     }
 }
 
@@ -67,7 +125,7 @@ impl<W: Write> WriteBox<&mut W> for DataBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
-        writer.write_u32::<BigEndian>(self.data_type.clone() as u32)?;
+        writer.write_u32::<BigEndian>((&self.data_type).into())?;
         writer.write_u32::<BigEndian>(0)?; // reserved = 0
         writer.write_all(&self.data)?;
 
@@ -86,6 +144,28 @@ mod tests {
         let src_box = DataBox {
             data_type: DataType::Text,
             data: b"test_data".to_vec(),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::DataBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = DataBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_data_other_type() {
+        // UTF-8 sort, which isn't decoded.
+        let src_box = DataBox {
+            data_type: DataType::Other(4),
+            data: b"sort".to_vec(),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -98,6 +178,42 @@ mod tests {
 
         let dst_box = DataBox::read_box(&mut reader, header.size).unwrap();
         assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.value(), DataValue::Binary(b"sort"));
+    }
+
+    #[test]
+    fn test_data_value() {
+        let data = |data_type, data: &[u8]| DataBox {
+            data_type,
+            data: data.to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            data(DataType::Text, b"text").value(),
+            DataValue::Text("text".into())
+        );
+        assert_eq!(
+            data(DataType::Utf16, &[0, b'h', 0, b'i']).value(),
+            DataValue::Text("hi".into())
+        );
+        assert_eq!(
+            data(DataType::TempoCpil, &[0xff, 0xfe]).value(),
+            DataValue::Integer(-2)
+        );
+        assert_eq!(
+            data(DataType::Uint32, &[0, 0, 1, 0]).value(),
+            DataValue::UnsignedInteger(256)
+        );
+        assert_eq!(
+            data(DataType::Float32, &1.5f32.to_be_bytes()).value(),
+            DataValue::Float(1.5)
+        );
+        // Wrong size for the type.
+        assert_eq!(data(DataType::Int16, &[1]).value(), DataValue::Binary(&[1]));
+        assert_eq!(
+            data(DataType::Png, b"png").value(),
+            DataValue::Binary(b"png")
+        );
     }
 
     #[test]
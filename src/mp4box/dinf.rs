@@ -6,6 +6,9 @@ use crate::mp4box::*;
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct DinfBox {
     dref: DrefBox,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl DinfBox {
@@ -35,19 +38,26 @@ impl Mp4Box for DinfBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for DinfBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for DinfBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut dref = None;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::DinfBox, end, ctx, |reader, header, _| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -64,9 +74,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for DinfBox {
                     skip_box(reader, s)?;
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            Ok(())
+        })?;
 
         if dref.is_none() {
             return Err(Error::BoxNotFound(BoxType::DrefBox));
@@ -76,6 +85,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for DinfBox {
 
         Ok(DinfBox {
             dref: dref.unwrap(),
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -96,6 +106,9 @@ pub struct DrefBox {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<UrlBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Default for DrefBox {
@@ -104,6 +117,7 @@ impl Default for DrefBox {
             version: 0,
             flags: 0,
             url: Some(UrlBox::default()),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -139,6 +153,10 @@ impl Mp4Box for DrefBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for DrefBox {
@@ -185,6 +203,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for DrefBox {
             version,
             flags,
             url,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -211,6 +230,9 @@ pub struct UrlBox {
     pub version: u8,
     pub flags: u32,
     pub location: String,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Default for UrlBox {
@@ -219,6 +241,7 @@ impl Default for UrlBox {
             version: 0,
             flags: 1,
             location: String::default(),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -232,7 +255,7 @@ impl UrlBox {
         let mut size = HEADER_SIZE + HEADER_EXT_SIZE;
 
         if !self.location.is_empty() {
-            size += self.location.bytes().len() as u64 + 1;
+            size += self.location.len() as u64 + 1;
         }
 
         size
@@ -256,6 +279,10 @@ impl Mp4Box for UrlBox {
         let s = format!("location={}", self.location);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for UrlBox {
@@ -281,6 +308,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for UrlBox {
             version,
             flags,
             location,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
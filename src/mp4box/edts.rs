@@ -7,6 +7,9 @@ use crate::mp4box::*;
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct EdtsBox {
     pub elst: Option<ElstBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl EdtsBox {
@@ -44,13 +47,20 @@ impl Mp4Box for EdtsBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for EdtsBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
         let start = box_start(reader)?;
 
-        let mut edts = EdtsBox::new();
+        let mut edts = EdtsBox {
+            offset: BoxOffset(Some(start)),
+            ..EdtsBox::new()
+        };
 
         let header = BoxHeader::read(reader)?;
         let BoxHeader { name, size: s } = header;
@@ -12,12 +12,17 @@ pub struct ElstBox {
 
     #[serde(skip_serializing)]
     pub entries: Vec<ElstEntry>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct ElstEntry {
     pub segment_duration: u64,
-    pub media_time: u64,
+
+    /// Start of the edit in media time, or -1 for an empty edit.
+    pub media_time: i64,
     pub media_rate: u16,
     pub media_rate_fraction: u16,
 }
@@ -55,6 +60,10 @@ impl Mp4Box for ElstBox {
         let s = format!("elst_entries={}", self.entries.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for ElstBox {
@@ -91,12 +100,12 @@ impl<R: Read + Seek> ReadBox<&mut R> for ElstBox {
             let (segment_duration, media_time) = if version == 1 {
                 (
                     reader.read_u64::<BigEndian>()?,
-                    reader.read_u64::<BigEndian>()?,
+                    reader.read_i64::<BigEndian>()?,
                 )
             } else {
                 (
                     reader.read_u32::<BigEndian>()? as u64,
-                    reader.read_u32::<BigEndian>()? as u64,
+                    reader.read_i32::<BigEndian>()? as i64,
                 )
             };
 
@@ -115,6 +124,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for ElstBox {
             version,
             flags,
             entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -130,10 +140,10 @@ impl<W: Write> WriteBox<&mut W> for ElstBox {
         for entry in self.entries.iter() {
             if self.version == 1 {
                 writer.write_u64::<BigEndian>(entry.segment_duration)?;
-                writer.write_u64::<BigEndian>(entry.media_time)?;
+                writer.write_i64::<BigEndian>(entry.media_time)?;
             } else {
                 writer.write_u32::<BigEndian>(entry.segment_duration as u32)?;
-                writer.write_u32::<BigEndian>(entry.media_time as u32)?;
+                writer.write_i32::<BigEndian>(entry.media_time as i32)?;
             }
             writer.write_u16::<BigEndian>(entry.media_rate)?;
             writer.write_u16::<BigEndian>(entry.media_rate_fraction)?;
@@ -160,6 +170,7 @@ mod tests {
                 media_rate: 1,
                 media_rate_fraction: 0,
             }],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -185,6 +196,7 @@ mod tests {
                 media_rate: 1,
                 media_rate_fraction: 0,
             }],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -198,4 +210,35 @@ mod tests {
         let dst_box = ElstBox::read_box(&mut reader, header.size).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[test]
+    fn test_elst32_empty_edit() {
+        let src_box = ElstBox {
+            version: 0,
+            flags: 0,
+            entries: vec![
+                ElstEntry {
+                    segment_duration: 1000,
+                    media_time: -1,
+                    media_rate: 1,
+                    media_rate_fraction: 0,
+                },
+                ElstEntry {
+                    segment_duration: 634634,
+                    media_time: 2112,
+                    media_rate: 1,
+                    media_rate_fraction: 0,
+                },
+            ],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(&buf[20..24], &[0xff, 0xff, 0xff, 0xff]);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let dst_box = ElstBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
 }
@@ -18,6 +18,9 @@ pub struct EmsgBox {
     pub scheme_id_uri: String,
     pub value: String,
     pub message_data: Vec<u8>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl EmsgBox {
@@ -56,6 +59,10 @@ impl Mp4Box for EmsgBox {
         let s = format!("id={} value={}", self.id, self.value);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for EmsgBox {
@@ -116,6 +123,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for EmsgBox {
             scheme_id_uri,
             value,
             message_data,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -199,6 +207,7 @@ mod tests {
             scheme_id_uri: String::from("foo"),
             value: String::from("foo"),
             message_data: vec![1, 2, 3],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -226,6 +235,7 @@ mod tests {
             scheme_id_uri: String::from("foo"),
             value: String::from("foo"),
             message_data: vec![3, 2, 1],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -9,6 +9,9 @@ pub struct FtypBox {
     pub major_brand: FourCC,
     pub minor_version: u32,
     pub compatible_brands: Vec<FourCC>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl FtypBox {
@@ -47,13 +50,17 @@ impl Mp4Box for FtypBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for FtypBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
         let start = box_start(reader)?;
 
-        if size < 16 || size % 4 != 0 {
+        if size < 16 || !size.is_multiple_of(4) {
             return Err(Error::InvalidData("ftyp size too small or not aligned"));
         }
         let brand_count = (size - 16) / 4; // header + major + minor
@@ -72,6 +79,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for FtypBox {
             major_brand: From::from(major),
             minor_version: minor,
             compatible_brands: brands,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -107,6 +115,7 @@ mod tests {
                 str::parse("avc1").unwrap(),
                 str::parse("mp41").unwrap(),
             ],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
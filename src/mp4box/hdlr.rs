@@ -10,6 +10,9 @@ pub struct HdlrBox {
     pub flags: u32,
     pub handler_type: FourCC,
     pub name: String,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl HdlrBox {
@@ -39,6 +42,10 @@ impl Mp4Box for HdlrBox {
         let s = format!("handler_type={} name={}", self.handler_type, self.name);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for HdlrBox {
@@ -70,6 +77,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for HdlrBox {
             flags,
             handler_type: From::from(handler),
             name: handler_string,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -109,6 +117,7 @@ mod tests {
             flags: 0,
             handler_type: str::parse::<FourCC>("vide").unwrap(),
             name: String::from("VideoHandler"),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -130,6 +139,7 @@ mod tests {
             flags: 0,
             handler_type: str::parse::<FourCC>("vide").unwrap(),
             name: String::new(),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -151,12 +161,14 @@ mod tests {
             flags: 0,
             handler_type: str::parse::<FourCC>("vide").unwrap(),
             name: String::from("Good"),
+            ..Default::default()
         };
         let src_box = HdlrBox {
             version: 0,
             flags: 0,
             handler_type: str::parse::<FourCC>("vide").unwrap(),
             name: String::from_utf8(b"Good\0Bad".to_vec()).unwrap(),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
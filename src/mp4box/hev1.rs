@@ -18,6 +18,9 @@ pub struct Hev1Box {
     pub frame_count: u16,
     pub depth: u16,
     pub hvcc: HvcCBox,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Default for Hev1Box {
@@ -31,6 +34,7 @@ impl Default for Hev1Box {
             frame_count: 1,
             depth: 0x0018,
             hvcc: HvcCBox::default(),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -46,6 +50,7 @@ impl Hev1Box {
             frame_count: 1,
             depth: 0x0018,
             hvcc: HvcCBox::new(),
+            offset: BoxOffset::default(),
         }
     }
 
@@ -78,6 +83,10 @@ impl Mp4Box for Hev1Box {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for Hev1Box {
@@ -122,6 +131,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for Hev1Box {
                 frame_count,
                 depth,
                 hvcc,
+                offset: BoxOffset(Some(start)),
             })
         } else {
             Err(Error::InvalidData("hvcc not found"))
@@ -178,6 +188,9 @@ pub struct HvcCBox {
     pub temporal_id_nested: bool,
     pub length_size_minus_one: u8,
     pub arrays: Vec<HvcCArray>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl HvcCBox {
@@ -209,7 +222,8 @@ impl Mp4Box for HvcCBox {
     }
 
     fn summary(&self) -> Result<String> {
-        Ok(format!("configuration_version={} general_profile_space={} general_tier_flag={} general_profile_idc={} general_profile_compatibility_flags={} general_constraint_indicator_flag={} general_level_idc={} min_spatial_segmentation_idc={} parallelism_type={} chroma_format_idc={} bit_depth_luma_minus8={} bit_depth_chroma_minus8={} avg_frame_rate={} constant_frame_rate={} num_temporal_layers={} temporal_id_nested={} length_size_minus_one={}", 
+        Ok(format!(
+            "configuration_version={} general_profile_space={} general_tier_flag={} general_profile_idc={} general_profile_compatibility_flags={} general_constraint_indicator_flag={} general_level_idc={} min_spatial_segmentation_idc={} parallelism_type={} chroma_format_idc={} bit_depth_luma_minus8={} bit_depth_chroma_minus8={} avg_frame_rate={} constant_frame_rate={} num_temporal_layers={} temporal_id_nested={} length_size_minus_one={}",
             self.configuration_version,
             self.general_profile_space,
             self.general_tier_flag,
@@ -229,6 +243,10 @@ impl Mp4Box for HvcCBox {
             self.length_size_minus_one
         ))
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
@@ -246,6 +264,8 @@ pub struct HvcCArray {
 
 impl<R: Read + Seek> ReadBox<&mut R> for HvcCBox {
     fn read_box(reader: &mut R, _size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
         let configuration_version = reader.read_u8()?;
         let params = reader.read_u8()?;
         let general_profile_space = params & 0b11000000 >> 6;
@@ -311,6 +331,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for HvcCBox {
             temporal_id_nested,
             length_size_minus_one,
             arrays,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -379,6 +400,7 @@ mod tests {
                 configuration_version: 1,
                 ..Default::default()
             },
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
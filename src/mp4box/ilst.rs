@@ -11,6 +11,15 @@ use crate::mp4box::*;
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct IlstBox {
     pub items: HashMap<MetadataKey, IlstItemBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub freeform: Vec<FreeformItemBox>,
+
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl IlstBox {
@@ -23,8 +32,23 @@ impl IlstBox {
         for item in self.items.values() {
             size += item.get_size();
         }
+        for item in self.freeform.iter() {
+            size += item.get_size();
+        }
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.freeform.is_empty() && self.unknown_boxes.is_empty()
+    }
+
+    /// The freeform item with the given mean and name.
+    pub fn freeform_item(&self, mean: &str, name: &str) -> Option<&FreeformItemBox> {
+        self.freeform
+            .iter()
+            .find(|item| item.mean == mean && item.name == name)
+    }
 }
 
 impl Mp4Box for IlstBox {
@@ -44,19 +68,29 @@ impl Mp4Box for IlstBox {
         let s = format!("item_count={}", self.items.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for IlstBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for IlstBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut items = HashMap::new();
+        let mut freeform = Vec::new();
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::IlstBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -64,31 +98,29 @@ impl<R: Read + Seek> ReadBox<&mut R> for IlstBox {
                 ));
             }
 
-            match name {
-                BoxType::NameBox => {
-                    items.insert(MetadataKey::Title, IlstItemBox::read_box(reader, s)?);
-                }
-                BoxType::DayBox => {
-                    items.insert(MetadataKey::Year, IlstItemBox::read_box(reader, s)?);
-                }
-                BoxType::CovrBox => {
-                    items.insert(MetadataKey::Poster, IlstItemBox::read_box(reader, s)?);
+            match item_key(name) {
+                Some(key) => {
+                    items.insert(key, IlstItemBox::read_box(reader, s)?);
                 }
-                BoxType::DescBox => {
-                    items.insert(MetadataKey::Summary, IlstItemBox::read_box(reader, s)?);
+                None if name == BoxType::FreeformBox => {
+                    freeform.push(FreeformItemBox::read_box_with(reader, s, ctx)?);
                 }
-                _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                None => {
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         skip_bytes_to(reader, start + size)?;
 
-        Ok(IlstBox { items })
+        Ok(IlstBox {
+            items,
+            freeform,
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
+        })
     }
 }
 
@@ -97,20 +129,67 @@ impl<W: Write> WriteBox<&mut W> for IlstBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
         for (key, value) in &self.items {
-            let name = match key {
-                MetadataKey::Title => BoxType::NameBox,
-                MetadataKey::Year => BoxType::DayBox,
-                MetadataKey::Poster => BoxType::CovrBox,
-                MetadataKey::Summary => BoxType::DescBox,
-            };
-            BoxHeader::new(name, value.get_size()).write(writer)?;
-            value.data.write_box(writer)?;
+            children.write_with(|writer| {
+                BoxHeader::new(item_box_type(key), value.get_size()).write(writer)?;
+                value.data.write_box(writer)?;
+                Ok(())
+            })?;
+        }
+        for item in self.freeform.iter() {
+            children.write(item)?;
         }
+        children.finish()?;
         Ok(size)
     }
 }
 
+// The item boxes for each key.
+const ITEM_TYPES: [(MetadataKey, BoxType); 26] = [
+    (MetadataKey::Title, BoxType::NameBox),
+    (MetadataKey::Year, BoxType::DayBox),
+    (MetadataKey::Poster, BoxType::CovrBox),
+    (MetadataKey::Summary, BoxType::DescBox),
+    (MetadataKey::Artist, BoxType::ArtBox),
+    (MetadataKey::AlbumArtist, BoxType::AartBox),
+    (MetadataKey::Album, BoxType::AlbBox),
+    (MetadataKey::Genre, BoxType::GenBox),
+    (MetadataKey::GenreId, BoxType::GnreBox),
+    (MetadataKey::TrackNumber, BoxType::TrknBox),
+    (MetadataKey::DiscNumber, BoxType::DiskBox),
+    (MetadataKey::Composer, BoxType::WrtBox),
+    (MetadataKey::Comment, BoxType::CmtBox),
+    (MetadataKey::Encoder, BoxType::TooBox),
+    (MetadataKey::Bpm, BoxType::TmpoBox),
+    (MetadataKey::Compilation, BoxType::CpilBox),
+    (MetadataKey::Rating, BoxType::RtngBox),
+    (MetadataKey::MediaKind, BoxType::StikBox),
+    (MetadataKey::TvShow, BoxType::TvshBox),
+    (MetadataKey::TvEpisode, BoxType::TvesBox),
+    (MetadataKey::TvSeason, BoxType::TvsnBox),
+    (MetadataKey::Lyrics, BoxType::LyrBox),
+    (MetadataKey::SortTitle, BoxType::SonmBox),
+    (MetadataKey::SortArtist, BoxType::SoarBox),
+    (MetadataKey::SortAlbum, BoxType::SoalBox),
+    (MetadataKey::SortAlbumArtist, BoxType::SoaaBox),
+];
+
+fn item_key(name: BoxType) -> Option<MetadataKey> {
+    ITEM_TYPES
+        .iter()
+        .find(|(_, box_type)| *box_type == name)
+        .map(|(key, _)| key.clone())
+}
+
+fn item_box_type(key: &MetadataKey) -> BoxType {
+    ITEM_TYPES
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, box_type)| *box_type)
+        .unwrap()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct IlstItemBox {
     pub data: DataBox,
@@ -165,8 +244,142 @@ impl<R: Read + Seek> ReadBox<&mut R> for IlstItemBox {
     }
 }
 
+/// A freeform (`----`) item, named by a reverse domain `mean` such as
+/// `com.apple.iTunes` and a `name` within it.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct FreeformItemBox {
+    pub mean: String,
+    pub name: String,
+
+    /// The first value. Any others are kept in `unknown_boxes`.
+    pub data: DataBox,
+
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+}
+
+impl FreeformItemBox {
+    fn get_size(&self) -> u64 {
+        HEADER_SIZE
+            + HEADER_SIZE
+            + HEADER_EXT_SIZE
+            + self.mean.len() as u64
+            + HEADER_SIZE
+            + HEADER_EXT_SIZE
+            + self.name.len() as u64
+            + self.data.box_size()
+            + unknown_boxes_size(&self.unknown_boxes)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for FreeformItemBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for FreeformItemBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let mut mean = None;
+        let mut name = None;
+        let mut data = None;
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
+
+        let end = start + size;
+        read_children(
+            reader,
+            BoxType::FreeformBox,
+            end,
+            ctx,
+            |reader, header, _| {
+                let BoxHeader {
+                    name: box_name,
+                    size: s,
+                } = header;
+
+                match box_name {
+                    BoxType::MeanBox => {
+                        mean = Some(read_freeform_string(reader, s, end)?);
+                    }
+                    BoxType::ItemNameBox => {
+                        name = Some(read_freeform_string(reader, s, end)?);
+                    }
+                    BoxType::DataBox if data.is_none() => {
+                        data = Some(DataBox::read_box(reader, s)?);
+                    }
+                    _ => {
+                        unknown_boxes.push(read_unknown_box(reader, box_name, s, end, position)?);
+                    }
+                }
+                position += 1;
+                Ok(())
+            },
+        )?;
+
+        let Some(mean) = mean else {
+            return Err(Error::BoxNotFound(BoxType::MeanBox));
+        };
+        let Some(name) = name else {
+            return Err(Error::BoxNotFound(BoxType::ItemNameBox));
+        };
+        let Some(data) = data else {
+            return Err(Error::BoxNotFound(BoxType::DataBox));
+        };
+
+        skip_bytes_to(reader, end)?;
+
+        Ok(FreeformItemBox {
+            mean,
+            name,
+            data,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for FreeformItemBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.get_size();
+        BoxHeader::new(BoxType::FreeformBox, size).write(writer)?;
+
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
+        for (box_type, value) in [
+            (BoxType::MeanBox, &self.mean),
+            (BoxType::ItemNameBox, &self.name),
+        ] {
+            children.write_with(|writer| {
+                let s = HEADER_SIZE + HEADER_EXT_SIZE + value.len() as u64;
+                BoxHeader::new(box_type, s).write(writer)?;
+                write_box_header_ext(writer, 0, 0)?;
+                writer.write_all(value.as_bytes())?;
+                Ok(())
+            })?;
+        }
+        children.write(&self.data)?;
+        children.finish()?;
+        Ok(size)
+    }
+}
+
+// The payload of a mean or name box: version, flags and a UTF-8 string.
+fn read_freeform_string<R: Read + Seek>(reader: &mut R, size: u64, end: u64) -> Result<String> {
+    if size < HEADER_SIZE + HEADER_EXT_SIZE {
+        return Err(Error::InvalidData("freeform item string box too small"));
+    }
+    if size - HEADER_SIZE > end.saturating_sub(reader.stream_position()?) {
+        return Err(Error::InvalidData("box size larger than its parent"));
+    }
+    read_box_header_ext(reader)?;
+    let mut buf = vec![0u8; (size - HEADER_SIZE - HEADER_EXT_SIZE) as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
 impl<'a> Metadata<'a> for IlstBox {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         self.items.get(&MetadataKey::Title).map(item_to_str)
     }
 
@@ -178,17 +391,184 @@ impl<'a> Metadata<'a> for IlstBox {
         self.items.get(&MetadataKey::Poster).map(item_to_bytes)
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         self.items.get(&MetadataKey::Summary).map(item_to_str)
     }
+
+    fn artist(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::Artist).map(item_to_str)
+    }
+
+    fn album_artist(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::AlbumArtist).map(item_to_str)
+    }
+
+    fn album(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::Album).map(item_to_str)
+    }
+
+    fn genre(&self) -> Option<Cow<'_, str>> {
+        if let Some(item) = self.items.get(&MetadataKey::Genre) {
+            return Some(item_to_str(item));
+        }
+        // gnre holds the ID3v1 genre plus one.
+        let id = self
+            .items
+            .get(&MetadataKey::GenreId)
+            .and_then(item_to_int)?;
+        let name = ID3_GENRES.get(usize::try_from(id).ok()?.checked_sub(1)?)?;
+        Some(Cow::Borrowed(name))
+    }
+
+    fn track_number(&self) -> Option<(u16, u16)> {
+        self.items
+            .get(&MetadataKey::TrackNumber)
+            .and_then(item_to_pair)
+    }
+
+    fn disc_number(&self) -> Option<(u16, u16)> {
+        self.items
+            .get(&MetadataKey::DiscNumber)
+            .and_then(item_to_pair)
+    }
+
+    fn composer(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::Composer).map(item_to_str)
+    }
+
+    fn comment(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::Comment).map(item_to_str)
+    }
+
+    fn encoder(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::Encoder).map(item_to_str)
+    }
+
+    fn bpm(&self) -> Option<u16> {
+        let bpm = self.items.get(&MetadataKey::Bpm).and_then(item_to_int)?;
+        u16::try_from(bpm).ok()
+    }
+
+    fn compilation(&self) -> Option<bool> {
+        let compilation = self
+            .items
+            .get(&MetadataKey::Compilation)
+            .and_then(item_to_int)?;
+        Some(compilation != 0)
+    }
+
+    fn rating(&self) -> Option<u8> {
+        let rating = self.items.get(&MetadataKey::Rating).and_then(item_to_int)?;
+        u8::try_from(rating).ok()
+    }
+
+    fn media_kind(&self) -> Option<u8> {
+        let kind = self
+            .items
+            .get(&MetadataKey::MediaKind)
+            .and_then(item_to_int)?;
+        u8::try_from(kind).ok()
+    }
+
+    fn tv_show(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::TvShow).map(item_to_str)
+    }
+
+    fn tv_episode(&self) -> Option<u32> {
+        let episode = self
+            .items
+            .get(&MetadataKey::TvEpisode)
+            .and_then(item_to_int)?;
+        u32::try_from(episode).ok()
+    }
+
+    fn tv_season(&self) -> Option<u32> {
+        let season = self
+            .items
+            .get(&MetadataKey::TvSeason)
+            .and_then(item_to_int)?;
+        u32::try_from(season).ok()
+    }
+
+    fn lyrics(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::Lyrics).map(item_to_str)
+    }
+
+    fn sort_title(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::SortTitle).map(item_to_str)
+    }
+
+    fn sort_artist(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::SortArtist).map(item_to_str)
+    }
+
+    fn sort_album(&self) -> Option<Cow<'_, str>> {
+        self.items.get(&MetadataKey::SortAlbum).map(item_to_str)
+    }
+
+    fn sort_album_artist(&self) -> Option<Cow<'_, str>> {
+        self.items
+            .get(&MetadataKey::SortAlbumArtist)
+            .map(item_to_str)
+    }
+
+    fn freeform(&self, mean: &str, name: &str) -> Option<DataValue<'_>> {
+        self.freeform_item(mean, name).map(|item| item.data.value())
+    }
+}
+
+impl MetadataMut for IlstBox {
+    fn set_item(&mut self, key: MetadataKey, data_type: DataType, data: Vec<u8>) {
+        let data = DataBox {
+            data,
+            data_type,
+            ..Default::default()
+        };
+        self.items.insert(key, IlstItemBox { data });
+    }
+
+    fn remove_item(&mut self, key: &MetadataKey) -> bool {
+        self.items.remove(key).is_some()
+    }
+
+    fn set_freeform(&mut self, mean: &str, name: &str, data_type: DataType, data: Vec<u8>) {
+        let data = DataBox {
+            data,
+            data_type,
+            ..Default::default()
+        };
+        match self
+            .freeform
+            .iter_mut()
+            .find(|item| item.mean == mean && item.name == name)
+        {
+            Some(item) => item.data = data,
+            None => self.freeform.push(FreeformItemBox {
+                mean: mean.to_string(),
+                name: name.to_string(),
+                data,
+                unknown_boxes: Vec::new(),
+            }),
+        }
+    }
+
+    fn remove_freeform(&mut self, mean: &str, name: &str) -> bool {
+        let len = self.freeform.len();
+        self.freeform
+            .retain(|item| item.mean != mean || item.name != name);
+        self.freeform.len() != len
+    }
 }
 
 fn item_to_bytes(item: &IlstItemBox) -> &[u8] {
     &item.data.data
 }
 
-fn item_to_str(item: &IlstItemBox) -> Cow<str> {
-    String::from_utf8_lossy(&item.data.data)
+fn item_to_str(item: &IlstItemBox) -> Cow<'_, str> {
+    match item.data.value() {
+        DataValue::Text(text) => text,
+        _ => String::from_utf8_lossy(&item.data.data),
+    }
 }
 
 fn item_to_u32(item: &IlstItemBox) -> Option<u32> {
@@ -199,6 +579,185 @@ fn item_to_u32(item: &IlstItemBox) -> Option<u32> {
     }
 }
 
+// An integer, as used by tmpo, cpil, stik, etc. Implicitly typed data is
+// taken as a big-endian integer of up to 8 bytes.
+fn item_to_int(item: &IlstItemBox) -> Option<u64> {
+    match item.data.value() {
+        DataValue::Integer(value) => u64::try_from(value).ok(),
+        DataValue::UnsignedInteger(value) => Some(value),
+        DataValue::Binary(data)
+            if item.data.data_type == DataType::Binary && (1..=8).contains(&data.len()) =>
+        {
+            Some(BigEndian::read_uint(data, data.len()))
+        }
+        _ => None,
+    }
+}
+
+// trkn and disk: reserved (16), number (16), total (16), then optional
+// padding.
+fn item_to_pair(item: &IlstItemBox) -> Option<(u16, u16)> {
+    let data = &item.data.data;
+    if data.len() < 6 {
+        return None;
+    }
+    Some((
+        BigEndian::read_u16(&data[2..]),
+        BigEndian::read_u16(&data[4..]),
+    ))
+}
+
+const ID3_GENRES: [&str; 148] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+    "Folk",
+    "Folk-Rock",
+    "National Folk",
+    "Swing",
+    "Fast Fusion",
+    "Bebob",
+    "Latin",
+    "Revival",
+    "Celtic",
+    "Bluegrass",
+    "Avantgarde",
+    "Gothic Rock",
+    "Progressive Rock",
+    "Psychedelic Rock",
+    "Symphonic Rock",
+    "Slow Rock",
+    "Big Band",
+    "Chorus",
+    "Easy Listening",
+    "Acoustic",
+    "Humour",
+    "Speech",
+    "Chanson",
+    "Opera",
+    "Chamber Music",
+    "Sonata",
+    "Symphony",
+    "Booty Bass",
+    "Primus",
+    "Porn Groove",
+    "Satire",
+    "Slow Jam",
+    "Club",
+    "Tango",
+    "Samba",
+    "Folklore",
+    "Ballad",
+    "Power Ballad",
+    "Rhythmic Soul",
+    "Freestyle",
+    "Duet",
+    "Punk Rock",
+    "Drum Solo",
+    "A capella",
+    "Euro-House",
+    "Dance Hall",
+    "Goa",
+    "Drum & Bass",
+    "Club-House",
+    "Hardcore",
+    "Terror",
+    "Indie",
+    "BritPop",
+    "Negerpunk",
+    "Polsk Punk",
+    "Beat",
+    "Christian Gangsta Rap",
+    "Heavy Metal",
+    "Black Metal",
+    "Crossover",
+    "Contemporary Christian",
+    "Christian Rock",
+    "Merengue",
+    "Salsa",
+    "Thrash Metal",
+    "Anime",
+    "JPop",
+    "Synthpop",
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,6 +770,7 @@ mod tests {
             data: DataBox {
                 data_type: DataType::Text,
                 data: b"test_year".to_vec(),
+                ..Default::default()
             },
         };
         let src_box = IlstBox {
@@ -221,6 +781,12 @@ mod tests {
                 (MetadataKey::Summary, IlstItemBox::default()),
             ]
             .into(),
+            unknown_boxes: vec![UnknownChildBox::new(
+                BoxType::UnknownBox(0xa9677270),
+                Bytes::from_static(b"grp!"),
+                4,
+            )],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -235,6 +801,144 @@ mod tests {
         assert_eq!(src_box, dst_box);
     }
 
+    #[test]
+    fn test_ilst_itunes_items() {
+        fn item(data_type: DataType, data: &[u8]) -> IlstItemBox {
+            IlstItemBox {
+                data: DataBox {
+                    data_type,
+                    data: data.to_vec(),
+                    ..Default::default()
+                },
+            }
+        }
+        let src_box = IlstBox {
+            items: [
+                (MetadataKey::Artist, item(DataType::Text, b"artist")),
+                (MetadataKey::Album, item(DataType::Text, b"album")),
+                (MetadataKey::GenreId, item(DataType::Binary, &[0, 18])),
+                (
+                    MetadataKey::TrackNumber,
+                    item(DataType::Binary, &[0, 0, 0, 3, 0, 12, 0, 0]),
+                ),
+                (
+                    MetadataKey::DiscNumber,
+                    item(DataType::Binary, &[0, 0, 0, 1, 0, 2]),
+                ),
+                (MetadataKey::Bpm, item(DataType::TempoCpil, &[0, 120])),
+                (MetadataKey::Compilation, item(DataType::TempoCpil, &[1])),
+                (MetadataKey::Rating, item(DataType::TempoCpil, &[2])),
+                (MetadataKey::MediaKind, item(DataType::TempoCpil, &[10])),
+                (
+                    MetadataKey::TvEpisode,
+                    item(DataType::TempoCpil, &[0, 0, 0, 7]),
+                ),
+                (
+                    MetadataKey::SortArtist,
+                    item(DataType::Text, b"artist, the"),
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let dst_box = IlstBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert!(dst_box.unknown_boxes.is_empty());
+
+        assert_eq!(dst_box.artist(), Some("artist".into()));
+        assert_eq!(dst_box.album(), Some("album".into()));
+        assert_eq!(dst_box.genre(), Some("Rock".into()));
+        assert_eq!(dst_box.track_number(), Some((3, 12)));
+        assert_eq!(dst_box.disc_number(), Some((1, 2)));
+        assert_eq!(dst_box.bpm(), Some(120));
+        assert_eq!(dst_box.compilation(), Some(true));
+        assert_eq!(dst_box.rating(), Some(2));
+        assert_eq!(dst_box.media_kind(), Some(10));
+        assert_eq!(dst_box.tv_episode(), Some(7));
+        assert_eq!(dst_box.tv_season(), None);
+        assert_eq!(dst_box.sort_artist(), Some("artist, the".into()));
+    }
+
+    #[test]
+    fn test_ilst_freeform() {
+        let mut src_box = IlstBox::default();
+        src_box.set_freeform_text("com.apple.iTunes", "iTunSMPB", " 00000000 00000840");
+        src_box.set_freeform(
+            "com.apple.iTunes",
+            "replaygain_track_gain",
+            DataType::Float32,
+            (-6.5f32).to_be_bytes().to_vec(),
+        );
+        src_box.set_freeform_text("org.musicbrainz", "Track Id", "old");
+        src_box.set_freeform_text("org.musicbrainz", "Track Id", "new");
+        assert_eq!(src_box.freeform.len(), 3);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let mut dst_box = IlstBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert!(dst_box.unknown_boxes.is_empty());
+
+        assert_eq!(
+            dst_box.freeform("com.apple.iTunes", "iTunSMPB"),
+            Some(DataValue::Text(" 00000000 00000840".into()))
+        );
+        assert_eq!(
+            dst_box.freeform("com.apple.iTunes", "replaygain_track_gain"),
+            Some(DataValue::Float(-6.5))
+        );
+        assert_eq!(
+            dst_box.freeform("org.musicbrainz", "Track Id"),
+            Some(DataValue::Text("new".into()))
+        );
+        assert_eq!(dst_box.freeform("com.apple.iTunes", "Track Id"), None);
+
+        assert!(dst_box.remove_freeform("org.musicbrainz", "Track Id"));
+        assert!(!dst_box.remove_freeform("org.musicbrainz", "Track Id"));
+        assert_eq!(dst_box.freeform.len(), 2);
+    }
+
+    #[test]
+    fn test_freeform_item_extra_children() {
+        let second_value = [&[0, 0, 0, 1, 0, 0, 0, 0][..], b"second"].concat();
+        let src_box = FreeformItemBox {
+            mean: "com.apple.iTunes".to_string(),
+            name: "ARTISTS".to_string(),
+            data: DataBox {
+                data_type: DataType::Text,
+                data: b"first".to_vec(),
+                ..Default::default()
+            },
+            unknown_boxes: vec![
+                UnknownChildBox::new(
+                    BoxType::UnknownBox(0x666c6167),
+                    Bytes::from_static(&[1, 2, 3]),
+                    2,
+                ),
+                UnknownChildBox::new(BoxType::DataBox, Bytes::from(second_value), 4),
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.get_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::FreeformBox);
+        let dst_box = FreeformItemBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
     #[test]
     fn test_ilst_empty() {
         let src_box = IlstBox::default();
@@ -0,0 +1,171 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+/// Namespace of reverse DNS keys such as `com.apple.quicktime.make`.
+pub const MDTA: FourCC = FourCC { value: *b"mdta" };
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct KeysBox {
+    pub version: u8,
+    pub flags: u32,
+    pub keys: Vec<KeysEntry>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct KeysEntry {
+    pub namespace: FourCC,
+    pub name: String,
+}
+
+impl KeysBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::KeysBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE;
+        size += 4; // entry_count
+        for key in self.keys.iter() {
+            size += 8 + key.name.len() as u64; // key_size + key_namespace + key_value
+        }
+        size
+    }
+
+    /// The 1-based index of a key, as used by the `ilst` items.
+    pub fn index(&self, name: &str) -> Option<u32> {
+        let i = self.keys.iter().position(|key| key.name == name)?;
+        Some(i as u32 + 1)
+    }
+
+    /// The key at a 1-based index.
+    pub fn get(&self, index: u32) -> Option<&KeysEntry> {
+        self.keys.get((index as usize).checked_sub(1)?)
+    }
+}
+
+impl Mp4Box for KeysBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("entries={}", self.keys.len());
+        Ok(s)
+    }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for KeysBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+        let end = start + size;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+
+        let entry_count = reader.read_u32::<BigEndian>()?;
+        if u64::from(entry_count) > size.saturating_sub(HEADER_SIZE + HEADER_EXT_SIZE + 4) / 8 {
+            return Err(Error::InvalidData(
+                "keys entry_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let mut keys = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let key_size = reader.read_u32::<BigEndian>()? as u64;
+            if key_size < 8 || key_size - 4 > end.saturating_sub(reader.stream_position()?) {
+                return Err(Error::InvalidData("keys entry has an invalid size"));
+            }
+            let namespace = FourCC::from(reader.read_u32::<BigEndian>()?);
+            let mut name = vec![0u8; (key_size - 8) as usize];
+            reader.read_exact(&mut name)?;
+            keys.push(KeysEntry {
+                namespace,
+                name: String::from_utf8_lossy(&name).into_owned(),
+            });
+        }
+
+        skip_bytes_to(reader, end)?;
+
+        Ok(KeysBox {
+            version,
+            flags,
+            keys,
+            offset: BoxOffset(Some(start)),
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for KeysBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.keys.len() as u32)?;
+        for key in self.keys.iter() {
+            writer.write_u32::<BigEndian>(8 + key.name.len() as u32)?;
+            writer.write_u32::<BigEndian>((&key.namespace).into())?;
+            writer.write_all(key.name.as_bytes())?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_keys() {
+        let src_box = KeysBox {
+            version: 0,
+            flags: 0,
+            keys: vec![
+                KeysEntry {
+                    namespace: MDTA,
+                    name: String::from("com.apple.quicktime.make"),
+                },
+                KeysEntry {
+                    namespace: MDTA,
+                    name: String::from("com.apple.quicktime.model"),
+                },
+            ],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::KeysBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = KeysBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.index("com.apple.quicktime.model"), Some(2));
+        assert_eq!(dst_box.get(1), src_box.keys.first());
+        assert_eq!(dst_box.get(0), None);
+    }
+}
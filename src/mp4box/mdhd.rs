@@ -1,6 +1,6 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
-use std::char::{decode_utf16, REPLACEMENT_CHARACTER};
+use std::char::{REPLACEMENT_CHARACTER, decode_utf16};
 use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
@@ -14,6 +14,9 @@ pub struct MdhdBox {
     pub timescale: u32,
     pub duration: u64,
     pub language: String,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl MdhdBox {
@@ -44,6 +47,7 @@ impl Default for MdhdBox {
             timescale: 1000,
             duration: 0,
             language: String::from("und"),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -68,6 +72,10 @@ impl Mp4Box for MdhdBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MdhdBox {
@@ -106,6 +114,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MdhdBox {
             timescale,
             duration,
             language,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -147,11 +156,9 @@ fn language_string(language: u16) -> String {
     lang[2] = ((language) & 0x1F) + 0x60;
 
     // Decode utf-16 encoded bytes into a string.
-    let lang_str = decode_utf16(lang.iter().cloned())
+    decode_utf16(lang.iter().cloned())
         .map(|r| r.unwrap_or(REPLACEMENT_CHARACTER))
-        .collect::<String>();
-
-    lang_str
+        .collect::<String>()
 }
 
 fn language_code(language: &str) -> u16 {
@@ -191,6 +198,7 @@ mod tests {
             timescale: 48000,
             duration: 30439936,
             language: String::from("und"),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -215,6 +223,7 @@ mod tests {
             timescale: 48000,
             duration: 30439936,
             language: String::from("eng"),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -9,6 +9,11 @@ pub struct MdiaBox {
     pub mdhd: MdhdBox,
     pub hdlr: HdlrBox,
     pub minf: MinfBox,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl MdiaBox {
@@ -17,7 +22,11 @@ impl MdiaBox {
     }
 
     pub fn get_size(&self) -> u64 {
-        HEADER_SIZE + self.mdhd.box_size() + self.hdlr.box_size() + self.minf.box_size()
+        HEADER_SIZE
+            + self.mdhd.box_size()
+            + self.hdlr.box_size()
+            + self.minf.box_size()
+            + unknown_boxes_size(&self.unknown_boxes)
     }
 }
 
@@ -38,21 +47,30 @@ impl Mp4Box for MdiaBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MdiaBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for MdiaBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut mdhd = None;
         let mut hdlr = None;
         let mut minf = None;
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::MdiaBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -68,16 +86,15 @@ impl<R: Read + Seek> ReadBox<&mut R> for MdiaBox {
                     hdlr = Some(HdlrBox::read_box(reader, s)?);
                 }
                 BoxType::MinfBox => {
-                    minf = Some(MinfBox::read_box(reader, s)?);
+                    minf = Some(MinfBox::read_box_with(reader, s, ctx)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         if mdhd.is_none() {
             return Err(Error::BoxNotFound(BoxType::MdhdBox));
@@ -95,6 +112,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for MdiaBox {
             mdhd: mdhd.unwrap(),
             hdlr: hdlr.unwrap(),
             minf: minf.unwrap(),
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -103,10 +122,12 @@ impl<W: Write> WriteBox<&mut W> for MdiaBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
-        self.mdhd.write_box(writer)?;
-        self.hdlr.write_box(writer)?;
-        self.minf.write_box(writer)?;
+        children.write(&self.mdhd)?;
+        children.write(&self.hdlr)?;
+        children.write(&self.minf)?;
+        children.finish()?;
 
         Ok(size)
     }
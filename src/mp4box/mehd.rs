@@ -9,6 +9,9 @@ pub struct MehdBox {
     pub version: u8,
     pub flags: u32,
     pub fragment_duration: u64,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl MehdBox {
@@ -45,6 +48,10 @@ impl Mp4Box for MehdBox {
         let s = format!("fragment_duration={}", self.fragment_duration);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MehdBox {
@@ -66,6 +73,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MehdBox {
             version,
             flags,
             fragment_duration,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -101,6 +109,7 @@ mod tests {
             version: 0,
             flags: 0,
             fragment_duration: 32,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -121,6 +130,7 @@ mod tests {
             version: 0,
             flags: 0,
             fragment_duration: 30439936,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
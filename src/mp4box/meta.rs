@@ -3,7 +3,8 @@ use std::io::{Read, Seek};
 use serde::Serialize;
 
 use crate::mp4box::hdlr::HdlrBox;
-use crate::mp4box::ilst::IlstBox;
+use crate::mp4box::ilst::{IlstBox, IlstItemBox};
+use crate::mp4box::keys::{KeysBox, KeysEntry, MDTA};
 use crate::mp4box::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -13,6 +14,21 @@ pub enum MetaBox {
     Mdir {
         #[serde(skip_serializing_if = "Option::is_none")]
         ilst: Option<IlstBox>,
+
+        #[serde(skip)]
+        unknown_boxes: Vec<UnknownChildBox>,
+    },
+
+    /// QuickTime metadata: `ilst` items named by their index into `keys`.
+    Mdta {
+        keys: KeysBox,
+
+        /// (1-based key index, value) in `ilst` order.
+        #[serde(skip)]
+        items: Vec<(u32, DataBox)>,
+
+        #[serde(skip)]
+        unknown_boxes: Vec<UnknownChildBox>,
     },
 
     #[serde(skip)]
@@ -35,11 +51,28 @@ impl MetaBox {
     pub fn get_size(&self) -> u64 {
         let mut size = HEADER_SIZE + HEADER_EXT_SIZE;
         match self {
-            Self::Mdir { ilst } => {
+            Self::Mdir {
+                ilst,
+                unknown_boxes,
+            } => {
                 size += HdlrBox::default().box_size();
                 if let Some(ilst) = ilst {
                     size += ilst.box_size();
                 }
+                size += unknown_boxes_size(unknown_boxes);
+            }
+            Self::Mdta {
+                keys,
+                items,
+                unknown_boxes,
+            } => {
+                size += HdlrBox::default().box_size();
+                size += keys.box_size();
+                size += HEADER_SIZE; // ilst
+                for (_, data) in items.iter() {
+                    size += HEADER_SIZE + data.box_size();
+                }
+                size += unknown_boxes_size(unknown_boxes);
             }
             Self::Unknown { hdlr, data } => {
                 size += hdlr.box_size()
@@ -51,6 +84,85 @@ impl MetaBox {
         }
         size
     }
+
+    /// An empty `mdta` meta box.
+    pub fn new_mdta() -> Self {
+        Self::Mdta {
+            keys: KeysBox::default(),
+            items: Vec::new(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+
+    /// The `mdta` keys with their values, in `ilst` order. Empty for other
+    /// handlers.
+    pub fn mdta_entries(&self) -> Vec<(&str, &DataBox)> {
+        let Self::Mdta { keys, items, .. } = self else {
+            return Vec::new();
+        };
+        items
+            .iter()
+            .filter_map(|(index, data)| Some((keys.get(*index)?.name.as_str(), data)))
+            .collect()
+    }
+
+    /// The value of an `mdta` key, such as `com.apple.quicktime.make`.
+    pub fn mdta_value(&self, key: &str) -> Option<&DataBox> {
+        let Self::Mdta { keys, items, .. } = self else {
+            return None;
+        };
+        let index = keys.index(key)?;
+        items
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, data)| data)
+    }
+
+    /// Set the value of an `mdta` key, adding the key if needed.
+    pub fn set_mdta_value(&mut self, key: &str, data_type: DataType, data: Vec<u8>) -> Result<()> {
+        let Self::Mdta { keys, items, .. } = self else {
+            return Err(Error::InvalidData("meta box is not an mdta meta box"));
+        };
+        let index = match keys.index(key) {
+            Some(index) => index,
+            None => {
+                keys.keys.push(KeysEntry {
+                    namespace: MDTA,
+                    name: key.to_string(),
+                });
+                keys.keys.len() as u32
+            }
+        };
+        let data = DataBox {
+            data,
+            data_type,
+            ..Default::default()
+        };
+        match items.iter_mut().find(|(i, _)| *i == index) {
+            Some(item) => item.1 = data,
+            None => items.push((index, data)),
+        }
+        Ok(())
+    }
+
+    /// Remove an `mdta` key and its value, returning whether it was present.
+    pub fn remove_mdta_value(&mut self, key: &str) -> bool {
+        let Self::Mdta { keys, items, .. } = self else {
+            return false;
+        };
+        let Some(index) = keys.index(key) else {
+            return false;
+        };
+        keys.keys.remove(index as usize - 1);
+        items.retain(|(i, _)| *i != index);
+        // Later keys move down by one.
+        for (i, _) in items.iter_mut() {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        true
+    }
 }
 
 impl Mp4Box for MetaBox {
@@ -69,6 +181,7 @@ impl Mp4Box for MetaBox {
     fn summary(&self) -> Result<String> {
         let s = match self {
             Self::Mdir { .. } => "hdlr=ilst".to_string(),
+            Self::Mdta { keys, .. } => format!("hdlr=mdta keys={}", keys.keys.len()),
             Self::Unknown { hdlr, data } => {
                 format!("hdlr={} data_len={}", hdlr.handler_type, data.len())
             }
@@ -88,6 +201,12 @@ impl Default for MetaBox {
 
 impl<R: Read + Seek> ReadBox<&mut R> for MetaBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for MetaBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let extended_header = reader.read_u32::<BigEndian>()?;
@@ -139,28 +258,85 @@ impl<R: Read + Seek> ReadBox<&mut R> for MetaBox {
         current = reader.stream_position()?;
 
         let mut ilst = None;
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
         match hdlr.handler_type {
             MDIR => {
-                while current < end {
-                    // Get box header.
-                    let header = BoxHeader::read(reader)?;
+                read_children(reader, BoxType::MetaBox, end, ctx, |reader, header, ctx| {
                     let BoxHeader { name, size: s } = header;
 
                     match name {
+                        BoxType::HdlrBox => {
+                            skip_box(reader, s)?;
+                        }
                         BoxType::IlstBox => {
-                            ilst = Some(IlstBox::read_box(reader, s)?);
+                            ilst = Some(IlstBox::read_box_with(reader, s, ctx)?);
                         }
                         _ => {
-                            // XXX warn!()
-                            skip_box(reader, s)?;
+                            unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                         }
                     }
+                    position += 1;
+                    Ok(())
+                })?;
 
-                    current = reader.stream_position()?;
-                }
+                skip_bytes_to(reader, end)?;
+
+                Ok(MetaBox::Mdir {
+                    ilst,
+                    unknown_boxes,
+                })
+            }
+            MDTA => {
+                let mut keys = None;
+                let mut items = Vec::new();
 
-                Ok(MetaBox::Mdir { ilst })
+                read_children(reader, BoxType::MetaBox, end, ctx, |reader, header, ctx| {
+                    let BoxHeader { name, size: s } = header;
+
+                    match name {
+                        BoxType::HdlrBox => {
+                            skip_box(reader, s)?;
+                        }
+                        BoxType::KeysBox => {
+                            keys = Some(KeysBox::read_box(reader, s)?);
+                        }
+                        BoxType::IlstBox => {
+                            let ilst_end = reader.stream_position()? - HEADER_SIZE + s;
+                            read_children(
+                                reader,
+                                BoxType::IlstBox,
+                                ilst_end,
+                                ctx,
+                                |reader, header, _| {
+                                    // Items are named by their key index.
+                                    let index = u32::from(header.name);
+                                    let item = IlstItemBox::read_box(reader, header.size)?;
+                                    items.push((index, item.data));
+                                    Ok(())
+                                },
+                            )?;
+                            skip_bytes_to(reader, ilst_end)?;
+                        }
+                        _ => {
+                            unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
+                        }
+                    }
+                    position += 1;
+                    Ok(())
+                })?;
+
+                skip_bytes_to(reader, end)?;
+
+                let Some(keys) = keys else {
+                    return Err(Error::BoxNotFound(BoxType::KeysBox));
+                };
+                Ok(MetaBox::Mdta {
+                    keys,
+                    items,
+                    unknown_boxes,
+                })
             }
             _ => {
                 let mut data = Vec::new();
@@ -203,17 +379,51 @@ impl<W: Write> WriteBox<&mut W> for MetaBox {
                 handler_type: MDIR,
                 ..Default::default()
             },
+            Self::Mdta { .. } => HdlrBox {
+                handler_type: MDTA,
+                ..Default::default()
+            },
             Self::Unknown { hdlr, .. } => hdlr.clone(),
         };
-        hdlr.write_box(writer)?;
 
         match self {
-            Self::Mdir { ilst } => {
+            Self::Mdir {
+                ilst,
+                unknown_boxes,
+            } => {
+                let mut children = ChildWriter::new(writer, unknown_boxes);
+                children.write(&hdlr)?;
                 if let Some(ilst) = ilst {
-                    ilst.write_box(writer)?;
+                    children.write(ilst)?;
                 }
+                children.finish()?;
+            }
+            Self::Mdta {
+                keys,
+                items,
+                unknown_boxes,
+            } => {
+                let mut children = ChildWriter::new(writer, unknown_boxes);
+                children.write(&hdlr)?;
+                children.write(keys)?;
+                children.write_with(|writer| {
+                    let ilst_size = HEADER_SIZE
+                        + items
+                            .iter()
+                            .map(|(_, data)| HEADER_SIZE + data.box_size())
+                            .sum::<u64>();
+                    BoxHeader::new(BoxType::IlstBox, ilst_size).write(writer)?;
+                    for (index, data) in items.iter() {
+                        let item_size = HEADER_SIZE + data.box_size();
+                        BoxHeader::new(BoxType::from(*index), item_size).write(writer)?;
+                        data.write_box(writer)?;
+                    }
+                    Ok(())
+                })?;
+                children.finish()?;
             }
             Self::Unknown { data, .. } => {
+                hdlr.write_box(writer)?;
                 for (box_type, data) in data {
                     BoxHeader::new(*box_type, data.len() as u64 + HEADER_SIZE).write(writer)?;
                     writer.write_all(data)?;
@@ -232,7 +442,10 @@ mod tests {
 
     #[test]
     fn test_meta_mdir_empty() {
-        let src_box = MetaBox::Mdir { ilst: None };
+        let src_box = MetaBox::Mdir {
+            ilst: None,
+            unknown_boxes: Vec::new(),
+        };
 
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -251,6 +464,11 @@ mod tests {
     fn test_meta_mdir() {
         let src_box = MetaBox::Mdir {
             ilst: Some(IlstBox::default()),
+            unknown_boxes: vec![UnknownChildBox::new(
+                BoxType::FreeBox,
+                Bytes::from_static(&[0; 4]),
+                2,
+            )],
         };
 
         let mut buf = Vec::new();
@@ -275,13 +493,85 @@ mod tests {
 
         let meta_box = MetaBox::read_box(&mut reader, header.size).unwrap();
 
-        // this contains \xa9too box in the ilst
-        // it designates the tool that created the file, but is not yet supported by this crate
+        // this contains \xa9too box in the ilst, which designates the tool
+        // that created the file
+        let MetaBox::Mdir {
+            ilst: Some(ilst),
+            unknown_boxes,
+        } = meta_box
+        else {
+            panic!("expected an mdir meta box with an ilst");
+        };
+        assert!(unknown_boxes.is_empty());
+        assert!(ilst.unknown_boxes.is_empty());
+        assert_eq!(
+            ilst.encoder(),
+            Some("TMPGEnc Video Mastering Works 7 Version 7.0.15.17".into())
+        );
+    }
+
+    #[test]
+    fn test_meta_mdta() {
+        let mut src_box = MetaBox::new_mdta();
+        src_box
+            .set_mdta_value(
+                "com.apple.quicktime.make",
+                DataType::Text,
+                b"Apple".to_vec(),
+            )
+            .unwrap();
+        src_box
+            .set_mdta_value(
+                "com.apple.quicktime.model",
+                DataType::Text,
+                b"iPhone".to_vec(),
+            )
+            .unwrap();
+        src_box
+            .set_mdta_value(
+                "com.apple.quicktime.location.ISO6709",
+                DataType::Text,
+                b"+37.3349-122.0090+012.345/".to_vec(),
+            )
+            .unwrap();
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::MetaBox);
+        assert_eq!(header.size, src_box.box_size());
+
+        let mut dst_box = MetaBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(dst_box, src_box);
+        assert_eq!(dst_box.mdta_entries().len(), 3);
         assert_eq!(
-            meta_box,
-            MetaBox::Mdir {
-                ilst: Some(IlstBox::default())
-            }
+            dst_box
+                .mdta_value("com.apple.quicktime.model")
+                .unwrap()
+                .data,
+            b"iPhone"
+        );
+
+        // Removing a key renumbers the items after it.
+        assert!(dst_box.remove_mdta_value("com.apple.quicktime.make"));
+        assert!(!dst_box.remove_mdta_value("com.apple.quicktime.make"));
+        let keys: Vec<_> = dst_box.mdta_entries().iter().map(|(key, _)| *key).collect();
+        assert_eq!(
+            keys,
+            [
+                "com.apple.quicktime.model",
+                "com.apple.quicktime.location.ISO6709"
+            ]
+        );
+        assert_eq!(
+            dst_box
+                .mdta_value("com.apple.quicktime.model")
+                .unwrap()
+                .data,
+            b"iPhone"
         );
     }
 
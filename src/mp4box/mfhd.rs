@@ -9,6 +9,9 @@ pub struct MfhdBox {
     pub version: u8,
     pub flags: u32,
     pub sequence_number: u32,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Default for MfhdBox {
@@ -17,6 +20,7 @@ impl Default for MfhdBox {
             version: 0,
             flags: 0,
             sequence_number: 1,
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -48,6 +52,10 @@ impl Mp4Box for MfhdBox {
         let s = format!("sequence_number={}", self.sequence_number);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MfhdBox {
@@ -63,6 +71,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MfhdBox {
             version,
             flags,
             sequence_number,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -91,6 +100,7 @@ mod tests {
             version: 0,
             flags: 0,
             sequence_number: 1,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::mp4box::*;
+use crate::mp4box::{mfro::MfroBox, tfra::TfraBox};
+
+/// Movie fragment random access, usually found at the end of a fragmented
+/// file.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct MfraBox {
+    #[serde(rename = "tfra")]
+    pub tfras: Vec<TfraBox>,
+    pub mfro: MfroBox,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
+}
+
+impl MfraBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::MfraBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE
+            + self.tfras.iter().map(|x| x.box_size()).sum::<u64>()
+            + self.mfro.box_size()
+            + unknown_boxes_size(&self.unknown_boxes)
+    }
+
+    /// The random access points of `track_id`.
+    pub fn tfra(&self, track_id: u32) -> Option<&TfraBox> {
+        self.tfras.iter().find(|tfra| tfra.track_id == track_id)
+    }
+
+    /// Find the `mfra` through the `mfro` at the end of the `size` bytes of
+    /// `reader`, without scanning the fragments before it.
+    ///
+    /// Returns `None` if the file doesn't end with an `mfro`, or it doesn't
+    /// point at an `mfra`.
+    pub fn read_from_end<R: Read + Seek>(reader: &mut R, size: u64) -> Result<Option<Self>> {
+        let mfro_size = MfroBox::default().box_size();
+        if size < mfro_size {
+            return Ok(None);
+        }
+        reader.seek(SeekFrom::Start(size - mfro_size))?;
+        let header = BoxHeader::read(reader)?;
+        if header.name != BoxType::MfroBox || header.size != mfro_size {
+            return Ok(None);
+        }
+        let mfro = MfroBox::read_box(reader, header.size)?;
+
+        let Some(start) = size.checked_sub(mfro.mfra_size as u64) else {
+            return Ok(None);
+        };
+        reader.seek(SeekFrom::Start(start))?;
+        let header = BoxHeader::read(reader)?;
+        if header.name != BoxType::MfraBox || header.size != mfro.mfra_size as u64 {
+            return Ok(None);
+        }
+        Ok(Some(MfraBox::read_box(reader, header.size)?))
+    }
+}
+
+impl Mp4Box for MfraBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("tfras={}", self.tfras.len());
+        Ok(s)
+    }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for MfraBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for MfraBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let mut tfras = Vec::new();
+        let mut mfro = None;
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
+
+        let end = start + size;
+        read_children(reader, BoxType::MfraBox, end, ctx, |reader, header, _| {
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "mfra box contains a box with a larger size than it",
+                ));
+            }
+
+            match name {
+                BoxType::TfraBox => {
+                    tfras.push(TfraBox::read_box(reader, s)?);
+                }
+                BoxType::MfroBox => {
+                    mfro = Some(MfroBox::read_box(reader, s)?);
+                }
+                _ => {
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
+                }
+            }
+            position += 1;
+            Ok(())
+        })?;
+
+        let Some(mfro) = mfro else {
+            return Err(Error::BoxNotFound(BoxType::MfroBox));
+        };
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(MfraBox {
+            tfras,
+            mfro,
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for MfraBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        let mut children = ChildWriter::new(&mut *writer, &self.unknown_boxes);
+        for tfra in self.tfras.iter() {
+            children.write(tfra)?;
+        }
+        children.finish()?;
+        // The mfro has to come last, and always holds the actual size.
+        MfroBox {
+            mfra_size: size as u32,
+            ..self.mfro.clone()
+        }
+        .write_box(writer)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::tfra::TfraEntry;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_mfra() {
+        let mut src_box = MfraBox {
+            tfras: vec![
+                TfraBox::new(
+                    1,
+                    vec![TfraEntry {
+                        time: 0,
+                        moof_offset: 700,
+                        traf_number: 1,
+                        trun_number: 1,
+                        sample_number: 1,
+                    }],
+                ),
+                TfraBox::new(2, Vec::new()),
+            ],
+            ..Default::default()
+        };
+        src_box.mfro.mfra_size = src_box.box_size() as u32;
+
+        let mut buf = vec![0u8; 100];
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len() as u64, 100 + src_box.box_size());
+
+        let size = buf.len() as u64;
+        let mut reader = Cursor::new(&buf);
+        let dst_box = MfraBox::read_from_end(&mut reader, size).unwrap().unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.byte_offset(), Some(100));
+        assert_eq!(dst_box.tfra(2).unwrap().entries.len(), 0);
+
+        let mut reader = Cursor::new(&buf[..buf.len() - 1]);
+        assert!(
+            MfraBox::read_from_end(&mut reader, size - 1)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -14,6 +14,11 @@ pub struct MinfBox {
 
     pub dinf: DinfBox,
     pub stbl: StblBox,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl MinfBox {
@@ -31,6 +36,7 @@ impl MinfBox {
         }
         size += self.dinf.box_size();
         size += self.stbl.box_size();
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
 }
@@ -52,22 +58,31 @@ impl Mp4Box for MinfBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MinfBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for MinfBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut vmhd = None;
         let mut smhd = None;
         let mut dinf = None;
         let mut stbl = None;
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::MinfBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -83,19 +98,18 @@ impl<R: Read + Seek> ReadBox<&mut R> for MinfBox {
                     smhd = Some(SmhdBox::read_box(reader, s)?);
                 }
                 BoxType::DinfBox => {
-                    dinf = Some(DinfBox::read_box(reader, s)?);
+                    dinf = Some(DinfBox::read_box_with(reader, s, ctx)?);
                 }
                 BoxType::StblBox => {
-                    stbl = Some(StblBox::read_box(reader, s)?);
+                    stbl = Some(StblBox::read_box_with(reader, s, ctx)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         if dinf.is_none() {
             return Err(Error::BoxNotFound(BoxType::DinfBox));
@@ -111,6 +125,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for MinfBox {
             smhd,
             dinf: dinf.unwrap(),
             stbl: stbl.unwrap(),
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -119,15 +135,17 @@ impl<W: Write> WriteBox<&mut W> for MinfBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
         if let Some(ref vmhd) = self.vmhd {
-            vmhd.write_box(writer)?;
+            children.write(vmhd)?;
         }
         if let Some(ref smhd) = self.smhd {
-            smhd.write_box(writer)?;
+            children.write(smhd)?;
         }
-        self.dinf.write_box(writer)?;
-        self.stbl.write_box(writer)?;
+        children.write(&self.dinf)?;
+        children.write(&self.stbl)?;
+        children.finish()?;
 
         Ok(size)
     }
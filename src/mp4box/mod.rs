@@ -54,11 +54,15 @@
 //!         trun
 //! mdat
 //! free
+//! mfra
+//!     tfra
+//!     mfro
 //!
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::convert::TryInto;
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::fmt;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 
 use crate::*;
 
@@ -74,17 +78,21 @@ pub mod ftyp;
 pub mod hdlr;
 pub mod hev1;
 pub mod ilst;
+pub mod keys;
 pub mod mdhd;
 pub mod mdia;
 pub mod mehd;
 pub mod meta;
 pub mod mfhd;
+pub mod mfra;
+pub mod mfro;
 pub mod minf;
 pub mod moof;
 pub mod moov;
 pub mod mp4a;
 pub mod mvex;
 pub mod mvhd;
+pub mod sgpd;
 pub mod smhd;
 pub mod stbl;
 pub mod stco;
@@ -95,6 +103,7 @@ pub mod stsz;
 pub mod stts;
 pub mod tfdt;
 pub mod tfhd;
+pub mod tfra;
 pub mod tkhd;
 pub mod traf;
 pub mod trak;
@@ -119,11 +128,14 @@ pub use ftyp::FtypBox;
 pub use hdlr::HdlrBox;
 pub use hev1::Hev1Box;
 pub use ilst::IlstBox;
+pub use keys::KeysBox;
 pub use mdhd::MdhdBox;
 pub use mdia::MdiaBox;
 pub use mehd::MehdBox;
 pub use meta::MetaBox;
 pub use mfhd::MfhdBox;
+pub use mfra::MfraBox;
+pub use mfro::MfroBox;
 pub use minf::MinfBox;
 pub use moof::MoofBox;
 pub use moov::MoovBox;
@@ -131,16 +143,18 @@ pub use mp4a::Mp4aBox;
 pub use mvex::MvexBox;
 pub use mvhd::MvhdBox;
 pub use sidx::SidxBox;
+pub use sgpd::SgpdBox;
 pub use smhd::SmhdBox;
 pub use stbl::StblBox;
 pub use stco::StcoBox;
 pub use stsc::StscBox;
-pub use stsd::StsdBox;
+pub use stsd::{SampleEntry, StsdBox};
 pub use stss::StssBox;
 pub use stsz::StszBox;
 pub use stts::SttsBox;
 pub use tfdt::TfdtBox;
 pub use tfhd::TfhdBox;
+pub use tfra::{TfraBox, TfraEntry};
 pub use tkhd::TkhdBox;
 pub use traf::TrafBox;
 pub use trak::TrakBox;
@@ -211,6 +225,7 @@ boxtype! {
     SttsBox => 0x73747473,
     CttsBox => 0x63747473,
     StssBox => 0x73747373,
+    SgpdBox => 0x73677064,
     StscBox => 0x73747363,
     StszBox => 0x7374737A,
     StcoBox => 0x7374636F,
@@ -235,13 +250,42 @@ boxtype! {
     Vp09Box => 0x76703039,
     DataBox => 0x64617461,
     IlstBox => 0x696c7374,
+    KeysBox => 0x6b657973,
     NameBox => 0xa96e616d,
     DayBox => 0xa9646179,
     CovrBox => 0x636f7672,
     DescBox => 0x64657363,
+    ArtBox => 0xa9415254,
+    AartBox => 0x61415254,
+    AlbBox => 0xa9616c62,
+    GenBox => 0xa967656e,
+    GnreBox => 0x676e7265,
+    TrknBox => 0x74726b6e,
+    DiskBox => 0x6469736b,
+    WrtBox => 0xa9777274,
+    CmtBox => 0xa9636d74,
+    TooBox => 0xa9746f6f,
+    TmpoBox => 0x746d706f,
+    CpilBox => 0x6370696c,
+    RtngBox => 0x72746e67,
+    StikBox => 0x7374696b,
+    TvshBox => 0x74767368,
+    TvesBox => 0x74766573,
+    TvsnBox => 0x7476736e,
+    LyrBox => 0xa96c7972,
+    SonmBox => 0x736f6e6d,
+    SoarBox => 0x736f6172,
+    SoalBox => 0x736f616c,
+    SoaaBox => 0x736f6161,
+    FreeformBox => 0x2d2d2d2d,
+    MeanBox => 0x6d65616e,
+    ItemNameBox => 0x6e616d65,
     SidxBox => 0x73696478,
     WideBox => 0x77696465,
-    WaveBox => 0x77617665
+    WaveBox => 0x77617665,
+    MfraBox => 0x6d667261,
+    TfraBox => 0x74667261,
+    MfroBox => 0x6d66726f
 }
 
 pub trait Mp4Box: Sized {
@@ -325,6 +369,22 @@ impl BoxHeader {
     }
 }
 
+/// File offset of a box that was read from a file, or `None` for a box that
+/// was built in memory.
+///
+/// Offsets are ignored when comparing boxes, so a box read back from a file
+/// equals the one that was written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoxOffset(pub Option<u64>);
+
+impl PartialEq for BoxOffset {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for BoxOffset {}
+
 pub fn read_box_header_ext<R: Read>(reader: &mut R) -> Result<(u8, u32)> {
     let version = reader.read_u8()?;
     let flags = reader.read_u24::<BigEndian>()?;
@@ -341,6 +401,42 @@ pub fn box_start<R: Seek>(seeker: &mut R) -> Result<u64> {
     Ok(seeker.stream_position()? - HEADER_SIZE)
 }
 
+/// Like [box_start], but also right for a header with a 64-bit size, which
+/// is told apart by reading the header back.
+pub(crate) fn header_start<R: Read + Seek>(
+    reader: &mut R,
+    name: BoxType,
+    size: u64,
+) -> Result<u64> {
+    let data_start = reader.stream_position()?;
+    if data_start < 16 {
+        return Ok(data_start - HEADER_SIZE);
+    }
+
+    // An 8-byte header leaves its size and type where the largesize would
+    // be, and those never read as `size + 8`.
+    let mut largesize = [0u8; 16];
+    largesize[..4].copy_from_slice(&1u32.to_be_bytes());
+    largesize[4..8].copy_from_slice(&u32::from(name).to_be_bytes());
+    largesize[8..].copy_from_slice(&(size + 8).to_be_bytes());
+
+    let mut header = [0u8; 16];
+    reader.seek(SeekFrom::Start(data_start - 16))?;
+    reader.read_exact(&mut header)?;
+    reader.seek(SeekFrom::Start(data_start))?;
+    if header == largesize {
+        Ok(data_start - 16)
+    } else {
+        Ok(data_start - HEADER_SIZE)
+    }
+}
+
+/// The end of the box whose header was just read. A 64-bit size leaves
+/// `header.size` short of the 16-byte header, so go by where the data starts.
+pub(crate) fn box_end<S: Seek>(seeker: &mut S, header: &BoxHeader) -> Result<u64> {
+    Ok(seeker.stream_position()? + header.size - HEADER_SIZE)
+}
+
 pub fn skip_bytes<S: Seek>(seeker: &mut S, size: u64) -> Result<()> {
     seeker.seek(SeekFrom::Current(size as i64))?;
     Ok(())
@@ -357,6 +453,330 @@ pub fn skip_box<S: Seek>(seeker: &mut S, size: u64) -> Result<()> {
     Ok(())
 }
 
+/// In-memory copy of a box that reports positions as if it were still at
+/// `base` in the file, so the offsets recorded while parsing it are exact.
+pub(crate) struct BufferedBox {
+    base: u64,
+    data: Cursor<Vec<u8>>,
+}
+
+impl BufferedBox {
+    /// `data` holds the whole box, header included. The reader is positioned
+    /// `header_len` bytes in, just past the header.
+    pub(crate) fn new(base: u64, data: Vec<u8>, header_len: u64) -> Self {
+        let mut data = Cursor::new(data);
+        data.set_position(header_len);
+        Self { base, data }
+    }
+}
+
+impl Read for BufferedBox {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.data.read(buf)
+    }
+}
+
+impl Seek for BufferedBox {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => {
+                SeekFrom::Start(pos.checked_sub(self.base).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "seek before start of box")
+                })?)
+            }
+            pos => pos,
+        };
+        Ok(self.base + self.data.seek(pos)?)
+    }
+}
+
+/// A child box that isn't parsed, kept so that it can be written back
+/// unchanged and in place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownChildBox {
+    pub box_type: BoxType,
+    pub data: Bytes,
+
+    /// How many sibling boxes come before it.
+    pub position: usize,
+}
+
+impl UnknownChildBox {
+    pub fn new(box_type: BoxType, data: Bytes, position: usize) -> Self {
+        Self {
+            box_type,
+            data,
+            position,
+        }
+    }
+}
+
+/// Read the payload of a child box that isn't parsed, so that it can be
+/// written back unchanged. `end` is the end of its parent, which the payload
+/// has to fit in, and `position` how many siblings were read before it.
+pub fn read_unknown_box<R: Read + Seek>(
+    reader: &mut R,
+    name: BoxType,
+    size: u64,
+    end: u64,
+    position: usize,
+) -> Result<UnknownChildBox> {
+    let size = size
+        .checked_sub(HEADER_SIZE)
+        .ok_or(Error::InvalidData("box size too small"))?;
+    // Check the size read from the file before allocating for it.
+    if size > end.saturating_sub(reader.stream_position()?) {
+        return Err(Error::InvalidData("box size larger than its parent"));
+    }
+    let mut data = vec![0u8; size as usize];
+    reader.read_exact(&mut data)?;
+    Ok(UnknownChildBox::new(name, Bytes::from(data), position))
+}
+
+pub fn unknown_boxes_size(boxes: &[UnknownChildBox]) -> u64 {
+    boxes
+        .iter()
+        .map(|unknown| HEADER_SIZE + unknown.data.len() as u64)
+        .sum()
+}
+
+/// Writes the children of a box in order, putting its unknown children back
+/// at their positions among them.
+pub(crate) struct ChildWriter<'a, W> {
+    writer: &'a mut W,
+    unknown_boxes: &'a [UnknownChildBox],
+    written: usize,
+}
+
+impl<'a, W: Write> ChildWriter<'a, W> {
+    pub(crate) fn new(writer: &'a mut W, unknown_boxes: &'a [UnknownChildBox]) -> Self {
+        Self {
+            writer,
+            unknown_boxes,
+            written: 0,
+        }
+    }
+
+    pub(crate) fn write<B>(&mut self, child: &B) -> Result<()>
+    where
+        B: for<'b> WriteBox<&'b mut W>,
+    {
+        self.write_with(|writer| child.write_box(writer).map(|_| ()))
+    }
+
+    /// Write a child that isn't a box of its own type.
+    pub(crate) fn write_with(&mut self, write: impl FnOnce(&mut W) -> Result<()>) -> Result<()> {
+        self.write_unknown_boxes(false)?;
+        write(self.writer)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Write the unknown children that come after all the others.
+    pub(crate) fn finish(mut self) -> Result<()> {
+        self.write_unknown_boxes(true)
+    }
+
+    fn write_unknown_boxes(&mut self, all: bool) -> Result<()> {
+        while let Some((unknown, rest)) = self.unknown_boxes.split_first()
+            && (all || unknown.position <= self.written)
+        {
+            let size = HEADER_SIZE + unknown.data.len() as u64;
+            BoxHeader::new(unknown.box_type, size).write(self.writer)?;
+            self.writer.write_all(&unknown.data)?;
+            self.unknown_boxes = rest;
+            self.written += 1;
+        }
+        Ok(())
+    }
+}
+
+/// A box that could not be parsed while reading in lenient mode.
+#[derive(Debug)]
+pub struct ParseWarning {
+    /// Types of the enclosing boxes, outermost first, followed by the type of
+    /// the failed box if its header could be read.
+    pub path: Vec<BoxType>,
+    /// Offset of the failed box.
+    pub offset: u64,
+    pub reason: Error,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path: Vec<String> = self.path.iter().map(|t| t.to_string()).collect();
+        write!(
+            f,
+            "{} at offset {}: {}",
+            path.join("."),
+            self.offset,
+            self.reason
+        )
+    }
+}
+
+/// Options and diagnostics for reading a tree of boxes, handed down from each
+/// box to its children.
+#[derive(Default)]
+pub(crate) struct ReadContext {
+    /// Skip boxes that fail to parse instead of failing the whole read.
+    lenient: bool,
+    path: Vec<BoxType>,
+    warnings: Vec<ParseWarning>,
+}
+
+impl ReadContext {
+    pub(crate) fn lenient() -> Self {
+        Self {
+            lenient: true,
+            ..Self::default()
+        }
+    }
+
+    /// The boxes that were skipped because they failed to parse.
+    pub(crate) fn into_warnings(self) -> Vec<ParseWarning> {
+        self.warnings
+    }
+
+    /// Record a box at `offset` that failed to parse, so the caller can skip
+    /// it. `path` leads from the box currently being read
+    /// to the failed one. Unless reading leniently, the error is returned as
+    /// is.
+    pub(crate) fn recover(&mut self, path: &[BoxType], offset: u64, err: Error) -> Result<()> {
+        if !self.lenient {
+            return Err(err);
+        }
+        self.warnings.push(ParseWarning {
+            path: [self.path.as_slice(), path].concat(),
+            offset,
+            reason: err,
+        });
+        Ok(())
+    }
+}
+
+/// Reading of boxes with children, which follows the options of a
+/// [ReadContext] and records its diagnostics. [ReadBox] reads them strictly.
+pub(crate) trait ReadBoxWith<T>: Sized {
+    fn read_box_with(_: T, size: u64, ctx: &mut ReadContext) -> Result<Self>;
+}
+
+/// Read the child boxes of `parent` up to `end`, passing each header to
+/// `read_child` with the reader positioned just past it.
+///
+/// When reading leniently, a child that fails to parse is skipped. If even its
+/// header can't be read, or its size runs past `end`, the loop ends early,
+/// leaving the caller to skip to the end of `parent` as usual.
+pub(crate) fn read_children<R, F>(
+    reader: &mut R,
+    parent: BoxType,
+    end: u64,
+    ctx: &mut ReadContext,
+    mut read_child: F,
+) -> Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&mut R, BoxHeader, &mut ReadContext) -> Result<()>,
+{
+    ctx.path.push(parent);
+    let result = (|| {
+        let mut current = reader.stream_position()?;
+        while current < end {
+            let mut child = None;
+            let mut child_end = None;
+            let read = BoxHeader::read(reader).and_then(|header| {
+                child = Some(header.name);
+                if header.size < HEADER_SIZE {
+                    return Err(Error::InvalidData("box size too small"));
+                }
+                child_end = Some(box_end(reader, &header)?);
+                read_child(reader, header, ctx)
+            });
+            if let Err(err) = read {
+                ctx.recover(child.as_slice(), current, err)?;
+                match child_end {
+                    Some(child_end) if child_end <= end => {
+                        reader.seek(SeekFrom::Start(child_end))?;
+                    }
+                    // Without a size to go by, the rest of the parent is lost.
+                    _ => break,
+                }
+            }
+            current = reader.stream_position()?;
+        }
+        Ok(())
+    })();
+    ctx.path.pop();
+    result
+}
+
+/// Check the header of a top-level box against the `size` of the file.
+/// Returns `None` for a box of size zero, which ends the top-level boxes.
+pub(crate) fn top_level_header(header: BoxHeader, size: u64) -> Result<Option<BoxHeader>> {
+    if header.size > size {
+        return Err(Error::InvalidData(
+            "file contains a box with a larger size than it",
+        ));
+    }
+
+    // Break if size zero BoxHeader, which can result in dead-loop.
+    if header.size == 0 {
+        return Ok(None);
+    }
+    if header.size < HEADER_SIZE {
+        return Err(Error::InvalidData("box size too small"));
+    }
+    Ok(Some(header))
+}
+
+/// Read the top-level boxes from the current position up to `size`, passing
+/// each header and the offset of its box to `read_box` with the reader
+/// positioned just past the header. The reader then moves on to the end of
+/// the box, however much of it `read_box` read.
+///
+/// The loop stops at a box of size zero, or once `read_box` returns `false`.
+/// When reading leniently, a box that fails to parse also ends it, as nothing
+/// after a broken top-level box can be trusted. Returns the offset of the box
+/// the loop stopped at, or of the end of the last box.
+pub(crate) fn read_top_level_boxes<R, F>(
+    reader: &mut R,
+    size: u64,
+    ctx: &mut ReadContext,
+    mut read_box: F,
+) -> Result<u64>
+where
+    R: Read + Seek,
+    F: FnMut(&mut R, BoxHeader, u64, &mut ReadContext) -> Result<bool>,
+{
+    let mut current = reader.stream_position()?;
+    while current < size {
+        let mut child = None;
+        let read = BoxHeader::read(reader).and_then(|header| {
+            child = Some(header.name);
+            let Some(header) = top_level_header(header, size)? else {
+                return Ok(None);
+            };
+            let end = box_end(reader, &header)?;
+            if !read_box(reader, header, current, ctx)? {
+                return Ok(None);
+            }
+            Ok(Some(end))
+        });
+        match read {
+            Ok(Some(end)) => {
+                reader.seek(SeekFrom::Start(end))?;
+                current = end;
+            }
+            Ok(None) => break,
+            Err(err) => {
+                ctx.recover(child.as_slice(), current, err)?;
+                break;
+            }
+        }
+    }
+    Ok(current)
+}
+
 pub fn write_zeros<W: Write>(writer: &mut W, size: u64) -> Result<()> {
     for _ in 0..size {
         writer.write_u8(0)?;
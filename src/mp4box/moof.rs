@@ -10,6 +10,11 @@ pub struct MoofBox {
 
     #[serde(rename = "traf")]
     pub trafs: Vec<TrafBox>,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl MoofBox {
@@ -22,6 +27,7 @@ impl MoofBox {
         for traf in self.trafs.iter() {
             size += traf.box_size();
         }
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
 }
@@ -43,20 +49,29 @@ impl Mp4Box for MoofBox {
         let s = format!("trafs={}", self.trafs.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MoofBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for MoofBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut mfhd = None;
         let mut trafs = Vec::new();
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::MoofBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -69,16 +84,16 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoofBox {
                     mfhd = Some(MfhdBox::read_box(reader, s)?);
                 }
                 BoxType::TrafBox => {
-                    let traf = TrafBox::read_box(reader, s)?;
+                    let traf = TrafBox::read_box_with(reader, s, ctx)?;
                     trafs.push(traf);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         if mfhd.is_none() {
             return Err(Error::BoxNotFound(BoxType::MfhdBox));
@@ -89,6 +104,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoofBox {
         Ok(MoofBox {
             mfhd: mfhd.unwrap(),
             trafs,
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -97,11 +114,13 @@ impl<W: Write> WriteBox<&mut W> for MoofBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
-        self.mfhd.write_box(writer)?;
+        children.write(&self.mfhd)?;
         for traf in self.trafs.iter() {
-            traf.write_box(writer)?;
+            children.write(traf)?;
         }
+        children.finish()?;
         Ok(0)
     }
 }
@@ -20,6 +20,11 @@ pub struct MoovBox {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub udta: Option<UdtaBox>,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl MoovBox {
@@ -32,14 +37,27 @@ impl MoovBox {
         for trak in self.traks.iter() {
             size += trak.box_size();
         }
+        if let Some(mvex) = &self.mvex {
+            size += mvex.box_size();
+        }
         if let Some(meta) = &self.meta {
             size += meta.box_size();
         }
         if let Some(udta) = &self.udta {
             size += udta.box_size();
         }
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
+
+    /// The QuickTime `mdta` metadata, kept in `moov/meta` or `moov/udta/meta`.
+    pub fn quicktime_metadata(&self) -> Option<&MetaBox> {
+        let udta_meta = self.udta.as_ref().and_then(|udta| udta.meta.as_ref());
+        [self.meta.as_ref(), udta_meta]
+            .into_iter()
+            .flatten()
+            .find(|meta| matches!(meta, MetaBox::Mdta { .. }))
+    }
 }
 
 impl Mp4Box for MoovBox {
@@ -59,10 +77,20 @@ impl Mp4Box for MoovBox {
         let s = format!("traks={}", self.traks.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for MoovBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut mvhd = None;
@@ -70,12 +98,11 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
         let mut udta = None;
         let mut mvex = None;
         let mut traks = Vec::new();
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::MoovBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -88,26 +115,25 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
                     mvhd = Some(MvhdBox::read_box(reader, s)?);
                 }
                 BoxType::MetaBox => {
-                    meta = Some(MetaBox::read_box(reader, s)?);
+                    meta = Some(MetaBox::read_box_with(reader, s, ctx)?);
                 }
                 BoxType::MvexBox => {
-                    mvex = Some(MvexBox::read_box(reader, s)?);
+                    mvex = Some(MvexBox::read_box_with(reader, s, ctx)?);
                 }
                 BoxType::TrakBox => {
-                    let trak = TrakBox::read_box(reader, s)?;
+                    let trak = TrakBox::read_box_with(reader, s, ctx)?;
                     traks.push(trak);
                 }
                 BoxType::UdtaBox => {
-                    udta = Some(UdtaBox::read_box(reader, s)?);
+                    udta = Some(UdtaBox::read_box_with(reader, s, ctx)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         if mvhd.is_none() {
             return Err(Error::BoxNotFound(BoxType::MvhdBox));
@@ -121,6 +147,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
             udta,
             mvex,
             traks,
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -129,17 +157,22 @@ impl<W: Write> WriteBox<&mut W> for MoovBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
-        self.mvhd.write_box(writer)?;
+        children.write(&self.mvhd)?;
         for trak in self.traks.iter() {
-            trak.write_box(writer)?;
+            children.write(trak)?;
+        }
+        if let Some(mvex) = &self.mvex {
+            children.write(mvex)?;
         }
         if let Some(meta) = &self.meta {
-            meta.write_box(writer)?;
+            children.write(meta)?;
         }
         if let Some(udta) = &self.udta {
-            udta.write_box(writer)?;
+            children.write(udta)?;
         }
+        children.finish()?;
         Ok(0)
     }
 }
@@ -148,16 +181,36 @@ impl<W: Write> WriteBox<&mut W> for MoovBox {
 mod tests {
     use super::*;
     use crate::mp4box::BoxHeader;
+    use crate::mp4box::trex::TrexBox;
     use std::io::Cursor;
 
     #[test]
     fn test_moov() {
         let src_box = MoovBox {
             mvhd: MvhdBox::default(),
-            mvex: None, // XXX mvex is not written currently
+            mvex: Some(MvexBox {
+                mehd: None,
+                trexs: vec![
+                    TrexBox {
+                        track_id: 1,
+                        ..Default::default()
+                    },
+                    TrexBox {
+                        track_id: 2,
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
             traks: vec![],
             meta: Some(MetaBox::default()),
             udta: Some(UdtaBox::default()),
+            unknown_boxes: vec![UnknownChildBox::new(
+                BoxType::UnknownBox(0x75756964),
+                Bytes::from_static(&[1; 20]),
+                4,
+            )],
+            ..Default::default()
         };
 
         let mut buf = Vec::new();
@@ -189,4 +242,47 @@ mod tests {
         let dst_box = MoovBox::read_box(&mut reader, header.size).unwrap();
         assert_eq!(dst_box, src_box);
     }
+
+    #[test]
+    fn test_moov_nested_unknown_boxes() {
+        // Between tkhd and mdia.
+        let mut trak = TrakBox {
+            unknown_boxes: vec![UnknownChildBox::new(
+                BoxType::UnknownBox(0x74726566),
+                Bytes::from_static(&[2; 12]),
+                1,
+            )],
+            ..Default::default()
+        };
+        trak.mdia.minf.stbl.stco = Some(Default::default());
+        // One right after stsd, one after all the others.
+        trak.mdia.minf.stbl.unknown_boxes = vec![
+            UnknownChildBox::new(
+                BoxType::UnknownBox(0x73756273),
+                Bytes::from_static(&[3; 16]),
+                1,
+            ),
+            UnknownChildBox::new(
+                BoxType::UnknownBox(0x73626770),
+                Bytes::from_static(&[4; 20]),
+                6,
+            ),
+        ];
+        let src_box = MoovBox {
+            traks: vec![trak],
+            ..Default::default()
+        };
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::MoovBox);
+        assert_eq!(header.size, src_box.box_size());
+
+        let dst_box = MoovBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(dst_box, src_box);
+    }
 }
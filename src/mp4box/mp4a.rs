@@ -13,6 +13,9 @@ pub struct Mp4aBox {
     #[serde(with = "value_u32")]
     pub samplerate: FixedPointU16,
     pub esds: Option<EsdsBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Default for Mp4aBox {
@@ -23,6 +26,7 @@ impl Default for Mp4aBox {
             samplesize: 16,
             samplerate: FixedPointU16::new(48000),
             esds: Some(EsdsBox::default()),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -35,6 +39,7 @@ impl Mp4aBox {
             samplesize: 16,
             samplerate: FixedPointU16::new(config.freq_index.freq() as u16),
             esds: Some(EsdsBox::new(config)),
+            offset: BoxOffset::default(),
         }
     }
 
@@ -73,6 +78,10 @@ impl Mp4Box for Mp4aBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for Mp4aBox {
@@ -131,6 +140,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for Mp4aBox {
             samplesize,
             samplerate,
             esds,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -163,6 +173,9 @@ pub struct EsdsBox {
     pub version: u8,
     pub flags: u32,
     pub es_desc: ESDescriptor,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl EsdsBox {
@@ -171,6 +184,7 @@ impl EsdsBox {
             version: 0,
             flags: 0,
             es_desc: ESDescriptor::new(config),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -195,6 +209,10 @@ impl Mp4Box for EsdsBox {
     fn summary(&self) -> Result<String> {
         Ok(String::new())
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for EsdsBox {
@@ -228,6 +246,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for EsdsBox {
             version,
             flags,
             es_desc: es_desc.unwrap(),
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -285,7 +304,7 @@ fn size_of_length(size: u32) -> u32 {
 fn write_desc<W: Write>(writer: &mut W, tag: u8, size: u32) -> Result<u64> {
     writer.write_u8(tag)?;
 
-    if size as u64 > std::u32::MAX as u64 {
+    if size as u64 > u32::MAX as u64 {
         return Err(Error::InvalidData("invalid descriptor length range"));
     }
 
@@ -644,7 +663,9 @@ mod tests {
                     },
                     sl_config: SLConfigDescriptor::default(),
                 },
+                ..Default::default()
             }),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -667,6 +688,7 @@ mod tests {
             samplesize: 16,
             samplerate: FixedPointU16::new(48000),
             esds: None,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
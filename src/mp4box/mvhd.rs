@@ -21,6 +21,9 @@ pub struct MvhdBox {
     pub matrix: tkhd::Matrix,
 
     pub next_track_id: u32,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl MvhdBox {
@@ -53,6 +56,7 @@ impl Default for MvhdBox {
             matrix: tkhd::Matrix::default(),
             volume: FixedPointU8::new(1),
             next_track_id: 1,
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -83,6 +87,10 @@ impl Mp4Box for MvhdBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for MvhdBox {
@@ -145,6 +153,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MvhdBox {
             volume,
             matrix,
             next_track_id,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -214,6 +223,7 @@ mod tests {
             volume: FixedPointU8::new(1),
             matrix: tkhd::Matrix::default(),
             next_track_id: 1,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -241,6 +251,7 @@ mod tests {
             volume: FixedPointU8::new(1),
             matrix: tkhd::Matrix::default(),
             next_track_id: 1,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
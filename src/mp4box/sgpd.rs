@@ -0,0 +1,276 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+/// Grouping type of audio roll recovery entries.
+pub const ROLL: FourCC = FourCC { value: *b"roll" };
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SgpdBox {
+    pub version: u8,
+    pub flags: u32,
+    pub grouping_type: FourCC,
+
+    /// Size of every entry in version 1, or 0 if each has its own.
+    pub default_length: u32,
+
+    /// Version 2 and later.
+    pub default_sample_description_index: u32,
+
+    #[serde(skip_serializing)]
+    pub entries: Vec<Vec<u8>>,
+
+    /// The entry count and the bytes of all entries, when the entries have
+    /// different sizes that the box doesn't give (version 0). `entries` is
+    /// empty then, and these are written back unchanged.
+    #[serde(skip)]
+    pub unparsed_entries: Option<(u32, Bytes)>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
+}
+
+impl SgpdBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::SgpdBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE;
+        size += 4; // grouping_type
+        if self.version == 1 {
+            size += 4; // default_length
+        }
+        if self.version >= 2 {
+            size += 4; // default_sample_description_index
+        }
+        size += 4; // entry_count
+        if let Some((_, data)) = &self.unparsed_entries {
+            size += data.len() as u64;
+        }
+        for entry in self.entries.iter() {
+            if self.version == 1 && self.default_length == 0 {
+                size += 4; // description_length
+            }
+            size += entry.len() as u64;
+        }
+        size
+    }
+
+    /// The `roll_distance` of each entry of a `roll` group: the number of
+    /// samples to decode before a sample can be played, negated.
+    pub fn roll_distances(&self) -> Vec<i16> {
+        if self.grouping_type != ROLL {
+            return Vec::new();
+        }
+        self.entries
+            .iter()
+            .filter(|entry| entry.len() >= 2)
+            .map(|entry| i16::from_be_bytes([entry[0], entry[1]]))
+            .collect()
+    }
+}
+
+impl Mp4Box for SgpdBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let entry_count = match &self.unparsed_entries {
+            Some((entry_count, _)) => *entry_count as usize,
+            None => self.entries.len(),
+        };
+        let s = format!(
+            "grouping_type={} entries={}",
+            self.grouping_type, entry_count
+        );
+        Ok(s)
+    }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for SgpdBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+        let end = start + size;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+        let grouping_type = FourCC::from(reader.read_u32::<BigEndian>()?);
+        let default_length = if version == 1 {
+            reader.read_u32::<BigEndian>()?
+        } else {
+            0
+        };
+        let default_sample_description_index = if version >= 2 {
+            reader.read_u32::<BigEndian>()?
+        } else {
+            0
+        };
+        let entry_count = reader.read_u32::<BigEndian>()?;
+
+        let remaining = end.saturating_sub(reader.stream_position()?);
+        if version != 1 && entry_count > 0 && remaining % entry_count as u64 != 0 {
+            // The entries have different sizes, which only version 1 gives,
+            // so keep them as they are.
+            if u64::from(entry_count) > remaining {
+                return Err(Error::InvalidData(
+                    "sgpd entry_count indicates more entries than could fit in the box",
+                ));
+            }
+            let mut data = vec![0u8; remaining as usize];
+            reader.read_exact(&mut data)?;
+
+            return Ok(SgpdBox {
+                version,
+                flags,
+                grouping_type,
+                default_length,
+                default_sample_description_index,
+                entries: Vec::new(),
+                unparsed_entries: Some((entry_count, Bytes::from(data))),
+                offset: BoxOffset(Some(start)),
+            });
+        }
+
+        // Only version 1 gives the entry size, otherwise the entries share
+        // the rest of the box evenly.
+        let fixed_length = match version {
+            1 if default_length > 0 => Some(default_length as u64),
+            1 => None,
+            _ if entry_count > 0 => Some(remaining / entry_count as u64),
+            _ => None,
+        };
+        let min_entry_size = fixed_length.unwrap_or(4).max(1);
+        if u64::from(entry_count) > remaining / min_entry_size {
+            return Err(Error::InvalidData(
+                "sgpd entry_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let length = match fixed_length {
+                Some(length) => length,
+                None => reader.read_u32::<BigEndian>()? as u64,
+            };
+            if length > end.saturating_sub(reader.stream_position()?) {
+                return Err(Error::InvalidData("sgpd entry is larger than the box"));
+            }
+            let mut entry = vec![0u8; length as usize];
+            reader.read_exact(&mut entry)?;
+            entries.push(entry);
+        }
+
+        skip_bytes_to(reader, end)?;
+
+        Ok(SgpdBox {
+            version,
+            flags,
+            grouping_type,
+            default_length,
+            default_sample_description_index,
+            entries,
+            unparsed_entries: None,
+            offset: BoxOffset(Some(start)),
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for SgpdBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+        writer.write_u32::<BigEndian>((&self.grouping_type).into())?;
+        if self.version == 1 {
+            writer.write_u32::<BigEndian>(self.default_length)?;
+        }
+        if self.version >= 2 {
+            writer.write_u32::<BigEndian>(self.default_sample_description_index)?;
+        }
+        if let Some((entry_count, data)) = &self.unparsed_entries {
+            writer.write_u32::<BigEndian>(*entry_count)?;
+            writer.write_all(data)?;
+            return Ok(size);
+        }
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            if self.version == 1 && self.default_length == 0 {
+                writer.write_u32::<BigEndian>(entry.len() as u32)?;
+            }
+            writer.write_all(entry)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_sgpd() {
+        for (version, default_length) in [(0, 0), (1, 2), (1, 0), (2, 0)] {
+            let src_box = SgpdBox {
+                version,
+                flags: 0,
+                grouping_type: ROLL,
+                default_length,
+                default_sample_description_index: if version >= 2 { 1 } else { 0 },
+                entries: vec![(-1i16).to_be_bytes().to_vec(), 2i16.to_be_bytes().to_vec()],
+                ..Default::default()
+            };
+            let mut buf = Vec::new();
+            src_box.write_box(&mut buf).unwrap();
+            assert_eq!(buf.len(), src_box.box_size() as usize);
+
+            let mut reader = Cursor::new(&buf);
+            let header = BoxHeader::read(&mut reader).unwrap();
+            assert_eq!(header.name, BoxType::SgpdBox);
+            assert_eq!(src_box.box_size(), header.size);
+
+            let dst_box = SgpdBox::read_box(&mut reader, header.size).unwrap();
+            assert_eq!(src_box, dst_box);
+            assert_eq!(dst_box.roll_distances(), vec![-1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_sgpd_v0_uneven_entries() {
+        let src_box = SgpdBox {
+            grouping_type: FourCC::from(*b"seig"),
+            unparsed_entries: Some((2, Bytes::from_static(&[1, 2, 3, 4, 5]))),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::SgpdBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = SgpdBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert!(dst_box.entries.is_empty());
+    }
+}
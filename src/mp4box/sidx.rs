@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
@@ -12,6 +12,14 @@ pub struct SidxBox {
     pub earliest_presentation_time: u64,
     pub first_offset: u64,
     pub references: Vec<SidxReference>,
+
+    /// Size of the box as read, when it isn't `box_size`, for example with
+    /// a 64-bit size or trailing bytes. `first_offset` counts from its end.
+    #[serde(skip)]
+    pub read_size: Option<u64>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
@@ -24,6 +32,33 @@ pub struct SidxReference {
     pub sap_delta_time: u32,
 }
 
+impl SidxBox {
+    /// The references along with the time and file offset each one starts
+    /// at, given the offset of this box. Times are in `timescale`.
+    pub fn reference_positions(
+        &self,
+        sidx_box_offset: u64,
+    ) -> impl Iterator<Item = (u64, u64, &SidxReference)> {
+        let size = self.read_size.unwrap_or_else(|| self.box_size());
+        self.positions_after(sidx_box_offset + size)
+    }
+
+    fn positions_after(
+        &self,
+        sidx_box_end: u64,
+    ) -> impl Iterator<Item = (u64, u64, &SidxReference)> {
+        // The first reference starts first_offset bytes after the sidx.
+        let mut time = self.earliest_presentation_time;
+        let mut offset = sidx_box_end + self.first_offset;
+        self.references.iter().map(move |reference| {
+            let position = (time, offset, reference);
+            time += reference.subsegment_duration as u64;
+            offset += reference.referenced_size as u64;
+            position
+        })
+    }
+}
+
 impl Mp4Box for SidxBox {
     fn box_type(&self) -> BoxType {
         BoxType::SidxBox
@@ -31,7 +66,7 @@ impl Mp4Box for SidxBox {
 
     fn box_size(&self) -> u64 {
         let mut size = HEADER_SIZE + HEADER_EXT_SIZE;
-        size += 12; // reference_id + timescale + reserved
+        size += 8; // reference_id + timescale
         size += if self.version == 0 { 8 } else { 16 }; // earliest_presentation_time + first_offset
         size += 4; // reserved + reference_count (2 bytes each)
         
@@ -56,12 +91,16 @@ impl Mp4Box for SidxBox {
         );
         Ok(s)
     }
-    
-    
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
-impl<R: Read> ReadBox<&mut R> for SidxBox {
+impl<R: Read + Seek> ReadBox<&mut R> for SidxBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = header_start(reader, BoxType::SidxBox, size)?;
+        let end = box_end(reader, &BoxHeader::new(BoxType::SidxBox, size))?;
         let (version, flags) = read_box_header_ext(reader)?;
         
         let reference_id = reader.read_u32::<BigEndian>()?;
@@ -108,8 +147,10 @@ impl<R: Read> ReadBox<&mut R> for SidxBox {
                 sap_delta_time,
             });
         }
-        
-        Ok(SidxBox {
+
+        skip_bytes_to(reader, end)?;
+
+        let mut sidx = SidxBox {
             version,
             flags,
             reference_id,
@@ -117,7 +158,13 @@ impl<R: Read> ReadBox<&mut R> for SidxBox {
             earliest_presentation_time,
             first_offset,
             references,
-        })
+            read_size: None,
+            offset: BoxOffset(Some(start)),
+        };
+        if end - start != sidx.box_size() {
+            sidx.read_size = Some(end - start);
+        }
+        Ok(sidx)
     }
 }
 
@@ -161,31 +208,15 @@ impl<T: Write> WriteBox<&mut T> for SidxBox {
 }
 
 pub fn sidx_to_seek_segments(sidx: &SidxBox, sidx_box_offset: u64, sidx_box_size: u64) -> Vec<SeekSegment> {
-    let mut segments = Vec::new();
     let timescale = sidx.timescale as f64;
-
-    // Base offset is the position after the SIDX box + first_offset from SIDX
-    let base_offset = sidx_box_offset + sidx_box_size + sidx.first_offset;
-
-    let mut current_time = sidx.earliest_presentation_time as f64 / timescale;
-    let mut current_offset = base_offset;
-
-    for reference in &sidx.references {
-        let duration_seconds = reference.subsegment_duration as f64 / timescale;
-
-        segments.push(SeekSegment {
-            time_seconds: current_time,
-            duration_seconds,
-            byte_offset: current_offset,
+    sidx.positions_after(sidx_box_offset + sidx_box_size)
+        .map(|(time, offset, reference)| SeekSegment {
+            time_seconds: time as f64 / timescale,
+            duration_seconds: reference.subsegment_duration as f64 / timescale,
+            byte_offset: offset,
             byte_size: reference.referenced_size,
-        });
-
-        // Update for next segment
-        current_time += duration_seconds;
-        current_offset += reference.referenced_size as u64;
-    }
-
-    segments
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -210,33 +241,17 @@ pub fn parse_dash_sidx(
     sidx_box_offset: u64,
     sidx_box_size: u64
 ) -> Vec<DashSegment> {
-    let mut segments = Vec::new();
     let timescale = sidx.timescale as f64;
-
-    // Base offset is the position after the SIDX box + first_offset from SIDX
-    let base_offset = sidx_box_offset + sidx_box_size + sidx.first_offset;
-
-    let mut current_time = sidx.earliest_presentation_time as f64 / timescale;
-    let mut current_offset = base_offset;
-
-    for reference in &sidx.references {
-        let duration_seconds = reference.subsegment_duration as f64 / timescale;
-
-        segments.push(DashSegment {
-            start_time_seconds: current_time,
-            duration_seconds,
-            byte_range_start: current_offset,
-            byte_range_end: current_offset + reference.referenced_size as u64 - 1,
+    sidx.positions_after(sidx_box_offset + sidx_box_size)
+        .map(|(time, offset, reference)| DashSegment {
+            start_time_seconds: time as f64 / timescale,
+            duration_seconds: reference.subsegment_duration as f64 / timescale,
+            byte_range_start: offset,
+            byte_range_end: offset + reference.referenced_size as u64 - 1,
             contains_sap: reference.starts_with_sap,
             sap_type: reference.sap_type,
-        });
-
-        // Update for next segment
-        current_time += duration_seconds;
-        current_offset += reference.referenced_size as u64;
-    }
-
-    segments
+        })
+        .collect()
 }
 
 // Utility function to find the segment containing a specific time
@@ -256,3 +271,122 @@ pub fn get_range_request(base_url: &str, segment: &DashSegment) -> String {
         segment.byte_range_end
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_sidx() {
+        let src_box = SidxBox {
+            version: 0,
+            flags: 0,
+            reference_id: 1,
+            timescale: 1000,
+            earliest_presentation_time: 0,
+            first_offset: 0,
+            references: vec![SidxReference {
+                reference_type: 0,
+                referenced_size: 1024,
+                subsegment_duration: 2000,
+                starts_with_sap: true,
+                sap_type: 1,
+                sap_delta_time: 0,
+            }],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::SidxBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = SidxBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_sidx_trailing_bytes() {
+        let src_box = SidxBox {
+            timescale: 1000,
+            first_offset: 10,
+            references: vec![
+                SidxReference {
+                    referenced_size: 1024,
+                    subsegment_duration: 2000,
+                    ..Default::default()
+                },
+                SidxReference {
+                    referenced_size: 512,
+                    subsegment_duration: 1000,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        BoxHeader::new(BoxType::SidxBox, src_box.box_size() + 4)
+            .write(&mut buf)
+            .unwrap();
+        let mut payload = Vec::new();
+        src_box.write_box(&mut payload).unwrap();
+        buf.extend_from_slice(&payload[HEADER_SIZE as usize..]);
+        buf.extend_from_slice(&[0; 4]);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let dst_box = SidxBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(dst_box.read_size, Some(buf.len() as u64));
+
+        // The references start after the trailing bytes.
+        let positions: Vec<_> = dst_box
+            .reference_positions(100)
+            .map(|(time, offset, _)| (time, offset))
+            .collect();
+        let first = 100 + buf.len() as u64 + 10;
+        assert_eq!(positions, vec![(0, first), (2000, first + 1024)]);
+        let segments = parse_dash_sidx(&dst_box, 100, buf.len() as u64);
+        assert_eq!(segments[1].byte_range_start, first + 1024);
+        assert_eq!(segments[1].start_time_seconds, 2.0);
+    }
+
+    #[test]
+    fn test_sidx_largesize() {
+        let src_box = SidxBox {
+            timescale: 1000,
+            first_offset: 10,
+            references: vec![SidxReference {
+                referenced_size: 1024,
+                subsegment_duration: 2000,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut payload = Vec::new();
+        src_box.write_box(&mut payload).unwrap();
+        let mut buf = Vec::new();
+        buf.write_u32::<BigEndian>(1).unwrap();
+        buf.write_u32::<BigEndian>(BoxType::SidxBox.into()).unwrap();
+        buf.write_u64::<BigEndian>(payload.len() as u64 + 8).unwrap();
+        buf.extend_from_slice(&payload[HEADER_SIZE as usize..]);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let dst_box = SidxBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(reader.position(), buf.len() as u64);
+        assert_eq!(dst_box.read_size, Some(buf.len() as u64));
+        assert_eq!(dst_box.byte_offset(), Some(0));
+
+        // The references start after the 16-byte header and the payload.
+        let positions: Vec<_> = dst_box
+            .reference_positions(100)
+            .map(|(time, offset, _)| (time, offset))
+            .collect();
+        assert_eq!(positions, vec![(0, 100 + buf.len() as u64 + 10)]);
+    }
+}
@@ -11,6 +11,9 @@ pub struct SmhdBox {
 
     #[serde(with = "value_i16")]
     pub balance: FixedPointI8,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl SmhdBox {
@@ -29,6 +32,7 @@ impl Default for SmhdBox {
             version: 0,
             flags: 0,
             balance: FixedPointI8::new_raw(0),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -50,6 +54,10 @@ impl Mp4Box for SmhdBox {
         let s = format!("balance={}", self.balance.value());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for SmhdBox {
@@ -66,6 +74,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for SmhdBox {
             version,
             flags,
             balance,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -96,6 +105,7 @@ mod tests {
             version: 0,
             flags: 0,
             balance: FixedPointI8::new_raw(-1),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
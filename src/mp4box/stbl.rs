@@ -3,8 +3,8 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 use crate::mp4box::{
-    co64::Co64Box, ctts::CttsBox, stco::StcoBox, stsc::StscBox, stsd::StsdBox, stss::StssBox,
-    stsz::StszBox, stts::SttsBox,
+    co64::Co64Box, ctts::CttsBox, sgpd::SgpdBox, stco::StcoBox, stsc::StscBox, stsd::StsdBox,
+    stss::StssBox, stsz::StszBox, stts::SttsBox,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
@@ -25,6 +25,14 @@ pub struct StblBox {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub co64: Option<Co64Box>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sgpds: Vec<SgpdBox>,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl StblBox {
@@ -50,6 +58,10 @@ impl StblBox {
         if let Some(ref co64) = self.co64 {
             size += co64.box_size();
         }
+        for sgpd in self.sgpds.iter() {
+            size += sgpd.box_size();
+        }
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
 }
@@ -71,10 +83,20 @@ impl Mp4Box for StblBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for StblBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut stsd = None;
@@ -85,12 +107,12 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
         let mut stsz = None;
         let mut stco = None;
         let mut co64 = None;
+        let mut sgpds = Vec::new();
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::StblBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -100,7 +122,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
 
             match name {
                 BoxType::StsdBox => {
-                    stsd = Some(StsdBox::read_box(reader, s)?);
+                    stsd = Some(StsdBox::read_box_with(reader, s, ctx)?);
                 }
                 BoxType::SttsBox => {
                     stts = Some(SttsBox::read_box(reader, s)?);
@@ -123,13 +145,16 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
                 BoxType::Co64Box => {
                     co64 = Some(Co64Box::read_box(reader, s)?);
                 }
+                BoxType::SgpdBox => {
+                    sgpds.push(SgpdBox::read_box(reader, s)?);
+                }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         if stsd.is_none() {
             return Err(Error::BoxNotFound(BoxType::StsdBox));
@@ -158,6 +183,9 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
             stsz: stsz.unwrap(),
             stco,
             co64,
+            sgpds,
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -166,23 +194,28 @@ impl<W: Write> WriteBox<&mut W> for StblBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
-        self.stsd.write_box(writer)?;
-        self.stts.write_box(writer)?;
+        children.write(&self.stsd)?;
+        children.write(&self.stts)?;
         if let Some(ref ctts) = self.ctts {
-            ctts.write_box(writer)?;
+            children.write(ctts)?;
         }
         if let Some(ref stss) = self.stss {
-            stss.write_box(writer)?;
+            children.write(stss)?;
         }
-        self.stsc.write_box(writer)?;
-        self.stsz.write_box(writer)?;
+        children.write(&self.stsc)?;
+        children.write(&self.stsz)?;
         if let Some(ref stco) = self.stco {
-            stco.write_box(writer)?;
+            children.write(stco)?;
         }
         if let Some(ref co64) = self.co64 {
-            co64.write_box(writer)?;
+            children.write(co64)?;
+        }
+        for sgpd in self.sgpds.iter() {
+            children.write(sgpd)?;
         }
+        children.finish()?;
 
         Ok(size)
     }
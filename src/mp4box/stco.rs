@@ -12,6 +12,9 @@ pub struct StcoBox {
 
     #[serde(skip_serializing)]
     pub entries: Vec<u32>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl StcoBox {
@@ -41,6 +44,10 @@ impl Mp4Box for StcoBox {
         let s = format!("entries={}", self.entries.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for StcoBox {
@@ -75,6 +82,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StcoBox {
             version,
             flags,
             entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -109,6 +117,7 @@ impl std::convert::TryFrom<&co64::Co64Box> for StcoBox {
             version: 0,
             flags: 0,
             entries,
+            offset: BoxOffset::default(),
         })
     }
 }
@@ -125,6 +134,7 @@ mod tests {
             version: 0,
             flags: 0,
             entries: vec![267, 1970, 2535, 2803, 11843, 22223, 33584],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
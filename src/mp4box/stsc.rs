@@ -12,6 +12,9 @@ pub struct StscBox {
 
     #[serde(skip_serializing)]
     pub entries: Vec<StscEntry>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl StscBox {
@@ -49,6 +52,10 @@ impl Mp4Box for StscBox {
         let s = format!("entries={}", self.entries.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for StscBox {
@@ -108,6 +115,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StscBox {
             version,
             flags,
             entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -155,6 +163,7 @@ mod tests {
                     first_sample: 19026,
                 },
             ],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
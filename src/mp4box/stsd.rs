@@ -11,20 +11,69 @@ pub struct StsdBox {
     pub version: u8,
     pub flags: u32,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub avc1: Option<Avc1Box>,
+    #[serde(rename = "entry")]
+    pub entries: Vec<SampleEntry>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub hev1: Option<Hev1Box>,
+    #[serde(skip)]
+    pub offset: BoxOffset,
+}
+
+/// A sample description. Samples refer to these by 1-based index through
+/// `stsc` or `tfhd`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleEntry {
+    Avc1(Avc1Box),
+    Hev1(Hev1Box),
+    Vp09(Vp09Box),
+    Mp4a(Mp4aBox),
+    Tx3g(Tx3gBox),
+
+    // Kept as is, so the indices of the other entries are preserved.
+    #[serde(skip)]
+    Unknown(BoxType, Vec<u8>),
+}
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub vp09: Option<Vp09Box>,
+impl SampleEntry {
+    pub fn box_type(&self) -> BoxType {
+        match self {
+            Self::Avc1(avc1) => avc1.box_type(),
+            Self::Hev1(hev1) => hev1.box_type(),
+            Self::Vp09(vp09) => vp09.box_type(),
+            Self::Mp4a(mp4a) => mp4a.box_type(),
+            Self::Tx3g(tx3g) => tx3g.box_type(),
+            Self::Unknown(box_type, _) => *box_type,
+        }
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub mp4a: Option<Mp4aBox>,
+    pub fn box_size(&self) -> u64 {
+        match self {
+            Self::Avc1(avc1) => avc1.box_size(),
+            Self::Hev1(hev1) => hev1.box_size(),
+            Self::Vp09(vp09) => vp09.box_size(),
+            Self::Mp4a(mp4a) => mp4a.box_size(),
+            Self::Tx3g(tx3g) => tx3g.box_size(),
+            Self::Unknown(_, data) => HEADER_SIZE + data.len() as u64,
+        }
+    }
+}
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tx3g: Option<Tx3gBox>,
+impl<W: Write> WriteBox<&mut W> for SampleEntry {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        match self {
+            Self::Avc1(avc1) => avc1.write_box(writer),
+            Self::Hev1(hev1) => hev1.write_box(writer),
+            Self::Vp09(vp09) => vp09.write_box(writer),
+            Self::Mp4a(mp4a) => mp4a.write_box(writer),
+            Self::Tx3g(tx3g) => tx3g.write_box(writer),
+            Self::Unknown(box_type, data) => {
+                let size = self.box_size();
+                BoxHeader::new(*box_type, size).write(writer)?;
+                writer.write_all(data)?;
+                Ok(size)
+            }
+        }
+    }
 }
 
 impl StsdBox {
@@ -34,19 +83,57 @@ impl StsdBox {
 
     pub fn get_size(&self) -> u64 {
         let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 4;
-        if let Some(ref avc1) = self.avc1 {
-            size += avc1.box_size();
-        } else if let Some(ref hev1) = self.hev1 {
-            size += hev1.box_size();
-        } else if let Some(ref vp09) = self.vp09 {
-            size += vp09.box_size();
-        } else if let Some(ref mp4a) = self.mp4a {
-            size += mp4a.box_size();
-        } else if let Some(ref tx3g) = self.tx3g {
-            size += tx3g.box_size();
+        for entry in self.entries.iter() {
+            size += entry.box_size();
         }
         size
     }
+
+    /// The entry with the 1-based `sample_description_index`.
+    pub fn entry(&self, sample_description_index: u32) -> Option<&SampleEntry> {
+        let i = sample_description_index.checked_sub(1)?;
+        self.entries.get(i as usize)
+    }
+
+    /// The first `avc1` entry.
+    pub fn avc1(&self) -> Option<&Avc1Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            SampleEntry::Avc1(avc1) => Some(avc1),
+            _ => None,
+        })
+    }
+
+    /// The first `hev1` entry.
+    pub fn hev1(&self) -> Option<&Hev1Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            SampleEntry::Hev1(hev1) => Some(hev1),
+            _ => None,
+        })
+    }
+
+    /// The first `vp09` entry.
+    pub fn vp09(&self) -> Option<&Vp09Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            SampleEntry::Vp09(vp09) => Some(vp09),
+            _ => None,
+        })
+    }
+
+    /// The first `mp4a` entry.
+    pub fn mp4a(&self) -> Option<&Mp4aBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            SampleEntry::Mp4a(mp4a) => Some(mp4a),
+            _ => None,
+        })
+    }
+
+    /// The first `tx3g` entry.
+    pub fn tx3g(&self) -> Option<&Tx3gBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            SampleEntry::Tx3g(tx3g) => Some(tx3g),
+            _ => None,
+        })
+    }
 }
 
 impl Mp4Box for StsdBox {
@@ -66,48 +153,70 @@ impl Mp4Box for StsdBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for StsdBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for StsdBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let (version, flags) = read_box_header_ext(reader)?;
 
-        reader.read_u32::<BigEndian>()?; // XXX entry_count
-
-        let mut avc1 = None;
-        let mut hev1 = None;
-        let mut vp09 = None;
-        let mut mp4a = None;
-        let mut tx3g = None;
-
-        // Get box header.
-        let header = BoxHeader::read(reader)?;
-        let BoxHeader { name, size: s } = header;
-        if s > size {
-            return Err(Error::InvalidData(
-                "stsd box contains a box with a larger size than it",
-            ));
-        }
+        let entry_count = reader.read_u32::<BigEndian>()?;
 
-        match name {
-            BoxType::Avc1Box => {
-                avc1 = Some(Avc1Box::read_box(reader, s)?);
+        let mut entries = Vec::new();
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        for _ in 0..entry_count {
+            if current >= end {
+                break;
             }
-            BoxType::Hev1Box => {
-                hev1 = Some(Hev1Box::read_box(reader, s)?);
-            }
-            BoxType::Vp09Box => {
-                vp09 = Some(Vp09Box::read_box(reader, s)?);
-            }
-            BoxType::Mp4aBox => {
-                mp4a = Some(Mp4aBox::read_box(reader, s)?);
-            }
-            BoxType::Tx3gBox => {
-                tx3g = Some(Tx3gBox::read_box(reader, s)?);
+
+            let mut path = vec![BoxType::StsdBox];
+            let entry = BoxHeader::read(reader).and_then(|header| {
+                let BoxHeader { name, size: s } = header;
+                path.push(name);
+                if s > size {
+                    return Err(Error::InvalidData(
+                        "stsd box contains a box with a larger size than it",
+                    ));
+                }
+                if s < HEADER_SIZE {
+                    return Err(Error::InvalidData("sample entry box size too small"));
+                }
+
+                Ok(match name {
+                    BoxType::Avc1Box => SampleEntry::Avc1(Avc1Box::read_box(reader, s)?),
+                    BoxType::Hev1Box => SampleEntry::Hev1(Hev1Box::read_box(reader, s)?),
+                    BoxType::Vp09Box => SampleEntry::Vp09(Vp09Box::read_box(reader, s)?),
+                    BoxType::Mp4aBox => SampleEntry::Mp4a(Mp4aBox::read_box(reader, s)?),
+                    BoxType::Tx3gBox => SampleEntry::Tx3g(Tx3gBox::read_box(reader, s)?),
+                    _ => {
+                        let mut data = vec![0u8; (s - HEADER_SIZE) as usize];
+                        reader.read_exact(&mut data)?;
+                        SampleEntry::Unknown(name, data)
+                    }
+                })
+            });
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(err) => {
+                    // Keep the entries read so far when reading leniently.
+                    ctx.recover(&path, current, err)?;
+                    break;
+                }
             }
-            _ => {}
+
+            current = reader.stream_position()?;
         }
 
         skip_bytes_to(reader, start + size)?;
@@ -115,11 +224,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for StsdBox {
         Ok(StsdBox {
             version,
             flags,
-            avc1,
-            hev1,
-            vp09,
-            mp4a,
-            tx3g,
+            entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -131,20 +237,54 @@ impl<W: Write> WriteBox<&mut W> for StsdBox {
 
         write_box_header_ext(writer, self.version, self.flags)?;
 
-        writer.write_u32::<BigEndian>(1)?; // entry_count
-
-        if let Some(ref avc1) = self.avc1 {
-            avc1.write_box(writer)?;
-        } else if let Some(ref hev1) = self.hev1 {
-            hev1.write_box(writer)?;
-        } else if let Some(ref vp09) = self.vp09 {
-            vp09.write_box(writer)?;
-        } else if let Some(ref mp4a) = self.mp4a {
-            mp4a.write_box(writer)?;
-        } else if let Some(ref tx3g) = self.tx3g {
-            tx3g.write_box(writer)?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            entry.write_box(writer)?;
         }
 
         Ok(size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_stsd_entries() {
+        let src_box = StsdBox {
+            version: 0,
+            flags: 0,
+            entries: vec![
+                SampleEntry::Avc1(Avc1Box::default()),
+                SampleEntry::Unknown(BoxType::UnknownBox(0x61766333), vec![0, 1, 2, 3]),
+                SampleEntry::Avc1(Avc1Box {
+                    width: 1920,
+                    height: 1080,
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::StsdBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = StsdBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(
+            dst_box.entry(2).unwrap().box_type(),
+            BoxType::UnknownBox(0x61766333)
+        );
+        assert!(matches!(dst_box.entry(3), Some(SampleEntry::Avc1(avc1)) if avc1.width == 1920));
+        assert!(dst_box.entry(0).is_none());
+        assert!(dst_box.entry(4).is_none());
+    }
+}
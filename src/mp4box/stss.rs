@@ -12,6 +12,9 @@ pub struct StssBox {
 
     #[serde(skip_serializing)]
     pub entries: Vec<u32>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl StssBox {
@@ -41,6 +44,10 @@ impl Mp4Box for StssBox {
         let s = format!("entries={}", self.entries.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for StssBox {
@@ -75,6 +82,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StssBox {
             version,
             flags,
             entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -107,6 +115,7 @@ mod tests {
             version: 0,
             flags: 0,
             entries: vec![1, 61, 121, 181, 241, 301, 361, 421, 481],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
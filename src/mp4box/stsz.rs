@@ -14,6 +14,9 @@ pub struct StszBox {
 
     #[serde(skip_serializing)]
     pub sample_sizes: Vec<u32>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl StszBox {
@@ -48,6 +51,10 @@ impl Mp4Box for StszBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for StszBox {
@@ -92,6 +99,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StszBox {
             sample_size,
             sample_count,
             sample_sizes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -132,6 +140,7 @@ mod tests {
             sample_size: 1165,
             sample_count: 12,
             sample_sizes: vec![],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -154,6 +163,7 @@ mod tests {
             sample_size: 0,
             sample_count: 9,
             sample_sizes: vec![1165, 11, 11, 8545, 10126, 10866, 9643, 9351, 7730],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
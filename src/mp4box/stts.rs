@@ -12,6 +12,9 @@ pub struct SttsBox {
 
     #[serde(skip_serializing)]
     pub entries: Vec<SttsEntry>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl SttsBox {
@@ -47,6 +50,10 @@ impl Mp4Box for SttsBox {
         let s = format!("entries={}", self.entries.len());
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for SttsBox {
@@ -84,6 +91,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for SttsBox {
             version,
             flags,
             entries,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -126,6 +134,7 @@ mod tests {
                     sample_delta: 512,
                 },
             ],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
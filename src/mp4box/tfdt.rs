@@ -9,6 +9,9 @@ pub struct TfdtBox {
     pub version: u8,
     pub flags: u32,
     pub base_media_decode_time: u64,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl TfdtBox {
@@ -44,6 +47,10 @@ impl Mp4Box for TfdtBox {
         let s = format!("base_media_decode_time={}", self.base_media_decode_time);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for TfdtBox {
@@ -66,6 +73,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for TfdtBox {
             version,
             flags,
             base_media_decode_time,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -101,6 +109,7 @@ mod tests {
             version: 0,
             flags: 0,
             base_media_decode_time: 0,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -121,6 +130,7 @@ mod tests {
             version: 1,
             flags: 0,
             base_media_decode_time: 0,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -14,6 +14,9 @@ pub struct TfhdBox {
     pub default_sample_duration: Option<u32>,
     pub default_sample_size: Option<u32>,
     pub default_sample_flags: Option<u32>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl TfhdBox {
@@ -67,6 +70,10 @@ impl Mp4Box for TfhdBox {
         let s = format!("track_id={}", self.track_id);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for TfhdBox {
@@ -112,6 +119,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for TfhdBox {
             default_sample_duration,
             default_sample_size,
             default_sample_flags,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -160,6 +168,7 @@ mod tests {
             default_sample_duration: None,
             default_sample_size: None,
             default_sample_flags: None,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -187,6 +196,7 @@ mod tests {
             default_sample_duration: Some(512),
             default_sample_size: None,
             default_sample_flags: Some(0x1010000),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
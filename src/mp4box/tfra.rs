@@ -0,0 +1,286 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+use std::mem::size_of;
+
+use crate::mp4box::*;
+
+/// Random access points of one track, pointing at the fragments that hold
+/// them.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TfraBox {
+    pub version: u8,
+    pub flags: u32,
+    pub track_id: u32,
+
+    /// Number of bytes, less one, used to store each of the traf, trun and
+    /// sample numbers.
+    pub length_size_of_traf_num: u8,
+    pub length_size_of_trun_num: u8,
+    pub length_size_of_sample_num: u8,
+
+    #[serde(skip_serializing)]
+    pub entries: Vec<TfraEntry>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
+}
+
+/// A sync sample that playback can start from.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TfraEntry {
+    /// Decode time of the sample, in the track timescale.
+    pub time: u64,
+
+    /// File offset of the `moof` holding the sample.
+    pub moof_offset: u64,
+
+    /// 1-based position of the `traf` in the `moof`, the `trun` in the `traf`
+    /// and the sample in the `trun`.
+    pub traf_number: u32,
+    pub trun_number: u32,
+    pub sample_number: u32,
+}
+
+impl TfraBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::TfraBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let entry_size = if self.version == 1 { 16 } else { 8 }
+            + self.length_size_of_traf_num as u64
+            + self.length_size_of_trun_num as u64
+            + self.length_size_of_sample_num as u64
+            + 3;
+        HEADER_SIZE + HEADER_EXT_SIZE + 12 + entry_size * self.entries.len() as u64
+    }
+
+    /// Build the box for `entries`, choosing the smallest version and
+    /// number sizes that can hold them.
+    pub fn new(track_id: u32, entries: Vec<TfraEntry>) -> Self {
+        let length_size = |max: u32| match max {
+            0..=0xff => 0,
+            0x100..=0xffff => 1,
+            0x1_0000..=0xff_ffff => 2,
+            _ => 3,
+        };
+        let max = |field: fn(&TfraEntry) -> u32| entries.iter().map(field).max().unwrap_or(0);
+        let version = entries
+            .iter()
+            .any(|entry| entry.time > u32::MAX as u64 || entry.moof_offset > u32::MAX as u64)
+            as u8;
+        TfraBox {
+            version,
+            flags: 0,
+            track_id,
+            length_size_of_traf_num: length_size(max(|entry| entry.traf_number)),
+            length_size_of_trun_num: length_size(max(|entry| entry.trun_number)),
+            length_size_of_sample_num: length_size(max(|entry| entry.sample_number)),
+            entries,
+            offset: BoxOffset::default(),
+        }
+    }
+}
+
+impl Mp4Box for TfraBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "track_id={} entries_count={}",
+            self.track_id,
+            self.entries.len()
+        );
+        Ok(s)
+    }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
+}
+
+fn read_number<R: Read>(reader: &mut R, length_size: u8) -> Result<u32> {
+    let value = reader.read_uint::<BigEndian>(length_size as usize + 1)?;
+    Ok(value as u32)
+}
+
+fn write_number<W: Write>(writer: &mut W, length_size: u8, value: u32) -> Result<()> {
+    writer.write_uint::<BigEndian>(value as u64, length_size as usize + 1)?;
+    Ok(())
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TfraBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+        let track_id = reader.read_u32::<BigEndian>()?;
+        let length_sizes = reader.read_u32::<BigEndian>()?;
+        let length_size_of_traf_num = ((length_sizes >> 4) & 0x3) as u8;
+        let length_size_of_trun_num = ((length_sizes >> 2) & 0x3) as u8;
+        let length_size_of_sample_num = (length_sizes & 0x3) as u8;
+
+        let header_size = HEADER_SIZE + HEADER_EXT_SIZE;
+        let number_of_entry = reader.read_u32::<BigEndian>()?;
+        let other_size = size_of::<u32>() * 3; // track_ID + length sizes + number_of_entry
+        let entry_size = if version == 1 { 16 } else { 8 }
+            + length_size_of_traf_num as u64
+            + length_size_of_trun_num as u64
+            + length_size_of_sample_num as u64
+            + 3;
+        if u64::from(number_of_entry)
+            > size
+                .saturating_sub(header_size)
+                .saturating_sub(other_size as u64)
+                / entry_size
+        {
+            return Err(Error::InvalidData(
+                "tfra number_of_entry indicates more entries than could fit in the box",
+            ));
+        }
+        let mut entries = Vec::with_capacity(number_of_entry as usize);
+        for _ in 0..number_of_entry {
+            let (time, moof_offset) = if version == 1 {
+                (
+                    reader.read_u64::<BigEndian>()?,
+                    reader.read_u64::<BigEndian>()?,
+                )
+            } else {
+                (
+                    reader.read_u32::<BigEndian>()? as u64,
+                    reader.read_u32::<BigEndian>()? as u64,
+                )
+            };
+            entries.push(TfraEntry {
+                time,
+                moof_offset,
+                traf_number: read_number(reader, length_size_of_traf_num)?,
+                trun_number: read_number(reader, length_size_of_trun_num)?,
+                sample_number: read_number(reader, length_size_of_sample_num)?,
+            });
+        }
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(TfraBox {
+            version,
+            flags,
+            track_id,
+            length_size_of_traf_num,
+            length_size_of_trun_num,
+            length_size_of_sample_num,
+            entries,
+            offset: BoxOffset(Some(start)),
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TfraBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        writer.write_u32::<BigEndian>(self.track_id)?;
+        let length_sizes = (self.length_size_of_traf_num as u32 & 0x3) << 4
+            | (self.length_size_of_trun_num as u32 & 0x3) << 2
+            | (self.length_size_of_sample_num as u32 & 0x3);
+        writer.write_u32::<BigEndian>(length_sizes)?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            if self.version == 1 {
+                writer.write_u64::<BigEndian>(entry.time)?;
+                writer.write_u64::<BigEndian>(entry.moof_offset)?;
+            } else {
+                writer.write_u32::<BigEndian>(entry.time as u32)?;
+                writer.write_u32::<BigEndian>(entry.moof_offset as u32)?;
+            }
+            write_number(writer, self.length_size_of_traf_num, entry.traf_number)?;
+            write_number(writer, self.length_size_of_trun_num, entry.trun_number)?;
+            write_number(writer, self.length_size_of_sample_num, entry.sample_number)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_tfra32() {
+        let src_box = TfraBox::new(
+            1,
+            vec![
+                TfraEntry {
+                    time: 0,
+                    moof_offset: 1024,
+                    traf_number: 1,
+                    trun_number: 1,
+                    sample_number: 1,
+                },
+                TfraEntry {
+                    time: 90000,
+                    moof_offset: 70000,
+                    traf_number: 2,
+                    trun_number: 1,
+                    sample_number: 300,
+                },
+            ],
+        );
+        assert_eq!(src_box.version, 0);
+        assert_eq!(src_box.length_size_of_traf_num, 0);
+        assert_eq!(src_box.length_size_of_sample_num, 1);
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::TfraBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TfraBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_tfra64() {
+        let src_box = TfraBox::new(
+            2,
+            vec![TfraEntry {
+                time: 1 << 33,
+                moof_offset: 5 << 32,
+                traf_number: 1,
+                trun_number: 70000,
+                sample_number: 1 << 30,
+            }],
+        );
+        assert_eq!(src_box.version, 1);
+        assert_eq!(src_box.length_size_of_trun_num, 2);
+        assert_eq!(src_box.length_size_of_sample_num, 3);
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let dst_box = TfraBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
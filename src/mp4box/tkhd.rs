@@ -30,6 +30,9 @@ pub struct TkhdBox {
 
     #[serde(with = "value_u32")]
     pub height: FixedPointU16,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Default for TkhdBox {
@@ -47,6 +50,7 @@ impl Default for TkhdBox {
             matrix: Matrix::default(),
             width: FixedPointU16::new(0),
             height: FixedPointU16::new(0),
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -143,6 +147,10 @@ impl Mp4Box for TkhdBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for TkhdBox {
@@ -206,6 +214,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for TkhdBox {
             matrix,
             width,
             height,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -278,6 +287,7 @@ mod tests {
             matrix: Matrix::default(),
             width: FixedPointU16::new(512),
             height: FixedPointU16::new(288),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -307,6 +317,7 @@ mod tests {
             matrix: Matrix::default(),
             width: FixedPointU16::new(512),
             height: FixedPointU16::new(288),
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
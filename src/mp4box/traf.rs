@@ -8,7 +8,12 @@ use crate::mp4box::{tfdt::TfdtBox, tfhd::TfhdBox, trun::TrunBox};
 pub struct TrafBox {
     pub tfhd: TfhdBox,
     pub tfdt: Option<TfdtBox>,
-    pub trun: Option<TrunBox>,
+    pub truns: Vec<TrunBox>,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl TrafBox {
@@ -22,9 +27,10 @@ impl TrafBox {
         if let Some(ref tfdt) = self.tfdt {
             size += tfdt.box_size();
         }
-        if let Some(ref trun) = self.trun {
+        for trun in self.truns.iter() {
             size += trun.box_size();
         }
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
 }
@@ -46,21 +52,30 @@ impl Mp4Box for TrafBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for TrafBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for TrafBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut tfhd = None;
         let mut tfdt = None;
-        let mut trun = None;
+        let mut truns = Vec::new();
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::TrafBox, end, ctx, |reader, header, _| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -76,16 +91,15 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrafBox {
                     tfdt = Some(TfdtBox::read_box(reader, s)?);
                 }
                 BoxType::TrunBox => {
-                    trun = Some(TrunBox::read_box(reader, s)?);
+                    truns.push(TrunBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         if tfhd.is_none() {
             return Err(Error::BoxNotFound(BoxType::TfhdBox));
@@ -96,7 +110,9 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrafBox {
         Ok(TrafBox {
             tfhd: tfhd.unwrap(),
             tfdt,
-            trun,
+            truns,
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -105,14 +121,16 @@ impl<W: Write> WriteBox<&mut W> for TrafBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
-        self.tfhd.write_box(writer)?;
+        children.write(&self.tfhd)?;
         if let Some(ref tfdt) = self.tfdt {
-            tfdt.write_box(writer)?;
+            children.write(tfdt)?;
         }
-        if let Some(ref trun) = self.trun {
-            trun.write_box(writer)?;
+        for trun in self.truns.iter() {
+            children.write(trun)?;
         }
+        children.finish()?;
 
         Ok(size)
     }
@@ -16,6 +16,11 @@ pub struct TrakBox {
     pub meta: Option<MetaBox>,
 
     pub mdia: MdiaBox,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl TrakBox {
@@ -29,7 +34,11 @@ impl TrakBox {
         if let Some(ref edts) = self.edts {
             size += edts.box_size();
         }
+        if let Some(ref meta) = self.meta {
+            size += meta.box_size();
+        }
         size += self.mdia.box_size();
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
 }
@@ -51,22 +60,31 @@ impl Mp4Box for TrakBox {
         let s = String::new();
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for TrakBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for TrakBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut tkhd = None;
         let mut edts = None;
         let mut meta = None;
         let mut mdia = None;
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::TrakBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -82,19 +100,18 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrakBox {
                     edts = Some(EdtsBox::read_box(reader, s)?);
                 }
                 BoxType::MetaBox => {
-                    meta = Some(MetaBox::read_box(reader, s)?);
+                    meta = Some(MetaBox::read_box_with(reader, s, ctx)?);
                 }
                 BoxType::MdiaBox => {
-                    mdia = Some(MdiaBox::read_box(reader, s)?);
+                    mdia = Some(MdiaBox::read_box_with(reader, s, ctx)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         if tkhd.is_none() {
             return Err(Error::BoxNotFound(BoxType::TkhdBox));
@@ -110,6 +127,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrakBox {
             edts,
             meta,
             mdia: mdia.unwrap(),
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -118,12 +137,17 @@ impl<W: Write> WriteBox<&mut W> for TrakBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
-        self.tkhd.write_box(writer)?;
+        children.write(&self.tkhd)?;
         if let Some(ref edts) = self.edts {
-            edts.write_box(writer)?;
+            children.write(edts)?;
+        }
+        if let Some(ref meta) = self.meta {
+            children.write(meta)?;
         }
-        self.mdia.write_box(writer)?;
+        children.write(&self.mdia)?;
+        children.finish()?;
 
         Ok(size)
     }
@@ -13,6 +13,9 @@ pub struct TrexBox {
     pub default_sample_duration: u32,
     pub default_sample_size: u32,
     pub default_sample_flags: u32,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl TrexBox {
@@ -45,6 +48,10 @@ impl Mp4Box for TrexBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for TrexBox {
@@ -69,6 +76,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrexBox {
             default_sample_duration,
             default_sample_size,
             default_sample_flags,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -106,6 +114,7 @@ mod tests {
             default_sample_duration: 1000,
             default_sample_size: 0,
             default_sample_flags: 65536,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -21,6 +21,9 @@ pub struct TrunBox {
     pub sample_flags: Vec<u32>,
     #[serde(skip_serializing)]
     pub sample_cts: Vec<u32>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl TrunBox {
@@ -76,6 +79,10 @@ impl Mp4Box for TrunBox {
         let s = format!("sample_size={}", self.sample_count);
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for TrunBox {
@@ -167,6 +174,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrunBox {
             sample_sizes,
             sample_flags,
             sample_cts,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -185,8 +193,15 @@ impl<W: Write> WriteBox<&mut W> for TrunBox {
         if let Some(v) = self.first_sample_flags {
             writer.write_u32::<BigEndian>(v)?;
         }
-        if self.sample_count != self.sample_sizes.len() as u32 {
-            return Err(Error::InvalidData("sample count out of sync"));
+        for (flag, entries) in [
+            (TrunBox::FLAG_SAMPLE_DURATION, &self.sample_durations),
+            (TrunBox::FLAG_SAMPLE_SIZE, &self.sample_sizes),
+            (TrunBox::FLAG_SAMPLE_FLAGS, &self.sample_flags),
+            (TrunBox::FLAG_SAMPLE_CTS, &self.sample_cts),
+        ] {
+            if flag & self.flags > 0 && self.sample_count != entries.len() as u32 {
+                return Err(Error::InvalidData("sample count out of sync"));
+            }
         }
         for i in 0..self.sample_count as usize {
             if TrunBox::FLAG_SAMPLE_DURATION & self.flags > 0 {
@@ -225,6 +240,34 @@ mod tests {
             first_sample_flags: None,
             sample_durations: vec![],
             sample_cts: vec![],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::TrunBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TrunBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_trun_default_sizes() {
+        let src_box = TrunBox {
+            version: 0,
+            flags: TrunBox::FLAG_DATA_OFFSET | TrunBox::FLAG_SAMPLE_DURATION,
+            data_offset: Some(120),
+            sample_count: 3,
+            sample_sizes: vec![],
+            sample_flags: vec![],
+            first_sample_flags: None,
+            sample_durations: vec![1024, 1024, 1024],
+            sample_cts: vec![],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -254,6 +297,7 @@ mod tests {
             first_sample_flags: None,
             sample_durations: vec![1165, 11, 11, 8545, 10126, 10866, 9643, 9351, 7730],
             sample_cts: vec![1165, 11, 11, 8545, 10126, 10866, 9643, 9351, 7730],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
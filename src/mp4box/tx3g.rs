@@ -13,6 +13,9 @@ pub struct Tx3gBox {
     pub bg_color_rgba: RgbaColor,
     pub box_record: [i16; 4],
     pub style_record: [u8; 12],
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
@@ -38,6 +41,7 @@ impl Default for Tx3gBox {
             },
             box_record: [0, 0, 0, 0],
             style_record: [0, 0, 0, 0, 0, 1, 0, 16, 255, 255, 255, 255],
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -66,12 +70,22 @@ impl Mp4Box for Tx3gBox {
     }
 
     fn summary(&self) -> Result<String> {
-        let s = format!("data_reference_index={} horizontal_justification={} vertical_justification={} rgba={}{}{}{}",
-            self.data_reference_index, self.horizontal_justification,
-            self.vertical_justification, self.bg_color_rgba.red,
-            self.bg_color_rgba.green, self.bg_color_rgba.blue, self.bg_color_rgba.alpha);
+        let s = format!(
+            "data_reference_index={} horizontal_justification={} vertical_justification={} rgba={}{}{}{}",
+            self.data_reference_index,
+            self.horizontal_justification,
+            self.vertical_justification,
+            self.bg_color_rgba.red,
+            self.bg_color_rgba.green,
+            self.bg_color_rgba.blue,
+            self.bg_color_rgba.alpha
+        );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for Tx3gBox {
@@ -122,6 +136,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for Tx3gBox {
             bg_color_rgba,
             box_record,
             style_record,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -173,6 +188,7 @@ mod tests {
             },
             box_record: [0, 0, 0, 0],
             style_record: [0, 0, 0, 0, 0, 1, 0, 16, 255, 255, 255, 255],
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -9,6 +9,11 @@ use crate::mp4box::*;
 pub struct UdtaBox {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<MetaBox>,
+    #[serde(skip)]
+    pub unknown_boxes: Vec<UnknownChildBox>,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl UdtaBox {
@@ -21,6 +26,7 @@ impl UdtaBox {
         if let Some(meta) = &self.meta {
             size += meta.box_size();
         }
+        size += unknown_boxes_size(&self.unknown_boxes);
         size
     }
 }
@@ -41,19 +47,28 @@ impl Mp4Box for UdtaBox {
     fn summary(&self) -> Result<String> {
         Ok(String::new())
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for UdtaBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        Self::read_box_with(reader, size, &mut ReadContext::default())
+    }
+}
+
+impl<R: Read + Seek> ReadBoxWith<&mut R> for UdtaBox {
+    fn read_box_with(reader: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = box_start(reader)?;
 
         let mut meta = None;
+        let mut unknown_boxes = Vec::new();
+        let mut position = 0;
 
-        let mut current = reader.stream_position()?;
         let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
+        read_children(reader, BoxType::UdtaBox, end, ctx, |reader, header, ctx| {
             let BoxHeader { name, size: s } = header;
             if s > size {
                 return Err(Error::InvalidData(
@@ -63,20 +78,23 @@ impl<R: Read + Seek> ReadBox<&mut R> for UdtaBox {
 
             match name {
                 BoxType::MetaBox => {
-                    meta = Some(MetaBox::read_box(reader, s)?);
+                    meta = Some(MetaBox::read_box_with(reader, s, ctx)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown_boxes.push(read_unknown_box(reader, name, s, end, position)?);
                 }
             }
-
-            current = reader.stream_position()?;
-        }
+            position += 1;
+            Ok(())
+        })?;
 
         skip_bytes_to(reader, start + size)?;
 
-        Ok(UdtaBox { meta })
+        Ok(UdtaBox {
+            meta,
+            unknown_boxes,
+            offset: BoxOffset(Some(start)),
+        })
     }
 }
 
@@ -84,10 +102,12 @@ impl<W: Write> WriteBox<&mut W> for UdtaBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+        let mut children = ChildWriter::new(writer, &self.unknown_boxes);
 
         if let Some(meta) = &self.meta {
-            meta.write_box(writer)?;
+            children.write(meta)?;
         }
+        children.finish()?;
         Ok(size)
     }
 }
@@ -100,7 +120,7 @@ mod tests {
 
     #[test]
     fn test_udta_empty() {
-        let src_box = UdtaBox { meta: None };
+        let src_box = UdtaBox::default();
 
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -119,6 +139,12 @@ mod tests {
     fn test_udta() {
         let src_box = UdtaBox {
             meta: Some(MetaBox::default()),
+            unknown_boxes: vec![UnknownChildBox::new(
+                BoxType::UnknownBox(0xa978797a),
+                Bytes::from_static(b"+00+000/"),
+                1,
+            )],
+            ..Default::default()
         };
 
         let mut buf = Vec::new();
@@ -133,4 +159,24 @@ mod tests {
         let dst_box = UdtaBox::read_box(&mut reader, header.size).unwrap();
         assert_eq!(dst_box, src_box);
     }
+
+    #[test]
+    fn test_udta_unknown_box_past_end() {
+        // An unknown child that claims all of udta, header included.
+        let mut buf = Vec::new();
+        BoxHeader::new(BoxType::UdtaBox, 24)
+            .write(&mut buf)
+            .unwrap();
+        BoxHeader::new(BoxType::UnknownBox(0xa978797a), 24)
+            .write(&mut buf)
+            .unwrap();
+        buf.extend_from_slice(&[0; 8]);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert!(matches!(
+            UdtaBox::read_box(&mut reader, header.size),
+            Err(Error::InvalidData(_))
+        ));
+    }
 }
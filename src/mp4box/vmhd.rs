@@ -10,6 +10,9 @@ pub struct VmhdBox {
     pub flags: u32,
     pub graphics_mode: u16,
     pub op_color: RgbColor,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
@@ -49,6 +52,10 @@ impl Mp4Box for VmhdBox {
         );
         Ok(s)
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for VmhdBox {
@@ -71,6 +78,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for VmhdBox {
             flags,
             graphics_mode,
             op_color,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -108,6 +116,7 @@ mod tests {
                 green: 0,
                 blue: 0,
             },
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -1,6 +1,6 @@
+use crate::Mp4Box;
 use crate::mp4box::vpcc::VpccBox;
 use crate::mp4box::*;
-use crate::Mp4Box;
 use serde::Serialize;
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
@@ -20,6 +20,9 @@ pub struct Vp09Box {
     pub depth: u16,
     pub end_code: u16,
     pub vpcc: VpccBox,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl Vp09Box {
@@ -60,7 +63,9 @@ impl Vp09Box {
                 transfer_characteristics: 0,
                 matrix_coefficients: 0,
                 codec_initialization_data_size: 0,
+                offset: BoxOffset::default(),
             },
+            offset: BoxOffset::default(),
         }
     }
 }
@@ -81,6 +86,10 @@ impl Mp4Box for Vp09Box {
     fn summary(&self) -> Result<String> {
         Ok(format!("{self:?}"))
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for Vp09Box {
@@ -147,6 +156,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for Vp09Box {
             depth,
             end_code,
             vpcc,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
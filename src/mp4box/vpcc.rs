@@ -1,5 +1,5 @@
-use crate::mp4box::*;
 use crate::Mp4Box;
+use crate::mp4box::*;
 use serde::Serialize;
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
@@ -15,6 +15,9 @@ pub struct VpccBox {
     pub transfer_characteristics: u8,
     pub matrix_coefficients: u8,
     pub codec_initialization_data_size: u16,
+
+    #[serde(skip)]
+    pub offset: BoxOffset,
 }
 
 impl VpccBox {
@@ -38,6 +41,10 @@ impl Mp4Box for VpccBox {
     fn summary(&self) -> Result<String> {
         Ok(format!("{self:?}"))
     }
+
+    fn byte_offset(&self) -> Option<u64> {
+        self.offset.0
+    }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for VpccBox {
@@ -69,6 +76,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for VpccBox {
             transfer_characteristics,
             matrix_coefficients,
             codec_initialization_data_size,
+            offset: BoxOffset(Some(start)),
         })
     }
 }
@@ -116,6 +124,7 @@ mod tests {
             transfer_characteristics: 0,
             matrix_coefficients: 0,
             codec_initialization_data_size: 0,
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
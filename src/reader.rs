@@ -1,5 +1,6 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
 use std::time::Duration;
 
 use crate::meta::MetaBox;
@@ -13,13 +14,19 @@ pub struct Mp4Reader<R> {
     pub moofs: Vec<MoofBox>,
     pub emsgs: Vec<EmsgBox>,
     pub sidx: Vec<(SidxBox, usize)>, // (SidxBox, offset)
+    pub mfra: Option<MfraBox>,
 
+    segment_index: SegmentIndex,
     tracks: HashMap<u32, Mp4Track>,
     size: u64,
 }
 
 impl<R: Read + Seek> Mp4Reader<R> {
-    pub fn read_header(mut reader: R, size: u64) -> Result<Self> {
+    pub fn read_header(reader: R, size: u64) -> Result<Self> {
+        Self::read_header_with(reader, size, &mut ReadContext::default())
+    }
+
+    fn read_header_with(mut reader: R, size: u64, ctx: &mut ReadContext) -> Result<Self> {
         let start = reader.stream_position()?;
 
         let mut ftyp = None;
@@ -28,61 +35,41 @@ impl<R: Read + Seek> Mp4Reader<R> {
         let mut moof_offsets = Vec::new();
         let mut emsgs = Vec::new();
         let mut sidx = Vec::new();
-        // let mut sidx_offsets = Vec::new();
-
-        let mut current = start;
-        while current < size {
-            // Get box header.
-            let header = BoxHeader::read(&mut reader)?;
-            let BoxHeader { name, size: s } = header;
-            if s > size {
-                return Err(Error::InvalidData(
-                    "file contains a box with a larger size than it",
-                ));
-            }
-
-            // Break if size zero BoxHeader, which can result in dead-loop.
-            if s == 0 {
-                break;
-            }
-
-            // Match and parse the atom boxes.
-            match name {
-                BoxType::FtypBox => {
-                    ftyp = Some(FtypBox::read_box(&mut reader, s)?);
-                }
-                BoxType::FreeBox => {
-                    skip_box(&mut reader, s)?;
-                }
-                BoxType::MdatBox => {
-                    skip_box(&mut reader, s)?;
-                }
-                BoxType::MoovBox => {
-                    moov = Some(MoovBox::read_box(&mut reader, s)?);
-                }
-                BoxType::MoofBox => {
-                    let moof_offset = reader.stream_position()? - 8;
-                    let moof = MoofBox::read_box(&mut reader, s)?;
-                    moofs.push(moof);
-                    moof_offsets.push(moof_offset);
-                }
-                BoxType::EmsgBox => {
-                    let emsg = EmsgBox::read_box(&mut reader, s)?;
-                    emsgs.push(emsg);
-                }
-                BoxType::SidxBox => {
-                    let sidx_offset = reader.stream_position()? - 8;
-                    let s = SidxBox::read_box(&mut reader, s)?;
-                    sidx.push((s, sidx_offset as usize));
-                    // sidx_offsets.push(sidx_offset);
-                }
-                _ => {
-                    // XXX warn!()
-                    skip_box(&mut reader, s)?;
+        let mut mfra = None;
+
+        let current =
+            read_top_level_boxes(&mut reader, size, ctx, |reader, header, offset, ctx| {
+                // Match and parse the atom boxes.
+                let BoxHeader { name, size: s } = header;
+                match name {
+                    BoxType::FtypBox => {
+                        ftyp = Some(FtypBox::read_box(reader, s)?);
+                    }
+                    BoxType::MoovBox => {
+                        moov = Some(MoovBox::read_box_with(reader, s, ctx)?);
+                    }
+                    BoxType::MoofBox => {
+                        let moof = MoofBox::read_box_with(reader, s, ctx)?;
+                        moofs.push(moof);
+                        moof_offsets.push(offset);
+                    }
+                    BoxType::EmsgBox => {
+                        let emsg = EmsgBox::read_box(reader, s)?;
+                        emsgs.push(emsg);
+                    }
+                    BoxType::SidxBox => {
+                        let s = SidxBox::read_box(reader, s)?;
+                        sidx.push((s, offset as usize));
+                    }
+                    BoxType::MfraBox => {
+                        mfra = Some(MfraBox::read_box_with(reader, s, ctx)?);
+                    }
+                    _ => {
+                        // XXX warn!()
+                    }
                 }
-            }
-            current = reader.stream_position()?;
-        }
+                Ok(true)
+            })?;
 
         if ftyp.is_none() {
             return Err(Error::BoxNotFound(BoxType::FtypBox));
@@ -92,59 +79,39 @@ impl<R: Read + Seek> Mp4Reader<R> {
         }
 
         let size = current - start;
-        let mut tracks = if let Some(moov) = &moov {
-            if moov.traks.iter().any(|trak| trak.tkhd.track_id == 0) {
-                return Err(Error::InvalidData("illegal track id 0"));
-            }
-            moov.traks
-                .iter()
-                .map(|trak| (trak.tkhd.track_id, Mp4Track::from(trak)))
-                .collect()
-        } else {
-            HashMap::new()
-        };
-
-        // Update tracks if any fragmented (moof) boxes are found.
-        if !moofs.is_empty() {
-            let mut default_sample_duration = 0;
-            if let Some(moov) = &moov {
-                if let Some(mvex) = &moov.mvex {
-                    default_sample_duration = mvex.trex.default_sample_duration
-                }
-            }
-
-            for (moof, moof_offset) in moofs.iter().zip(moof_offsets) {
-                for traf in moof.trafs.iter() {
-                    let track_id = traf.tfhd.track_id;
-                    if let Some(track) = tracks.get_mut(&track_id) {
-                        track.default_sample_duration = default_sample_duration;
-                        track.moof_offsets.push(moof_offset);
-                        track.trafs.push(traf.clone())
-                    } else {
-                        return Err(Error::TrakNotFound(track_id));
-                    }
-                }
-            }
-        }
-        if !sidx.is_empty() {
-            let sidx_offsets: Vec<usize> = sidx.iter().map(|(_, offset)| *offset).collect();
-            for (sidx_box, offset) in sidx.iter() {
-                println!("SidxBox at offset {}: {:?}", offset, sidx_box);
-            }
+        let moov = moov.unwrap();
+        if moov.traks.iter().any(|trak| trak.tkhd.track_id == 0) {
+            return Err(Error::InvalidData("illegal track id 0"));
         }
-        
+        let tracks = build_tracks(&moov, &moofs, &moof_offsets)?;
+        let segment_index = SegmentIndex::new(&sidx, &moof_offsets);
+
         Ok(Mp4Reader {
             reader,
             ftyp: ftyp.unwrap(),
-            moov: moov.unwrap(),
+            moov,
             moofs,
             emsgs,
             sidx,
+            mfra,
+            segment_index,
             size,
             tracks,
         })
     }
 
+    /// Like [Mp4Reader::read_header], but boxes that fail to parse are skipped
+    /// instead of failing the whole read. Only a box without a usable size
+    /// takes the rest of its parent with it, as does a broken top-level box.
+    ///
+    /// Returns the reader together with a warning for every skipped box. Boxes
+    /// that are required by their parent still have to be present.
+    pub fn read_header_lenient(reader: R, size: u64) -> Result<(Self, Vec<ParseWarning>)> {
+        let mut ctx = ReadContext::lenient();
+        let mp4 = Self::read_header_with(reader, size, &mut ctx)?;
+        Ok((mp4, ctx.into_warnings()))
+    }
+
     pub fn read_fragment_header<FR: Read + Seek>(
         &self,
         mut reader: FR,
@@ -155,76 +122,43 @@ impl<R: Read + Seek> Mp4Reader<R> {
         let mut moofs = Vec::new();
         let mut moof_offsets = Vec::new();
         let mut sidx = Vec::new();
+        let mut mfra = None;
 
-        let mut current = start;
-        while current < size {
-            // Get box header.
-            let header = BoxHeader::read(&mut reader)?;
-            let BoxHeader { name, size: s } = header;
-            if s > size {
-                return Err(Error::InvalidData(
-                    "file contains a box with a larger size than it",
-                ));
-            }
-
-            // Break if size zero BoxHeader, which can result in dead-loop.
-            if s == 0 {
-                break;
-            }
-
-            // Match and parse the atom boxes.
-            match name {
-                BoxType::MdatBox => {
-                    skip_box(&mut reader, s)?;
-                }
-                BoxType::MoofBox => {
-                    let moof_offset = reader.stream_position()? - 8;
-                    let moof = MoofBox::read_box(&mut reader, s)?;
-                    moofs.push(moof);
-                    moof_offsets.push(moof_offset);
-                }
-                BoxType::SidxBox => {
-                    let offset = reader.stream_position()? - 8;
-                    let s = SidxBox::read_box(&mut reader, s)?;
-                    sidx.push((s, offset as usize));
-                }
-                _ => {
-                    // XXX warn!()
-                    skip_box(&mut reader, s)?;
+        let current = read_top_level_boxes(
+            &mut reader,
+            size,
+            &mut ReadContext::default(),
+            |reader, header, offset, _| {
+                // Match and parse the atom boxes.
+                let BoxHeader { name, size: s } = header;
+                match name {
+                    BoxType::MoofBox => {
+                        let moof = MoofBox::read_box(reader, s)?;
+                        moofs.push(moof);
+                        moof_offsets.push(offset);
+                    }
+                    BoxType::SidxBox => {
+                        let s = SidxBox::read_box(reader, s)?;
+                        sidx.push((s, offset as usize));
+                    }
+                    BoxType::MfraBox => {
+                        mfra = Some(MfraBox::read_box(reader, s)?);
+                    }
+                    _ => {
+                        // XXX warn!()
+                    }
                 }
-            }
-            current = reader.stream_position()?;
-        }
+                Ok(true)
+            },
+        )?;
 
         if moofs.is_empty() {
             return Err(Error::BoxNotFound(BoxType::MoofBox));
         }
 
         let size = current - start;
-        let mut tracks: HashMap<u32, Mp4Track> = self
-            .moov
-            .traks
-            .iter()
-            .map(|trak| (trak.tkhd.track_id, Mp4Track::from(trak)))
-            .collect();
-
-        let mut default_sample_duration = 0;
-        if let Some(mvex) = &self.moov.mvex {
-            default_sample_duration = mvex.trex.default_sample_duration
-        }
-
-        for (moof, moof_offset) in moofs.iter().zip(moof_offsets) {
-            for traf in moof.trafs.iter() {
-                let track_id = traf.tfhd.track_id;
-                if let Some(track) = tracks.get_mut(&track_id) {
-                    track.default_sample_duration = default_sample_duration;
-                    track.moof_offsets.push(moof_offset);
-                    track.trafs.push(traf.clone())
-                } else {
-                    return Err(Error::TrakNotFound(track_id));
-                }
-            }
-        }
+        let tracks = build_tracks(&self.moov, &moofs, &moof_offsets)?;
+        let segment_index = SegmentIndex::new(&sidx, &moof_offsets);
 
         Ok(Mp4Reader {
             reader,
@@ -233,6 +167,8 @@ impl<R: Read + Seek> Mp4Reader<R> {
             moofs,
             emsgs: Vec::new(),
             sidx,
+            mfra,
+            segment_index,
             tracks,
             size,
         })
@@ -270,9 +206,17 @@ impl<R: Read + Seek> Mp4Reader<R> {
         &self.tracks
     }
 
+    /// Build the sample index of every track, see [Mp4Track::build_index].
+    pub fn build_sample_index(&mut self) -> Result<()> {
+        for track in self.tracks.values_mut() {
+            track.build_index()?;
+        }
+        Ok(())
+    }
+
     pub fn sample_count(&self, track_id: u32) -> Result<u32> {
         if let Some(track) = self.tracks.get(&track_id) {
-            Ok(track.sample_count())
+            track.sample_count()
         } else {
             Err(Error::TrakNotFound(track_id))
         }
@@ -294,11 +238,78 @@ impl<R: Read + Seek> Mp4Reader<R> {
         }
     }
 
-    pub fn segment_index(&self) -> Option<&(SidxBox, usize)> {
-        self.sidx.first()
+    pub fn seek(&self, track_id: u32, time: Duration) -> Result<Option<SeekPosition>> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            track.seek(time)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    pub fn seek_to_timestamp(&self, track_id: u32, timestamp: u64) -> Result<Option<SeekPosition>> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            track.seek_to_timestamp(timestamp)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// [Mp4Track::presentation_timeline] of a track, using the movie timescale.
+    pub fn presentation_timeline(&self, track_id: u32) -> Result<Vec<SamplePresentation>> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            track.presentation_timeline(self.timescale())
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// [Mp4Track::gapless_info] of a track, using the file's metadata.
+    pub fn gapless_info(&self, track_id: u32) -> Result<Option<GaplessInfo>> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            track.gapless_info(self.metadata(), self.timescale())
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// Iterate over the samples of all tracks in the given order.
+    ///
+    /// Each track's samples are merged in sample order, so
+    /// [SampleOrder::FileOffset] yields file order as long as every track's
+    /// chunks are laid out in ascending order.
+    pub fn samples(&mut self, order: SampleOrder) -> Result<Mp4Samples<'_, R>> {
+        let mut heads = Vec::with_capacity(self.tracks.len());
+        for track in self.tracks.values() {
+            if let Some(head) = SampleHead::new(track, 1)? {
+                heads.push(head);
+            }
+        }
+        Ok(Mp4Samples {
+            reader: &mut self.reader,
+            tracks: &self.tracks,
+            order,
+            heads,
+        })
+    }
+
+    /// The random access points of a track listed in the `mfra`, in the track
+    /// timescale. Empty if the file has no `mfra` or it doesn't cover the track.
+    pub fn random_access_points(&self, track_id: u32) -> &[TfraEntry] {
+        self.mfra
+            .as_ref()
+            .and_then(|mfra| mfra.tfra(track_id))
+            .map(|tfra| tfra.entries.as_slice())
+            .unwrap_or_default()
     }
-    pub fn segment_indexes(&self) -> &Vec<(SidxBox, usize)> {
-        self.sidx.as_ref()
+
+    /// The media segments listed in the `sidx` boxes of the file.
+    pub fn segment_index(&self) -> &SegmentIndex {
+        &self.segment_index
+    }
+
+    /// The `sidx` segment of a track that covers `time`.
+    pub fn segment_at(&self, track_id: u32, time: Duration) -> Option<&Segment> {
+        self.segment_index.find(track_id, time)
     }
 }
 
@@ -306,9 +317,157 @@ impl<R> Mp4Reader<R> {
     pub fn metadata(&self) -> impl Metadata<'_> {
         self.moov.udta.as_ref().and_then(|udta| {
             udta.meta.as_ref().and_then(|meta| match meta {
-                MetaBox::Mdir { ilst } => ilst.as_ref(),
+                MetaBox::Mdir { ilst, .. } => ilst.as_ref(),
                 _ => None,
             })
         })
     }
+
+    /// The value of a QuickTime `mdta` key, such as
+    /// `com.apple.quicktime.model`.
+    pub fn quicktime_value(&self, key: &str) -> Option<DataValue<'_>> {
+        let meta = self.moov.quicktime_metadata()?;
+        meta.mdta_value(key).map(DataBox::value)
+    }
+
+    /// The recording location, from `com.apple.quicktime.location.ISO6709`.
+    pub fn location(&self) -> Option<Location> {
+        match self.quicktime_value(QUICKTIME_LOCATION)? {
+            DataValue::Text(text) => Location::from_iso6709(&text).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleOrder {
+    /// By decode time, compared across track timescales.
+    #[default]
+    DecodeTime,
+
+    /// By position in the file, for sequential I/O.
+    FileOffset,
+}
+
+#[derive(Debug)]
+struct SampleHead {
+    track_id: u32,
+    sample_id: u32,
+    timescale: u32,
+    offset: u64,
+    size: u32,
+    sample: Mp4Sample,
+}
+
+impl SampleHead {
+    fn new(track: &Mp4Track, sample_id: u32) -> Result<Option<Self>> {
+        if sample_id > track.sample_count()? {
+            return Ok(None);
+        }
+        let head = track
+            .sample_location(sample_id)?
+            .map(|(offset, size, sample)| SampleHead {
+                track_id: track.track_id(),
+                sample_id,
+                timescale: track.timescale(),
+                offset,
+                size,
+                sample,
+            });
+        Ok(head)
+    }
+
+    fn cmp(&self, other: &Self, order: SampleOrder) -> Ordering {
+        let ordering = match order {
+            SampleOrder::DecodeTime => {
+                let time = self.sample.start_time as u128 * other.timescale as u128;
+                let other_time = other.sample.start_time as u128 * self.timescale as u128;
+                time.cmp(&other_time)
+            }
+            SampleOrder::FileOffset => self.offset.cmp(&other.offset),
+        };
+        ordering.then(self.track_id.cmp(&other.track_id))
+    }
+}
+
+/// Iterator over `(track_id, sample)` across all tracks, created by
+/// [Mp4Reader::samples].
+#[derive(Debug)]
+pub struct Mp4Samples<'a, R> {
+    reader: &'a mut R,
+    tracks: &'a HashMap<u32, Mp4Track>,
+    order: SampleOrder,
+    heads: Vec<SampleHead>,
+}
+
+impl<R: Read + Seek> Mp4Samples<'_, R> {
+    fn read_next(&mut self) -> Result<Option<(u32, Mp4Sample)>> {
+        let Some(index) =
+            (0..self.heads.len()).min_by(|a, b| self.heads[*a].cmp(&self.heads[*b], self.order))
+        else {
+            return Ok(None);
+        };
+
+        let track = &self.tracks[&self.heads[index].track_id];
+        let next = SampleHead::new(track, self.heads[index].sample_id + 1)?;
+        let head = match next {
+            Some(next) => std::mem::replace(&mut self.heads[index], next),
+            None => self.heads.swap_remove(index),
+        };
+
+        let mut buffer = vec![0x0u8; head.size as usize];
+        self.reader.seek(SeekFrom::Start(head.offset))?;
+        self.reader.read_exact(&mut buffer)?;
+
+        let mut sample = head.sample;
+        sample.bytes = Bytes::from(buffer);
+        Ok(Some((head.track_id, sample)))
+    }
+}
+
+impl<R: Read + Seek> Iterator for Mp4Samples<'_, R> {
+    type Item = Result<(u32, Mp4Sample)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_next() {
+            Ok(next) => next.map(Ok),
+            Err(err) => {
+                self.heads.clear();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Build the tracks described by `moov`, along with any fragments found.
+pub(crate) fn build_tracks(
+    moov: &MoovBox,
+    moofs: &[MoofBox],
+    moof_offsets: &[u64],
+) -> Result<HashMap<u32, Mp4Track>> {
+    let mut tracks: HashMap<u32, Mp4Track> = moov
+        .traks
+        .iter()
+        .map(|trak| (trak.tkhd.track_id, Mp4Track::from(trak)))
+        .collect();
+
+    if let Some(mvex) = &moov.mvex {
+        for track in tracks.values_mut() {
+            track.set_fragment_defaults(mvex);
+        }
+    }
+
+    for (moof, moof_offset) in moofs.iter().zip(moof_offsets) {
+        let mut data_end = *moof_offset;
+        for traf in moof.trafs.iter() {
+            let track_id = traf.tfhd.track_id;
+            if let Some(track) = tracks.get_mut(&track_id) {
+                data_end = track.add_traf(traf, *moof_offset, data_end)?;
+            } else {
+                return Err(Error::TrakNotFound(track_id));
+            }
+        }
+    }
+
+    Ok(tracks)
 }
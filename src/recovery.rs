@@ -0,0 +1,957 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::RangeInclusive;
+
+use crate::*;
+
+/// Codec parameters and timing of a track to recover.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryTrack {
+    pub config: TrackConfig,
+
+    /// Duration of every sample, in the track timescale.
+    pub sample_duration: u32,
+
+    /// How the samples are told apart in the `mdat` payload.
+    pub framing: SampleFraming,
+
+    /// The sample description of the reference track, which the recovered
+    /// track gets in place of one built from `config`. It keeps what
+    /// `config` can't hold, such as H.265 parameter sets, and the size of
+    /// the length prefix of each NAL unit. H.264 tracks without one are
+    /// taken to use 4 byte prefixes, as [Mp4Writer] does.
+    pub sample_entry: Option<SampleEntry>,
+}
+
+/// How the samples of a track to recover are told apart in the `mdat`
+/// payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SampleFraming {
+    /// H.264 or H.265 NAL units behind their length prefixes, grouped into
+    /// access units.
+    NalUnits,
+
+    /// Samples that all have the same size, such as PCM.
+    FixedSize(u32),
+
+    /// The size of each sample in turn, such as from an index the recording
+    /// device keeps next to the file.
+    Sizes(Vec<u32>),
+
+    /// Raw AAC frames, stored in chunks between the NAL units of the video
+    /// track. Each chunk is split where the AAC syntax allows a frame to end
+    /// and the next to begin, into a count of frames within
+    /// `frames_per_chunk` with sizes within `frame_sizes`. Only the last
+    /// chunk may hold fewer frames.
+    ///
+    /// This is a heuristic: of the splits that fit, the one picked has the
+    /// frames that look most like real ones, with sizes closest to
+    /// `typical_size` given `size_deviation`.
+    AacChunks {
+        frames_per_chunk: RangeInclusive<u32>,
+        frame_sizes: RangeInclusive<u32>,
+        typical_size: u32,
+        size_deviation: u32,
+    },
+}
+
+/// Parameters for rebuilding a file whose `moov` is missing or truncated.
+///
+/// At most one track may be made of NAL units and at most one framed some
+/// other way, since the data of two such tracks can't be told apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryConfig {
+    pub mp4: Mp4Config,
+    pub tracks: Vec<RecoveryTrack>,
+}
+
+impl RecoveryConfig {
+    /// Take the track parameters from a healthy file recorded by the same
+    /// device with the same settings.
+    pub fn from_reference<R: Read + Seek>(reference: &Mp4Reader<R>) -> Result<Self> {
+        let mut tracks: Vec<&Mp4Track> = reference.tracks().values().collect();
+        tracks.sort_by_key(|track| track.track_id());
+
+        let tracks = tracks
+            .into_iter()
+            .map(|track| {
+                let config = TrackConfig::try_from(track)?;
+                let stbl = &track.trak.mdia.minf.stbl;
+                let sample_duration = stbl
+                    .stts
+                    .entries
+                    .first()
+                    .map(|entry| entry.sample_delta)
+                    .unwrap_or(track.default_sample_duration);
+                let framing = match config.media_conf {
+                    MediaConfig::AvcConfig(_) | MediaConfig::HevcConfig(_) => {
+                        SampleFraming::NalUnits
+                    }
+                    _ if stbl.stsz.sample_size != 0 => {
+                        SampleFraming::FixedSize(stbl.stsz.sample_size)
+                    }
+                    _ => match stbl.stsz.sample_sizes.split_first() {
+                        Some((first, rest)) if rest.iter().all(|size| size == first) => {
+                            SampleFraming::FixedSize(*first)
+                        }
+                        // AAC is usually encoded at a variable bitrate.
+                        _ if matches!(config.media_conf, MediaConfig::AacConfig(_)) => {
+                            aac_chunks(stbl)?
+                        }
+                        _ => {
+                            return Err(Error::InvalidData("reference track samples vary in size"));
+                        }
+                    },
+                };
+                Ok(RecoveryTrack {
+                    config,
+                    sample_duration,
+                    framing,
+                    sample_entry: stbl.stsd.entries.first().cloned(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RecoveryConfig {
+            mp4: Mp4Config {
+                major_brand: *reference.major_brand(),
+                minor_version: reference.minor_version(),
+                compatible_brands: reference.compatible_brands().to_vec(),
+                timescale: reference.timescale(),
+            },
+            tracks,
+        })
+    }
+}
+
+/// The AAC framing of a reference track, from the number of samples in each
+/// of its chunks.
+fn aac_chunks(stbl: &StblBox) -> Result<SampleFraming> {
+    let chunk_count = match (&stbl.stco, &stbl.co64) {
+        (Some(stco), _) => stco.entries.len() as u32,
+        (None, Some(co64)) => co64.entries.len() as u32,
+        (None, None) => 0,
+    };
+    let mut min_frames = u32::MAX;
+    let mut max_frames = 0;
+    for (i, entry) in stbl.stsc.entries.iter().enumerate() {
+        let next_chunk = stbl
+            .stsc
+            .entries
+            .get(i + 1)
+            .map_or(chunk_count + 1, |next| next.first_chunk);
+        // The last chunk of a recording is usually cut short.
+        if next_chunk.saturating_sub(entry.first_chunk) > 1 || next_chunk <= chunk_count {
+            min_frames = min_frames.min(entry.samples_per_chunk);
+        }
+        max_frames = max_frames.max(entry.samples_per_chunk);
+    }
+
+    let sizes = &stbl.stsz.sample_sizes;
+    let (Some(min_size), Some(max_size)) = (sizes.iter().min(), sizes.iter().max()) else {
+        return Err(Error::InvalidData("reference track has no samples"));
+    };
+    let mean = sizes.iter().map(|size| *size as f64).sum::<f64>() / sizes.len() as f64;
+    let variance = sizes
+        .iter()
+        .map(|size| (*size as f64 - mean).powi(2))
+        .sum::<f64>()
+        / sizes.len() as f64;
+    // Leave a margin for frames a little smaller or larger than any in the
+    // reference.
+    Ok(SampleFraming::AacChunks {
+        frames_per_chunk: min_frames.min(max_frames).max(1)..=max_frames.max(1),
+        frame_sizes: min_size * 3 / 4..=max_size + max_size / 4,
+        typical_size: mean as u32,
+        size_deviation: (variance.sqrt() as u32).max(1),
+    })
+}
+
+/// Rebuild a file whose `moov` is missing or truncated.
+///
+/// The samples are recovered by scanning the payload of every `mdat` in
+/// `reader`, including one that is cut off, and are written to `writer`
+/// through [Mp4Writer] along with fresh sample tables. Scanning stops at the
+/// first data that fits neither track, such as a sample cut off by the end of
+/// the file. Composition offsets can't be recovered, so every sample is
+/// presented at its decode time.
+pub fn recover_mp4<R, W>(mut reader: R, size: u64, writer: W, config: &RecoveryConfig) -> Result<W>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut nal_track = None;
+    let mut framed_track = None;
+    for (i, track) in config.tracks.iter().enumerate() {
+        let track_id = i as u32 + 1;
+        let slot = match (&track.config.media_conf, &track.framing) {
+            (MediaConfig::AvcConfig(_), SampleFraming::NalUnits)
+            | (MediaConfig::HevcConfig(_), SampleFraming::NalUnits) => &mut nal_track,
+            (_, SampleFraming::NalUnits) => {
+                return Err(Error::InvalidData(
+                    "only H.264 and H.265 samples are made of NAL units",
+                ));
+            }
+            (_, SampleFraming::FixedSize(0)) => {
+                return Err(Error::InvalidData(
+                    "track samples cannot have a size of zero",
+                ));
+            }
+            (MediaConfig::AacConfig(_), SampleFraming::AacChunks { .. })
+            | (_, SampleFraming::FixedSize(_))
+            | (_, SampleFraming::Sizes(_)) => &mut framed_track,
+            (_, SampleFraming::AacChunks { .. }) => {
+                return Err(Error::InvalidData(
+                    "only AAC samples can be split into AAC frames",
+                ));
+            }
+        };
+        if slot.replace((track_id, track)).is_some() {
+            return Err(Error::InvalidData(
+                "tracks cannot be told apart in the mdat payload",
+            ));
+        }
+    }
+    if let Some((_, track)) = framed_track
+        && matches!(track.framing, SampleFraming::AacChunks { .. })
+        && nal_track.is_none()
+    {
+        return Err(Error::InvalidData(
+            "AAC chunks can only be told apart by the NAL units between them",
+        ));
+    }
+
+    let nal_length_size = match nal_track {
+        Some((_, track)) => nal_length_size(track)?,
+        None => 0,
+    };
+
+    let mut writer = Mp4Writer::write_start(writer, &config.mp4)?;
+    for (i, track) in config.tracks.iter().enumerate() {
+        writer.add_track(&track.config)?;
+        if let Some(sample_entry) = &track.sample_entry {
+            writer.set_sample_entry(i as u32 + 1, sample_entry.clone())?;
+        }
+    }
+
+    let mut scanner = Scanner {
+        writer,
+        nal_track: nal_track.map(|(track_id, track)| NalTrack {
+            track_id,
+            sample_duration: track.sample_duration,
+            is_hevc: matches!(track.config.media_conf, MediaConfig::HevcConfig(_)),
+            length_size: nal_length_size,
+            access_unit: Vec::new(),
+            has_vcl: false,
+            is_sync: false,
+            sample_count: 0,
+        }),
+        framed_track: framed_track.map(|(track_id, track)| FramedTrack {
+            track_id,
+            sample_duration: track.sample_duration,
+            framing: track.framing.clone(),
+            aac: match &track.config.media_conf {
+                MediaConfig::AacConfig(aac_config) => Some(AacSyntax::new(aac_config)),
+                _ => None,
+            },
+            sample_count: 0,
+        }),
+    };
+
+    let start = reader.stream_position()?;
+    let mut current = start;
+    while current + HEADER_SIZE <= size {
+        let mut raw = [0u8; 8];
+        reader.read_exact(&mut raw)?;
+        let mut header_size = HEADER_SIZE;
+        let mut box_size = u32::from_be_bytes(raw[0..4].try_into().unwrap()) as u64;
+        let name = BoxType::from(u32::from_be_bytes(raw[4..8].try_into().unwrap()));
+        if box_size == 1 {
+            if current + 16 > size {
+                break;
+            }
+            reader.read_exact(&mut raw)?;
+            header_size += 8;
+            box_size = u64::from_be_bytes(raw);
+        }
+
+        // Recorders that are cut off often leave the size unset or larger
+        // than what made it to disk.
+        let end = if box_size < header_size || current + box_size > size {
+            size
+        } else {
+            current + box_size
+        };
+        if name == BoxType::MdatBox {
+            let mut start = current + header_size;
+            // Skip the placeholder that Mp4Writer reserves for a largesize.
+            if start + HEADER_SIZE <= end {
+                reader.read_exact(&mut raw)?;
+                if raw == [0, 0, 0, 8, b'w', b'i', b'd', b'e'] {
+                    start += HEADER_SIZE;
+                }
+            }
+            scanner.scan(&mut reader, start, end)?;
+        } else if box_size < header_size {
+            break;
+        }
+        reader.seek(SeekFrom::Start(end))?;
+        current = end;
+    }
+
+    scanner.flush_access_unit()?;
+    let mut writer = scanner.writer;
+    writer.write_end()?;
+    Ok(writer.into_writer())
+}
+
+/// The size of the length prefix of each NAL unit of a track.
+fn nal_length_size(track: &RecoveryTrack) -> Result<usize> {
+    let length_size_minus_one = match (&track.config.media_conf, &track.sample_entry) {
+        (MediaConfig::AvcConfig(_), Some(SampleEntry::Avc1(avc1))) => {
+            avc1.avcc.length_size_minus_one
+        }
+        (MediaConfig::HevcConfig(_), Some(SampleEntry::Hev1(hev1))) => {
+            hev1.hvcc.length_size_minus_one
+        }
+        (MediaConfig::AvcConfig(_), None) => 3,
+        (MediaConfig::HevcConfig(_), None) => {
+            return Err(Error::InvalidData(
+                "H.265 tracks can only be recovered with the sample entry of a reference",
+            ));
+        }
+        _ => {
+            return Err(Error::InvalidData(
+                "sample entry does not match the track codec",
+            ));
+        }
+    };
+    Ok((length_size_minus_one & 0x3) as usize + 1)
+}
+
+struct NalTrack {
+    track_id: u32,
+    sample_duration: u32,
+    is_hevc: bool,
+    length_size: usize,
+    access_unit: Vec<u8>,
+    has_vcl: bool,
+    is_sync: bool,
+    sample_count: u64,
+}
+
+struct FramedTrack {
+    track_id: u32,
+    sample_duration: u32,
+    framing: SampleFraming,
+    aac: Option<AacSyntax>,
+    sample_count: u64,
+}
+
+struct Scanner<W> {
+    writer: Mp4Writer<W>,
+    nal_track: Option<NalTrack>,
+    framed_track: Option<FramedTrack>,
+}
+
+impl<W: Write + Seek> Scanner<W> {
+    fn scan<R: Read + Seek>(&mut self, reader: &mut R, start: u64, end: u64) -> Result<()> {
+        reader.seek(SeekFrom::Start(start))?;
+        let mut current = start;
+        while current < end {
+            // An mdat without a size runs into whatever the recorder managed
+            // to write of moov.
+            if end - current >= HEADER_SIZE {
+                let mut header = [0u8; HEADER_SIZE as usize];
+                reader.read_exact(&mut header)?;
+                reader.seek(SeekFrom::Current(-(HEADER_SIZE as i64)))?;
+                if &header[4..] == b"moov" {
+                    break;
+                }
+            }
+
+            if let Some(nal_size) = self.peek_nal(reader, end - current)? {
+                let mut nal = vec![0u8; nal_size as usize];
+                reader.read_exact(&mut nal)?;
+                self.push_nal(nal)?;
+                current += nal_size;
+            } else if let Some(sizes) = self.next_frames(reader, current, end)? {
+                for size in sizes {
+                    let mut bytes = vec![0u8; size as usize];
+                    reader.read_exact(&mut bytes)?;
+                    // Samples of a track are stored whole, so another track's
+                    // data means the pending access unit is complete.
+                    self.flush_access_unit()?;
+                    self.write_frame(Bytes::from(bytes))?;
+                    current += size as u64;
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the sizes of the framed track's samples at the current
+    /// position, without consuming anything.
+    fn next_frames<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        current: u64,
+        end: u64,
+    ) -> Result<Option<Vec<u32>>> {
+        let Some(track) = &self.framed_track else {
+            return Ok(None);
+        };
+        let size = match &track.framing {
+            SampleFraming::FixedSize(size) => Some(*size),
+            SampleFraming::Sizes(sizes) => sizes.get(track.sample_count as usize).copied(),
+            SampleFraming::AacChunks { .. } => return self.split_aac_chunk(reader, current, end),
+            SampleFraming::NalUnits => None,
+        };
+        Ok(size
+            .filter(|size| *size > 0 && current + *size as u64 <= end)
+            .map(|size| vec![size]))
+    }
+
+    fn write_frame(&mut self, bytes: Bytes) -> Result<()> {
+        let track = self.framed_track.as_mut().unwrap();
+        let sample = Mp4Sample {
+            start_time: track.sample_count * track.sample_duration as u64,
+            duration: track.sample_duration,
+            rendering_offset: 0,
+            is_sync: true,
+            flags: None,
+            sample_description_index: 1,
+            bytes,
+        };
+        track.sample_count += 1;
+        self.writer.write_sample(track.track_id, &sample)
+    }
+
+    /// Find the AAC chunk at the current position, which ends where the NAL
+    /// units of the video track resume, and return the sizes of its frames.
+    fn split_aac_chunk<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        start: u64,
+        end: u64,
+    ) -> Result<Option<Vec<u32>>> {
+        let track = self.framed_track.as_ref().unwrap();
+        let (
+            Some(aac),
+            SampleFraming::AacChunks {
+                frames_per_chunk,
+                frame_sizes,
+                typical_size,
+                size_deviation,
+            },
+        ) = (&track.aac, &track.framing)
+        else {
+            return Ok(None);
+        };
+        let max_frames = *frames_per_chunk.end();
+        let max_size = (*frame_sizes.end()).min(aac.max_frame_size);
+        let frame_sizes = *frame_sizes.start()..=max_size;
+
+        let window = (end - start).min(max_frames as u64 * max_size as u64);
+        let mut data = vec![0u8; window as usize];
+        reader.read_exact(&mut data)?;
+        reader.seek(SeekFrom::Start(start))?;
+        if !aac.is_frame_start(&data) {
+            return Ok(None);
+        }
+
+        for chunk_end in 2..=data.len() {
+            if !aac.is_frame_end(&data[..chunk_end]) {
+                continue;
+            }
+            // Only the last chunk, which ends at moov or the end of the
+            // data, may be short.
+            let position = start + chunk_end as u64;
+            let is_last = position == end || data[chunk_end..].get(4..8) == Some(b"moov");
+            if !is_last && !self.resumes_at(reader, &data[chunk_end..], position, end)? {
+                continue;
+            }
+            let min_frames = if is_last {
+                1
+            } else {
+                *frames_per_chunk.start()
+            };
+            if let Some(sizes) = aac.split(
+                &data[..chunk_end],
+                min_frames..=max_frames,
+                &frame_sizes,
+                *typical_size,
+                *size_deviation,
+            ) {
+                reader.seek(SeekFrom::Start(start))?;
+                return Ok(Some(sizes));
+            }
+        }
+        reader.seek(SeekFrom::Start(start))?;
+
+        // A chunk cut off by the end of the file keeps its whole frames.
+        if window == end - start {
+            for chunk_end in (2..=data.len()).rev() {
+                if aac.is_frame_end(&data[..chunk_end])
+                    && let Some(sizes) = aac.split(
+                        &data[..chunk_end],
+                        1..=max_frames,
+                        &frame_sizes,
+                        *typical_size,
+                        *size_deviation,
+                    )
+                {
+                    return Ok(Some(sizes));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the NAL units of the video track start at `position`. `data`
+    /// holds what was already read from there.
+    fn resumes_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        data: &[u8],
+        position: u64,
+        end: u64,
+    ) -> Result<bool> {
+        // Check the first NAL unit from what is at hand, then make sure the
+        // one after it fits as well, as AAC data can pass for one.
+        let Some(track) = &self.nal_track else {
+            return Ok(false);
+        };
+        let header_size = track.length_size + 2;
+        if data.len() >= header_size
+            && self
+                .nal_size(&data[..header_size], end - position)
+                .is_none()
+        {
+            return Ok(false);
+        }
+        reader.seek(SeekFrom::Start(position))?;
+        let resumes = match self.peek_nal(reader, end - position)? {
+            Some(nal_size) => {
+                let next = position + nal_size;
+                next == end || {
+                    reader.seek(SeekFrom::Start(next))?;
+                    self.peek_nal(reader, end - next)?.is_some()
+                }
+            }
+            None => false,
+        };
+        Ok(resumes)
+    }
+
+    /// Return the size of the NAL unit at the current position, with its
+    /// length prefix, if it looks like one of the NAL track's, without
+    /// consuming anything.
+    fn peek_nal<R: Read + Seek>(&self, reader: &mut R, remaining: u64) -> Result<Option<u64>> {
+        let Some(track) = &self.nal_track else {
+            return Ok(None);
+        };
+        let header_size = track.length_size + 2;
+        if remaining < header_size as u64 {
+            return Ok(None);
+        }
+        let mut buf = [0u8; 6];
+        let buf = &mut buf[..header_size];
+        reader.read_exact(buf)?;
+        reader.seek(SeekFrom::Current(-(header_size as i64)))?;
+        Ok(self.nal_size(buf, remaining))
+    }
+
+    /// Return the size of the NAL unit whose length prefix and header are in
+    /// `buf`, with its length prefix, if it looks like one of the NAL
+    /// track's.
+    fn nal_size(&self, buf: &[u8], remaining: u64) -> Option<u64> {
+        let track = self.nal_track.as_ref()?;
+        let (length, header) = buf.split_at(track.length_size);
+        let nal_size = length
+            .iter()
+            .fold(0u64, |size, byte| (size << 8) | *byte as u64);
+        let size = track.length_size as u64 + nal_size;
+        if nal_size < 2 || size > remaining || header[0] & 0x80 != 0 {
+            return None;
+        }
+        let plausible = if track.is_hevc {
+            // nuh_layer_id is zero and nuh_temporal_id_plus1 is not.
+            let nal_type = (header[0] >> 1) & 0x3f;
+            nal_type <= 40 && header[0] & 0x01 == 0 && header[1] >> 3 == 0 && header[1] & 0x07 != 0
+        } else {
+            let nal_type = header[0] & 0x1f;
+            (1..=23).contains(&nal_type) && (nal_type != 5 || header[0] & 0x60 != 0)
+        };
+        plausible.then_some(size)
+    }
+
+    /// Add a length-prefixed NAL unit to the pending access unit, writing out
+    /// the previous one first if this NAL unit starts a new one.
+    fn push_nal(&mut self, nal: Vec<u8>) -> Result<()> {
+        let track = self.nal_track.as_ref().unwrap();
+        let header = &nal[track.length_size..];
+        let (is_vcl, is_first_slice, is_sync, starts_access_unit) = if track.is_hevc {
+            let nal_type = (header[0] >> 1) & 0x3f;
+            let is_vcl = nal_type < 32;
+            let is_first_slice = is_vcl && header.len() > 2 && header[2] & 0x80 != 0;
+            // IRAP pictures, and VPS, SPS, PPS, AUD or prefix SEI.
+            (
+                is_vcl,
+                is_first_slice,
+                (16..=23).contains(&nal_type),
+                (32..=35).contains(&nal_type) || nal_type == 39,
+            )
+        } else {
+            let nal_type = header[0] & 0x1f;
+            let is_vcl = (1..=5).contains(&nal_type);
+            // first_mb_in_slice is zero.
+            let is_first_slice = is_vcl && header[1] & 0x80 != 0;
+            // SEI, SPS, PPS or AUD.
+            (
+                is_vcl,
+                is_first_slice,
+                nal_type == 5,
+                (6..=9).contains(&nal_type),
+            )
+        };
+        if track.has_vcl && (is_first_slice || starts_access_unit) {
+            self.flush_access_unit()?;
+        }
+
+        let track = self.nal_track.as_mut().unwrap();
+        track.access_unit.extend_from_slice(&nal);
+        track.has_vcl |= is_vcl;
+        track.is_sync |= is_sync;
+        Ok(())
+    }
+
+    fn flush_access_unit(&mut self) -> Result<()> {
+        let Some(track) = &mut self.nal_track else {
+            return Ok(());
+        };
+        if !track.has_vcl {
+            return Ok(());
+        }
+        let sample = Mp4Sample {
+            start_time: track.sample_count * track.sample_duration as u64,
+            duration: track.sample_duration,
+            rendering_offset: 0,
+            is_sync: track.is_sync,
+            flags: None,
+            sample_description_index: 1,
+            bytes: Bytes::from(std::mem::take(&mut track.access_unit)),
+        };
+        track.has_vcl = false;
+        track.is_sync = false;
+        track.sample_count += 1;
+        self.writer.write_sample(track.track_id, &sample)
+    }
+}
+
+/// What can be told of AAC raw data blocks without decoding them.
+struct AacSyntax {
+    /// CPE for stereo, otherwise the SCE that comes first.
+    first_element: u32,
+    /// Only AAC Main may use prediction.
+    allows_prediction: bool,
+    /// 6144 bits per channel.
+    max_frame_size: u32,
+}
+
+impl AacSyntax {
+    const SCE: u32 = 0;
+    const CPE: u32 = 1;
+    const FIL: u32 = 6;
+    const END: u32 = 7;
+    /// What a frame using prediction, which is not followed any further,
+    /// counts as.
+    const PREDICTION_SECTIONS: u32 = 2;
+    /// A frame without spectral data that ends right where the next starts
+    /// is as likely to be real as one with six sections.
+    const SILENT_FRAME_LIKELIHOOD: f64 = 4.0;
+
+    fn new(config: &AacConfig) -> Self {
+        let channels = match config.chan_conf {
+            ChannelConfig::SevenOne => 8,
+            chan_conf => chan_conf as u32,
+        };
+        Self {
+            first_element: if config.chan_conf == ChannelConfig::Stereo {
+                Self::CPE
+            } else {
+                Self::SCE
+            },
+            allows_prediction: config.profile == AudioObjectType::AacMain,
+            max_frame_size: 768 * channels,
+        }
+    }
+
+    /// Whether `data` starts like a raw data block: the first element with
+    /// instance tag 0, with a valid `ics_info` and `section_data`.
+    fn is_frame_start(&self, data: &[u8]) -> bool {
+        self.frame_start(data).is_some()
+    }
+
+    /// Check the start of a raw data block, returning what tells how likely
+    /// a frame is to really start there.
+    fn frame_start(&self, data: &[u8]) -> Option<FrameStart> {
+        let bits = &mut BitReader { data, offset: 0 };
+        if bits.read(3)? != self.first_element || bits.read(4)? != 0 {
+            return None;
+        }
+        let prediction = FrameStart {
+            sections: Self::PREDICTION_SECTIONS,
+            size: None,
+        };
+        let common_window = self.first_element == Self::CPE && bits.read(1)? == 1;
+        let ics = if common_window {
+            // Both channels share the ics_info.
+            let Some(ics) = self.ics_info(bits)? else {
+                return Some(prediction);
+            };
+            match bits.read(2)? {
+                1 => bits.skip(ics.max_sfb * ics.window_groups)?,
+                3 => return None,
+                _ => {}
+            }
+            bits.skip(8)?; // global_gain
+            ics
+        } else {
+            bits.skip(8)?; // global_gain
+            let Some(ics) = self.ics_info(bits)? else {
+                return Some(prediction);
+            };
+            ics
+        };
+
+        // Sections of each window group cover max_sfb bands exactly.
+        let (sect_bits, sect_esc) = if ics.short_windows { (3, 7) } else { (5, 31) };
+        let mut sections = 0;
+        for _ in 0..ics.window_groups {
+            let mut band = 0;
+            while band < ics.max_sfb {
+                // A reserved codebook, or intensity stereo, which only the
+                // second channel of a pair may use.
+                let codebook = bits.read(4)?;
+                if codebook == 12 || codebook >= 14 {
+                    return None;
+                }
+                let mut length = 0;
+                loop {
+                    let increment = bits.read(sect_bits)?;
+                    length += increment;
+                    if increment != sect_esc {
+                        break;
+                    }
+                }
+                band += length;
+                if length == 0 || band > ics.max_sfb {
+                    return None;
+                }
+                sections += 1;
+            }
+        }
+        let size = if sections == 0 {
+            self.silent_frame_size(bits, common_window)
+        } else {
+            None
+        };
+        Some(FrameStart { sections, size })
+    }
+
+    /// Follow a frame without spectral data in its first channel to its END
+    /// element, if the rest of it is as plain, and return its size.
+    fn silent_frame_size(&self, bits: &mut BitReader, common_window: bool) -> Option<u32> {
+        // pulse_data_present, tns_data_present and gain_control_data_present
+        if bits.read(3)? != 0 {
+            return None;
+        }
+        if self.first_element == Self::CPE {
+            bits.skip(8)?; // global_gain
+            if !common_window && self.ics_info(bits)??.max_sfb != 0 {
+                return None;
+            }
+            if bits.read(3)? != 0 {
+                return None;
+            }
+        }
+        loop {
+            match bits.read(3)? {
+                Self::END => return Some(bits.offset.div_ceil(8) as u32),
+                Self::FIL => {
+                    let mut count = bits.read(4)?;
+                    if count == 15 {
+                        count += bits.read(8)? - 1;
+                    }
+                    bits.skip(count * 8)?;
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Read `ics_info`, returning `Some(None)` if it is valid but uses
+    /// prediction, which is not worth following.
+    fn ics_info(&self, bits: &mut BitReader) -> Option<Option<IcsInfo>> {
+        if bits.read(1)? != 0 {
+            return None; // ics_reserved_bit
+        }
+        let window_sequence = bits.read(2)?;
+        bits.skip(1)?; // window_shape
+        if window_sequence == 2 {
+            let max_sfb = bits.read(4)?;
+            let grouping = bits.read(7)?;
+            return Some(Some(IcsInfo {
+                max_sfb,
+                window_groups: 1 + grouping.count_zeros() - 25,
+                short_windows: true,
+            }));
+        }
+        let max_sfb = bits.read(6)?;
+        if max_sfb > 51 {
+            return None;
+        }
+        if bits.read(1)? == 1 {
+            return self.allows_prediction.then_some(None);
+        }
+        Some(Some(IcsInfo {
+            max_sfb,
+            window_groups: 1,
+            short_windows: false,
+        }))
+    }
+
+    /// Whether `data` ends like a raw data block: an END element, then zero
+    /// bits up to the byte boundary.
+    fn is_frame_end(&self, data: &[u8]) -> bool {
+        let [.., a, b] = data else {
+            return false;
+        };
+        let last = u16::from_be_bytes([*a, *b]);
+        let padding = last.trailing_zeros();
+        padding < 8 && (last >> padding) & 0x7 == 0x7
+    }
+
+    /// Split a chunk into a count of frames within `frame_counts` and sizes
+    /// within `frame_sizes`, picking the likeliest split by how many sections
+    /// each frame starts with and how far its size is from `typical_size`.
+    fn split(
+        &self,
+        chunk: &[u8],
+        frame_counts: RangeInclusive<u32>,
+        frame_sizes: &RangeInclusive<u32>,
+        typical_size: u32,
+        size_deviation: u32,
+    ) -> Option<Vec<u32>> {
+        // Where a frame could start and its sections, with the end of the
+        // chunk last.
+        let mut bounds = vec![(0, self.frame_start(chunk)?)];
+        for i in 2..chunk.len() {
+            if self.is_frame_end(&chunk[..i])
+                && let Some(start) = self.frame_start(&chunk[i..])
+            {
+                bounds.push((i, start));
+            }
+        }
+        bounds.push((chunk.len(), FrameStart::default()));
+
+        // splits[i][n]: the likeliest split of the chunk up to bounds[i] into
+        // n frames, with the bound the last frame started at.
+        let max_frames = *frame_counts.end() as usize;
+        let mut splits = vec![vec![None::<(f64, usize)>; max_frames + 1]; bounds.len()];
+        splits[0][0] = Some((0.0, 0));
+        for i in 1..bounds.len() {
+            for j in (0..i).rev() {
+                let size = (bounds[i].0 - bounds[j].0) as u32;
+                if size > *frame_sizes.end() {
+                    break;
+                }
+                let likelihood = match bounds[j].1 {
+                    FrameStart {
+                        size: Some(silent_size),
+                        ..
+                    } => {
+                        if size != silent_size {
+                            continue;
+                        }
+                        Self::SILENT_FRAME_LIKELIHOOD
+                    }
+                    FrameStart { sections, .. } => {
+                        if size < *frame_sizes.start() {
+                            continue;
+                        }
+                        // A couple of sections are no better than none.
+                        let deviation = (size as f64 - typical_size as f64) / size_deviation as f64;
+                        sections as f64 - 2.0 - deviation * deviation / 2.0
+                    }
+                };
+                for n in 1..=max_frames {
+                    if let Some((previous, _)) = splits[j][n - 1]
+                        && splits[i][n].is_none_or(|(best, _)| previous + likelihood > best)
+                    {
+                        splits[i][n] = Some((previous + likelihood, j));
+                    }
+                }
+            }
+        }
+
+        let last = bounds.len() - 1;
+        let mut n = (*frame_counts.start() as usize..=max_frames)
+            .filter_map(|n| Some((splits[last][n]?.0, n)))
+            .reduce(|best, split| if best.0 > split.0 { best } else { split })?
+            .1;
+        let mut sizes = Vec::with_capacity(n);
+        let mut i = last;
+        while n > 0 {
+            let (_, j) = splits[i][n].unwrap();
+            sizes.push((bounds[i].0 - bounds[j].0) as u32);
+            i = j;
+            n -= 1;
+        }
+        sizes.reverse();
+        Some(sizes)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct FrameStart {
+    /// How many sections the `section_data` of the first channel has. Data
+    /// that only happens to fit the syntax rarely gets past one or two.
+    sections: u32,
+    /// The size of a frame without spectral data, which is fully known.
+    size: Option<u32>,
+}
+
+struct IcsInfo {
+    max_sfb: u32,
+    window_groups: u32,
+    short_windows: bool,
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl BitReader<'_> {
+    /// Read `count` bits, most significant first.
+    fn read(&mut self, count: u32) -> Option<u32> {
+        let end = self.offset + count as usize;
+        if end > self.data.len() * 8 {
+            return None;
+        }
+        let mut value = 0;
+        for bit in self.offset..end {
+            value = (value << 1) | ((self.data[bit / 8] >> (7 - bit % 8)) & 1) as u32;
+        }
+        self.offset = end;
+        Some(value)
+    }
+
+    fn skip(&mut self, count: u32) -> Option<()> {
+        self.offset += count as usize;
+        (self.offset <= self.data.len() * 8).then_some(())
+    }
+}
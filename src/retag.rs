@@ -0,0 +1,155 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::meta::MetaBox;
+use crate::*;
+
+/// Copy an mp4 file to `writer`, editing its `ilst` metadata with `edit`.
+///
+/// Everything but `moov` is copied as is. When `moov` comes before the sample
+/// data, the chunk offsets are moved by the change in its size. Fragmented
+/// files are not supported.
+pub fn retag_mp4<R, W, F>(mut reader: R, size: u64, mut writer: W, edit: F) -> Result<W>
+where
+    R: Read + Seek,
+    W: Write,
+    F: FnOnce(&mut IlstBox),
+{
+    let start = reader.stream_position()?;
+
+    let mut moov = None;
+    let end = read_top_level_boxes(
+        &mut reader,
+        size,
+        &mut ReadContext::default(),
+        |reader, header, offset, _| {
+            if header.name == BoxType::MoovBox {
+                // The size on disk, which a 64-bit size puts past header.size.
+                let moov_size = box_end(reader, &header)? - offset;
+                moov = Some((MoovBox::read_box(reader, header.size)?, offset, moov_size));
+            }
+            Ok(true)
+        },
+    )?;
+    // A box of size zero runs to the end of the file.
+    let end = end.max(size);
+
+    let Some((mut moov, moov_offset, moov_size)) = moov else {
+        return Err(Error::BoxNotFound(BoxType::MoovBox));
+    };
+    if moov.mvex.is_some() {
+        return Err(Error::InvalidData("cannot retag a fragmented file"));
+    }
+
+    edit(ilst_mut(&mut moov)?);
+    prune_metadata(&mut moov);
+    shift_chunk_offsets(&mut moov, moov_offset + moov_size, moov_size);
+
+    copy_range(&mut reader, start, moov_offset, &mut writer)?;
+    moov.write_box(&mut writer)?;
+    copy_range(&mut reader, moov_offset + moov_size, end, &mut writer)?;
+    Ok(writer)
+}
+
+/// Copy the bytes of `reader` from `start` up to `end` to `writer`.
+fn copy_range<R: Read + Seek, W: Write>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    writer: &mut W,
+) -> Result<()> {
+    reader.seek(SeekFrom::Start(start))?;
+    let size = end - start;
+    if io::copy(&mut reader.take(size), writer)? != size {
+        return Err(Error::InvalidData("file is shorter than its boxes"));
+    }
+    Ok(())
+}
+
+/// Replace the `ilst` of `moov`, removing the metadata boxes if it is empty.
+pub(crate) fn set_moov_metadata(moov: &mut MoovBox, ilst: &IlstBox) -> Result<()> {
+    *ilst_mut(moov)? = ilst.clone();
+    prune_metadata(moov);
+    Ok(())
+}
+
+/// The `ilst` under `moov/udta/meta`, added if there is none.
+fn ilst_mut(moov: &mut MoovBox) -> Result<&mut IlstBox> {
+    let udta = moov.udta.get_or_insert_with(UdtaBox::default);
+    let meta = udta.meta.get_or_insert_with(|| MetaBox::Mdir {
+        ilst: None,
+        unknown_boxes: Vec::new(),
+    });
+    match meta {
+        MetaBox::Mdir { ilst, .. } => Ok(ilst.get_or_insert_with(IlstBox::default)),
+        MetaBox::Mdta { .. } | MetaBox::Unknown { .. } => Err(Error::InvalidData(
+            "udta holds a meta box other than iTunes metadata",
+        )),
+    }
+}
+
+/// Drop the `ilst`, `meta` and `udta` boxes of `moov` that are left empty.
+fn prune_metadata(moov: &mut MoovBox) {
+    let Some(udta) = &mut moov.udta else {
+        return;
+    };
+    if let Some(MetaBox::Mdir {
+        ilst,
+        unknown_boxes,
+    }) = &mut udta.meta
+    {
+        if ilst.as_ref().is_some_and(IlstBox::is_empty) {
+            *ilst = None;
+        }
+        if ilst.is_none() && unknown_boxes.is_empty() {
+            udta.meta = None;
+        }
+    }
+    if udta.meta.is_none() && udta.unknown_boxes.is_empty() {
+        moov.udta = None;
+    }
+}
+
+/// Move the chunks at or after `moov_end` by the change in size of `moov`
+/// from `old_size`.
+fn shift_chunk_offsets(moov: &mut MoovBox, moov_end: u64, old_size: u64) {
+    // Shifting the chunk offsets can push some past 32 bits, and upgrading
+    // stco to co64 grows moov in turn, so repeat until no more are needed.
+    loop {
+        let shift = moov.box_size() as i64 - old_size as i64;
+        let mut upgraded = false;
+        for trak in moov.traks.iter_mut() {
+            let stbl = &mut trak.mdia.minf.stbl;
+            if let Some(stco) = &stbl.stco
+                && stco.entries.iter().any(|offset| {
+                    *offset as u64 >= moov_end && *offset as i64 + shift > u32::MAX as i64
+                })
+            {
+                stbl.co64 = Some(Co64Box::from(stco));
+                stbl.stco = None;
+                upgraded = true;
+            }
+        }
+        if !upgraded {
+            break;
+        }
+    }
+
+    let shift = moov.box_size() as i64 - old_size as i64;
+    for trak in moov.traks.iter_mut() {
+        let stbl = &mut trak.mdia.minf.stbl;
+        if let Some(stco) = &mut stbl.stco {
+            for offset in stco.entries.iter_mut() {
+                if *offset as u64 >= moov_end {
+                    *offset = (*offset as i64 + shift) as u32;
+                }
+            }
+        }
+        if let Some(co64) = &mut stbl.co64 {
+            for offset in co64.entries.iter_mut() {
+                if *offset >= moov_end {
+                    *offset = (*offset as i64 + shift) as u64;
+                }
+            }
+        }
+    }
+}
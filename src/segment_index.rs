@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::*;
+
+/// A media subsegment listed in a `sidx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// The track the times refer to, from the `reference_ID` of the `sidx`.
+    pub track_id: u32,
+    pub timescale: u32,
+
+    /// Earliest presentation time of the segment, in `timescale`.
+    pub start_time: u64,
+    pub duration: u64,
+
+    /// Byte range of the segment in the file.
+    pub offset: u64,
+    pub size: u64,
+
+    pub starts_with_sap: bool,
+    pub sap_type: u8,
+
+    /// Indices of the `moof` boxes within the byte range, in the order the
+    /// reader found them.
+    pub moofs: Range<usize>,
+}
+
+impl Segment {
+    pub fn start(&self) -> Duration {
+        to_duration(self.start_time, self.timescale)
+    }
+
+    pub fn end(&self) -> Duration {
+        to_duration(self.start_time + self.duration, self.timescale)
+    }
+}
+
+fn to_duration(time: u64, timescale: u32) -> Duration {
+    let nanos = time as u128 * 1_000_000_000 / timescale.max(1) as u128;
+    Duration::from_nanos(nanos as u64)
+}
+
+/// The media segments of a file, resolved from all of its `sidx` boxes.
+///
+/// A `sidx` may point at further `sidx` boxes instead of media, either
+/// nested or chained one after the other. These are followed down to the
+/// media segments, which are kept in file order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SegmentIndex {
+    segments: Vec<Segment>,
+}
+
+impl SegmentIndex {
+    /// Build the index from `(sidx, offset)` pairs as found in the file, and
+    /// the offsets of the `moof` boxes in file order.
+    ///
+    /// References to a `sidx` that isn't among `sidx` are left out.
+    pub fn new(sidx: &[(SidxBox, usize)], moof_offsets: &[u64]) -> Self {
+        let mut referenced = HashSet::new();
+        for (s, offset) in sidx.iter() {
+            for (_, offset, reference) in s.reference_positions(*offset as u64) {
+                if reference.reference_type == 1 {
+                    referenced.insert(offset);
+                }
+            }
+        }
+
+        let mut index = SegmentIndex::default();
+        let mut visited = HashSet::new();
+        for (s, offset) in sidx.iter() {
+            if !referenced.contains(&(*offset as u64)) {
+                index.add_sidx(sidx, s, *offset as u64, moof_offsets, &mut visited);
+            }
+        }
+        index
+    }
+
+    fn add_sidx(
+        &mut self,
+        all: &[(SidxBox, usize)],
+        sidx: &SidxBox,
+        sidx_offset: u64,
+        moof_offsets: &[u64],
+        visited: &mut HashSet<u64>,
+    ) {
+        // Guard against references that loop back.
+        if !visited.insert(sidx_offset) {
+            return;
+        }
+        for (time, offset, reference) in sidx.reference_positions(sidx_offset) {
+            if reference.reference_type == 1 {
+                if let Some((child, _)) = all.iter().find(|(_, o)| *o as u64 == offset) {
+                    self.add_sidx(all, child, offset, moof_offsets, visited);
+                }
+                continue;
+            }
+            let end = offset + reference.referenced_size as u64;
+            let first = moof_offsets.partition_point(|moof| *moof < offset);
+            let last = moof_offsets.partition_point(|moof| *moof < end);
+            self.segments.push(Segment {
+                track_id: sidx.reference_id,
+                timescale: sidx.timescale,
+                start_time: time,
+                duration: reference.subsegment_duration as u64,
+                offset,
+                size: reference.referenced_size as u64,
+                starts_with_sap: reference.starts_with_sap,
+                sap_type: reference.sap_type,
+                moofs: first..last,
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// All media segments, in file order.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The segment of `track_id` that covers `time`.
+    pub fn find(&self, track_id: u32, time: Duration) -> Option<&Segment> {
+        self.segments.iter().find(|segment| {
+            segment.track_id == track_id && segment.start() <= time && time < segment.end()
+        })
+    }
+}
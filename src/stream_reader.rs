@@ -0,0 +1,330 @@
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read};
+
+use crate::*;
+
+#[derive(Debug)]
+struct PendingSample {
+    track_id: u32,
+    offset: u64,
+    size: u32,
+    sample: Mp4Sample,
+}
+
+/// Forward-only reader for sources that cannot seek, such as pipes and sockets.
+///
+/// Top-level boxes are walked in order and samples are returned in file order,
+/// both for progressive files (using the sample tables in `moov`) and for
+/// fragmented streams (using each `moof`). Any `mdat` that precedes `moov` has
+/// to be buffered in memory, since its samples are not known until then.
+#[derive(Debug)]
+pub struct Mp4StreamReader<R> {
+    reader: R,
+    position: u64,
+    pub ftyp: FtypBox,
+    pub moov: MoovBox,
+
+    tracks: HashMap<u32, Mp4Track>,
+    pending: VecDeque<PendingSample>,
+    buffered_mdats: Vec<(u64, Bytes)>,
+    mdat_end: Option<u64>,
+}
+
+impl<R: Read> Mp4StreamReader<R> {
+    /// Read boxes up to and including `moov`.
+    pub fn read_header(mut reader: R) -> Result<Self> {
+        let mut position = 0;
+        let mut ftyp = None;
+        let mut buffered_mdats = Vec::new();
+
+        let moov = loop {
+            let Some(TopLevelBox { header, data, .. }) =
+                read_top_level_box(&mut reader, &mut position)?
+            else {
+                return Err(Error::BoxNotFound(BoxType::MoovBox));
+            };
+            // Break if size zero BoxHeader, as only mdat may extend to the end.
+            if header.size == 0 && header.name != BoxType::MdatBox {
+                return Err(Error::BoxNotFound(BoxType::MoovBox));
+            }
+            match header.name {
+                BoxType::FtypBox => {
+                    ftyp = Some(FtypBox::read_box(&mut data.unwrap(), header.size)?);
+                }
+                BoxType::MoovBox => {
+                    break MoovBox::read_box(&mut data.unwrap(), header.size)?;
+                }
+                BoxType::MdatBox => {
+                    // The samples in this mdat are only known once moov is read.
+                    let offset = position;
+                    let mut buf = Vec::new();
+                    if header.size == 0 {
+                        reader.read_to_end(&mut buf)?;
+                    } else {
+                        let size = header.size - HEADER_SIZE;
+                        (&mut reader).take(size).read_to_end(&mut buf)?;
+                        if buf.len() as u64 != size {
+                            return Err(Error::IoError(io::ErrorKind::UnexpectedEof.into()));
+                        }
+                    }
+                    position += buf.len() as u64;
+                    buffered_mdats.push((offset, Bytes::from(buf)));
+                }
+                _ => {
+                    // XXX warn!()
+                    skip(&mut reader, &mut position, header.size - HEADER_SIZE)?;
+                }
+            }
+        };
+
+        let Some(ftyp) = ftyp else {
+            return Err(Error::BoxNotFound(BoxType::FtypBox));
+        };
+        if moov.traks.iter().any(|trak| trak.tkhd.track_id == 0) {
+            return Err(Error::InvalidData("illegal track id 0"));
+        }
+
+        let mut tracks: HashMap<u32, Mp4Track> = moov
+            .traks
+            .iter()
+            .map(|trak| (trak.tkhd.track_id, Mp4Track::from(trak)))
+            .collect();
+        if let Some(mvex) = &moov.mvex {
+            for track in tracks.values_mut() {
+                track.set_fragment_defaults(mvex);
+            }
+        }
+
+        let mut stream = Mp4StreamReader {
+            reader,
+            position,
+            ftyp,
+            moov,
+            tracks,
+            pending: VecDeque::new(),
+            buffered_mdats,
+            mdat_end: None,
+        };
+        let tracks: Vec<&Mp4Track> = stream.tracks.values().collect();
+        stream.pending = pending_samples(&tracks)?;
+        Ok(stream)
+    }
+
+    pub fn tracks(&self) -> &HashMap<u32, Mp4Track> {
+        &self.tracks
+    }
+
+    /// Number of bytes consumed from the source so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Read the next sample in file order, along with its track id.
+    ///
+    /// Returns `None` once the source is exhausted.
+    pub fn next_sample(&mut self) -> Result<Option<(u32, Mp4Sample)>> {
+        loop {
+            if let Some(next) = self.pending.front() {
+                let end = next.offset + next.size as u64;
+                if let Some(mdat_end) = self.mdat_end
+                    && next.offset >= self.position
+                    && end <= mdat_end
+                {
+                    let next = self.pending.pop_front().unwrap();
+                    let gap = next.offset - self.position;
+                    skip(&mut self.reader, &mut self.position, gap)?;
+                    let mut buf = vec![0u8; next.size as usize];
+                    self.reader.read_exact(&mut buf)?;
+                    self.position += next.size as u64;
+                    let mut sample = next.sample;
+                    sample.bytes = Bytes::from(buf);
+                    return Ok(Some((next.track_id, sample)));
+                }
+
+                if next.offset < self.position {
+                    let next = self.pending.pop_front().unwrap();
+                    let bytes = self
+                        .buffered_mdats
+                        .iter()
+                        .find(|(offset, data)| {
+                            next.offset >= *offset && end <= *offset + data.len() as u64
+                        })
+                        .map(|(offset, data)| {
+                            let start = (next.offset - offset) as usize;
+                            data.slice(start..start + next.size as usize)
+                        })
+                        .ok_or(Error::InvalidData(
+                            "sample data precedes the current stream position",
+                        ))?;
+                    let mut sample = next.sample;
+                    sample.bytes = bytes;
+                    return Ok(Some((next.track_id, sample)));
+                }
+            }
+
+            // Nothing more to read from the current mdat.
+            match self.mdat_end.take() {
+                Some(u64::MAX) => {
+                    self.position += io::copy(&mut self.reader, &mut io::sink())?;
+                }
+                Some(mdat_end) => {
+                    let remaining = mdat_end - self.position;
+                    skip(&mut self.reader, &mut self.position, remaining)?;
+                }
+                None => {}
+            }
+
+            let box_offset = self.position;
+            let Some(TopLevelBox {
+                header,
+                header_len,
+                data,
+            }) = read_top_level_box(&mut self.reader, &mut self.position)?
+            else {
+                if self.pending.is_empty() {
+                    return Ok(None);
+                }
+                return Err(Error::InvalidData("stream ended before all sample data"));
+            };
+            match header.name {
+                BoxType::MdatBox => {
+                    self.mdat_end = Some(if header.size == 0 {
+                        u64::MAX
+                    } else {
+                        box_offset + header_len + header.size - HEADER_SIZE
+                    });
+                }
+                BoxType::MoofBox => {
+                    let moof = MoofBox::read_box(&mut data.unwrap(), header.size)?;
+                    self.add_fragment(&moof, box_offset)?;
+                }
+                _ if header.size == 0 => {
+                    // XXX warn!()
+                    self.position += io::copy(&mut self.reader, &mut io::sink())?;
+                }
+                _ => {
+                    // XXX warn!()
+                    skip(
+                        &mut self.reader,
+                        &mut self.position,
+                        header.size - HEADER_SIZE,
+                    )?;
+                }
+            }
+        }
+    }
+
+    /// Consume self, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn add_fragment(&mut self, moof: &MoofBox, moof_offset: u64) -> Result<()> {
+        let mut fragment_tracks: HashMap<u32, Mp4Track> = HashMap::new();
+        let mut data_end = moof_offset;
+        for traf in moof.trafs.iter() {
+            let track_id = traf.tfhd.track_id;
+            let Some(track) = self.tracks.get(&track_id) else {
+                return Err(Error::TrakNotFound(track_id));
+            };
+            let fragment_track = fragment_tracks.entry(track_id).or_insert_with(|| {
+                let mut fragment_track = Mp4Track::from(&track.trak);
+                fragment_track.default_sample_duration = track.default_sample_duration;
+                fragment_track.default_sample_size = track.default_sample_size;
+                fragment_track.default_sample_description_index =
+                    track.default_sample_description_index;
+                fragment_track.default_sample_flags = track.default_sample_flags;
+                fragment_track
+            });
+            data_end = fragment_track.add_traf(traf, moof_offset, data_end)?;
+        }
+
+        let tracks: Vec<&Mp4Track> = fragment_tracks.values().collect();
+        self.pending.extend(pending_samples(&tracks)?);
+        Ok(())
+    }
+}
+
+fn pending_samples(tracks: &[&Mp4Track]) -> Result<VecDeque<PendingSample>> {
+    let mut pending = Vec::new();
+    for track in tracks {
+        for sample_id in 1..=track.sample_count()? {
+            if let Some((offset, size, sample)) = track.sample_location(sample_id)? {
+                pending.push(PendingSample {
+                    track_id: track.track_id(),
+                    offset,
+                    size,
+                    sample,
+                });
+            }
+        }
+    }
+    pending.sort_by_key(|p| p.offset);
+    Ok(pending.into())
+}
+
+struct TopLevelBox {
+    header: BoxHeader,
+    header_len: u64,
+
+    // Boxes that need parsing are read in full, positioned just past the
+    // header so the regular `ReadBox` implementations can be used on them.
+    data: Option<BufferedBox>,
+}
+
+fn read_top_level_box<R: Read>(reader: &mut R, position: &mut u64) -> Result<Option<TopLevelBox>> {
+    let mut raw = [0u8; 16];
+    let mut len = 0;
+    while len < 8 {
+        match reader.read(&mut raw[len..8])? {
+            0 if len == 0 => return Ok(None),
+            0 => return Err(Error::IoError(io::ErrorKind::UnexpectedEof.into())),
+            n => len += n,
+        }
+    }
+    if raw[0..4] == [0, 0, 0, 1] {
+        reader.read_exact(&mut raw[8..16])?;
+        len = 16;
+    }
+    *position += len as u64;
+
+    let header = BoxHeader::read(&mut &raw[..len])?;
+    if header.size != 0 && header.size < HEADER_SIZE {
+        return Err(Error::InvalidData("box size too small"));
+    }
+    match header.name {
+        BoxType::FtypBox | BoxType::MoovBox | BoxType::MoofBox => {
+            if header.size == 0 {
+                return Err(Error::InvalidData("box size too small"));
+            }
+            let mut buf = raw[..len].to_vec();
+            let size = header.size - HEADER_SIZE;
+            (&mut *reader).take(size).read_to_end(&mut buf)?;
+            if (buf.len() - len) as u64 != size {
+                return Err(Error::IoError(io::ErrorKind::UnexpectedEof.into()));
+            }
+            let start = *position - len as u64;
+            *position += size;
+            Ok(Some(TopLevelBox {
+                header,
+                header_len: len as u64,
+                data: Some(BufferedBox::new(start, buf, len as u64)),
+            }))
+        }
+        _ => Ok(Some(TopLevelBox {
+            header,
+            header_len: len as u64,
+            data: None,
+        })),
+    }
+}
+
+fn skip<R: Read>(reader: &mut R, position: &mut u64, size: u64) -> Result<()> {
+    let skipped = io::copy(&mut reader.take(size), &mut io::sink())?;
+    *position += skipped;
+    if skipped != size {
+        return Err(Error::IoError(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
@@ -4,6 +4,7 @@ use std::convert::TryFrom;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::time::Duration;
 
+use crate::mp4box::elst::ElstEntry;
 use crate::mp4box::traf::TrafBox;
 use crate::mp4box::trak::TrakBox;
 use crate::mp4box::trun::TrunBox;
@@ -89,6 +90,235 @@ impl From<Vp9Config> for TrackConfig {
     }
 }
 
+impl TryFrom<&Mp4Track> for TrackConfig {
+    type Error = Error;
+
+    fn try_from(track: &Mp4Track) -> Result<Self> {
+        let media_conf = match track.media_type()? {
+            MediaType::H264 => MediaConfig::AvcConfig(AvcConfig {
+                width: track.width(),
+                height: track.height(),
+                seq_param_set: track.sequence_parameter_set()?.to_vec(),
+                pic_param_set: track.picture_parameter_set()?.to_vec(),
+            }),
+            MediaType::H265 => MediaConfig::HevcConfig(HevcConfig {
+                width: track.width(),
+                height: track.height(),
+            }),
+            MediaType::VP9 => MediaConfig::Vp9Config(Vp9Config {
+                width: track.width(),
+                height: track.height(),
+            }),
+            MediaType::AAC => MediaConfig::AacConfig(AacConfig {
+                bitrate: track.bitrate(),
+                profile: track.audio_profile()?,
+                freq_index: track.sample_freq_index()?,
+                chan_conf: track.channel_config()?,
+            }),
+            MediaType::TTXT => MediaConfig::TtxtConfig(TtxtConfig {}),
+        };
+        Ok(Self {
+            track_type: track.track_type()?,
+            timescale: track.timescale(),
+            language: track.language().to_string(),
+            media_conf,
+        })
+    }
+}
+
+/// Result of seeking within a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekPosition {
+    /// The sample containing the requested time.
+    pub sample_id: u32,
+
+    /// The closest sync sample at or before `sample_id`, where decoding
+    /// has to start.
+    pub sync_sample_id: u32,
+}
+
+/// An entry of a track's edit list, in the track timescale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditSegment {
+    /// Start of the segment on the presentation timeline.
+    pub presentation_time: u64,
+
+    /// `u64::MAX` for a segment that lasts until the end of the media.
+    pub duration: u64,
+
+    /// Start of the segment on the media timeline, `None` for an empty edit.
+    pub media_time: Option<i64>,
+
+    /// Playback rate as 16.16 fixed point, 0 to dwell on a single sample.
+    pub media_rate: u32,
+}
+
+/// An edit list entry for [Mp4Writer::set_edit_list], in the track timescale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+    /// Length of the edit, or `None` to last until the end of the media.
+    pub duration: Option<u64>,
+
+    /// Start of the edit on the media timeline, `None` for an empty edit.
+    pub media_time: Option<u64>,
+
+    /// 1 to play the media, 0 to hold the sample at `media_time`.
+    pub media_rate: u16,
+}
+
+impl Edit {
+    /// Show nothing for `duration`, delaying everything after it.
+    pub fn empty(duration: u64) -> Self {
+        Self {
+            duration: Some(duration),
+            media_time: None,
+            media_rate: 1,
+        }
+    }
+
+    /// Play the media from `media_time`, for `duration` or to its end.
+    pub fn media(media_time: u64, duration: Option<u64>) -> Self {
+        Self {
+            duration,
+            media_time: Some(media_time),
+            media_rate: 1,
+        }
+    }
+}
+
+/// Where a sample lands on the presentation timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplePresentation {
+    pub sample_id: u32,
+
+    /// Presentation start and end in the track timescale, clipped to the
+    /// edit that shows the sample. `None` when the edit list trims it.
+    pub time: Option<(u64, u64)>,
+}
+
+impl SamplePresentation {
+    pub fn is_trimmed(&self) -> bool {
+        self.time.is_none()
+    }
+}
+
+/// Gapless playback parameters of an audio track, counted in samples of the
+/// decoded audio like `iTunSMPB` does. That rate is the `mp4a` sample rate,
+/// which can differ from the track timescale, as with HE-AAC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GaplessInfo {
+    /// Priming samples added by the encoder at the start.
+    pub encoder_delay: u32,
+
+    /// Samples added at the end to fill the last frame.
+    pub padding: u32,
+
+    /// Number of samples of the original audio.
+    pub sample_count: u64,
+}
+
+pub(crate) const ITUNES_MEAN: &str = "com.apple.iTunes";
+pub(crate) const ITUNSMPB: &str = "iTunSMPB";
+
+impl GaplessInfo {
+    /// Convert from `timescale` units to samples at `sample_rate`.
+    fn from_media(
+        encoder_delay: u64,
+        padding: u64,
+        sample_count: u64,
+        timescale: u32,
+        sample_rate: u32,
+    ) -> Self {
+        let to_samples = |value| rescale(value, timescale, sample_rate);
+        GaplessInfo {
+            encoder_delay: to_samples(encoder_delay).try_into().unwrap_or(u32::MAX),
+            padding: to_samples(padding).try_into().unwrap_or(u32::MAX),
+            sample_count: to_samples(sample_count),
+        }
+    }
+
+    /// Parse the value of an `iTunSMPB` tag, a list of hexadecimal fields
+    /// of which the second to fourth are the delay, padding and sample count.
+    pub fn from_itunsmpb(value: &str) -> Option<Self> {
+        let mut fields = value.split_whitespace().skip(1);
+        let encoder_delay = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let padding = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let sample_count = u64::from_str_radix(fields.next()?, 16).ok()?;
+        Some(GaplessInfo {
+            encoder_delay,
+            padding,
+            sample_count,
+        })
+    }
+
+    /// Format as the value of an `iTunSMPB` tag, the way iTunes writes it.
+    pub fn to_itunsmpb(&self) -> String {
+        format!(
+            " 00000000 {:08X} {:08X} {:016X}{}",
+            self.encoder_delay,
+            self.padding,
+            self.sample_count,
+            " 00000000".repeat(8)
+        )
+    }
+}
+
+/// Convert `value` from `from` to `to` units per second.
+fn rescale(value: u64, from: u32, to: u32) -> u64 {
+    if from == to || from == 0 {
+        return value;
+    }
+    (value as u128 * to as u128 / from as u128)
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
+
+/// Sample rate of the decoded audio of an `mp4a` track, falling back to the
+/// track timescale when the sample entry doesn't give one.
+fn audio_sample_rate(trak: &TrakBox) -> u32 {
+    match trak.mdia.minf.stbl.stsd.mp4a() {
+        Some(mp4a) if mp4a.samplerate.value() > 0 => mp4a.samplerate.value() as u32,
+        _ => trak.mdia.mdhd.timescale,
+    }
+}
+
+impl EditSegment {
+    /// Map the composition interval `start..end` of a sample into this
+    /// segment, if any part of it is shown.
+    fn present(&self, start: i64, end: i64) -> Option<(u64, u64)> {
+        let media_time = self.media_time?;
+        if self.media_rate == 0 {
+            let is_shown = start <= media_time && (media_time < end || media_time == start);
+            let segment_end = self.presentation_time.saturating_add(self.duration);
+            return is_shown.then_some((self.presentation_time, segment_end));
+        }
+
+        let media_end = if self.duration == u64::MAX {
+            i64::MAX
+        } else {
+            let span = (self.duration as i128 * self.media_rate as i128) >> 16;
+            (media_time as i128 + span).min(i64::MAX as i128) as i64
+        };
+        let shown_start = start.max(media_time);
+        let shown_end = end.min(media_end);
+        let is_shown = if start == end {
+            media_time <= start && start < media_end
+        } else {
+            shown_start < shown_end
+        };
+        if !is_shown {
+            return None;
+        }
+
+        let to_presentation = |time: i64| {
+            let elapsed = (time - media_time) as u128 * 0x10000 / self.media_rate as u128;
+            self.presentation_time
+                .saturating_add(elapsed.try_into().unwrap_or(u64::MAX))
+        };
+        Some((to_presentation(shown_start), to_presentation(shown_end)))
+    }
+}
+
 #[derive(Debug)]
 pub struct Mp4Track {
     pub trak: TrakBox,
@@ -96,7 +326,48 @@ pub struct Mp4Track {
     pub moof_offsets: Vec<u64>,
 
     // Fragmented Tracks Defaults.
+    pub default_sample_description_index: u32,
     pub default_sample_duration: u32,
+    pub default_sample_size: u32,
+    pub default_sample_flags: u32,
+
+    // Resolved base data offset of every traf added with add_traf().
+    base_data_offsets: Vec<u64>,
+
+    index: Option<SampleIndex>,
+}
+
+/// Lookup tables for a track, so samples can be located without scanning
+/// the sample tables or fragments on every call.
+#[derive(Debug, Clone, Default)]
+struct SampleIndex {
+    sample_count: u32,
+
+    // Every trun with samples, in decode order.
+    runs: Vec<RunIndex>,
+
+    // (first sample id, start time) of every stts entry.
+    stts_first_samples: Vec<(u32, u64)>,
+
+    // First sample id of every ctts entry.
+    ctts_first_samples: Vec<u32>,
+
+    // Running totals of sample sizes and fragment sample durations, so any
+    // run of them can be summed with a subtraction.
+    size_sums: Option<Vec<u64>>,
+    duration_sums: Vec<u64>,
+
+    // Sync samples of the fragments, in decode order.
+    sync_samples: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RunIndex {
+    first_sample: u32,
+    traf_idx: usize,
+    trun_idx: usize,
+    start_time: u64,
+    data_offset: u64,
 }
 
 impl Mp4Track {
@@ -106,10 +377,195 @@ impl Mp4Track {
             trak,
             trafs: Vec::new(),
             moof_offsets: Vec::new(),
+            default_sample_description_index: 1,
             default_sample_duration: 0,
+            default_sample_size: 0,
+            default_sample_flags: 0,
+            base_data_offsets: Vec::new(),
+            index: None,
+        }
+    }
+
+    pub(crate) fn set_fragment_defaults(&mut self, mvex: &MvexBox) {
+        if let Some(trex) = mvex.trex(self.track_id()) {
+            self.default_sample_description_index = trex.default_sample_description_index;
+            self.default_sample_duration = trex.default_sample_duration;
+            self.default_sample_size = trex.default_sample_size;
+            self.default_sample_flags = trex.default_sample_flags;
         }
     }
 
+    /// Add a traf of the moof at `moof_offset`, where `data_end` is the end
+    /// of the data of the preceding traf in that moof, or `moof_offset` for
+    /// the first one.
+    ///
+    /// Returns the end of the data of this traf, for the next one.
+    pub(crate) fn add_traf(
+        &mut self,
+        traf: &TrafBox,
+        moof_offset: u64,
+        data_end: u64,
+    ) -> Result<u64> {
+        let base_data_offset = if let Some(base_data_offset) = traf.tfhd.base_data_offset {
+            base_data_offset
+        } else if TfhdBox::FLAG_DEFAULT_BASE_IS_MOOF & traf.tfhd.flags != 0 {
+            moof_offset
+        } else {
+            data_end
+        };
+        self.index = None;
+        self.moof_offsets.push(moof_offset);
+        self.trafs.push(traf.clone());
+        self.base_data_offsets.push(base_data_offset);
+
+        let traf_idx = self.trafs.len() - 1;
+        match traf.truns.last() {
+            Some(trun) => {
+                let trun_offset = self.trun_data_offset(traf_idx, traf.truns.len() - 1)?;
+                trun_offset
+                    .checked_add(self.trun_data_size(traf, trun)?)
+                    .ok_or(Error::InvalidData(
+                        "attempt to calculate traf data end with overflow",
+                    ))
+            }
+            None => Ok(base_data_offset),
+        }
+    }
+
+    /// Build lookup tables so that locating a sample no longer scans the
+    /// sample tables or fragments, which is otherwise linear per sample.
+    ///
+    /// The index reflects `trak` and `trafs` at the time it is built; call
+    /// this again after modifying them, or [Mp4Track::drop_index].
+    pub fn build_index(&mut self) -> Result<()> {
+        self.index = None;
+        let mut index = SampleIndex::default();
+
+        let mut start_time = 0u64;
+        for (traf_idx, traf) in self.trafs.iter().enumerate() {
+            if let Some(tfdt) = &traf.tfdt {
+                start_time = tfdt.base_media_decode_time;
+            }
+            for (trun_idx, trun) in traf.truns.iter().enumerate() {
+                if trun.sample_count == 0 {
+                    continue;
+                }
+                let first_sample = index.sample_count + 1;
+                index.runs.push(RunIndex {
+                    first_sample,
+                    traf_idx,
+                    trun_idx,
+                    start_time,
+                    data_offset: self.trun_data_offset(traf_idx, trun_idx)?,
+                });
+                index.sample_count =
+                    index
+                        .sample_count
+                        .checked_add(trun.sample_count)
+                        .ok_or(Error::InvalidData(
+                            "attempt to sum trun sample_count with overflow",
+                        ))?;
+
+                let mut sum = index.duration_sums.last().copied().unwrap_or(0);
+                index.duration_sums.reserve(trun.sample_count as usize);
+                for sample_idx in 0..trun.sample_count as usize {
+                    index.duration_sums.push(sum);
+                    let duration = self.trun_sample_duration(traf, trun, sample_idx) as u64;
+                    sum += duration;
+                    start_time += duration;
+
+                    if self.trun_sample_flags(traf, trun, sample_idx).is_sync() {
+                        index.sync_samples.push(first_sample + sample_idx as u32);
+                    }
+                }
+            }
+        }
+        if self.trafs.is_empty() {
+            index.sample_count = self.trak.mdia.minf.stbl.stsz.sample_count;
+        }
+
+        let mut first_sample = 1u32;
+        let mut start_time = 0u64;
+        for entry in self.trak.mdia.minf.stbl.stts.entries.iter() {
+            index.stts_first_samples.push((first_sample, start_time));
+            first_sample =
+                first_sample
+                    .checked_add(entry.sample_count)
+                    .ok_or(Error::InvalidData(
+                        "attempt to sum stts entries sample_count with overflow",
+                    ))?;
+            start_time += entry.sample_count as u64 * entry.sample_delta as u64;
+        }
+
+        if let Some(ctts) = &self.trak.mdia.minf.stbl.ctts {
+            let mut first_sample = 1u32;
+            for entry in ctts.entries.iter() {
+                index.ctts_first_samples.push(first_sample);
+                first_sample =
+                    first_sample
+                        .checked_add(entry.sample_count)
+                        .ok_or(Error::InvalidData(
+                            "attempt to sum ctts entries sample_count with overflow",
+                        ))?;
+            }
+        }
+
+        // Sample sizes go through sample_size(), which can already use the
+        // fragment table above.
+        self.index = Some(index);
+        let sample_count = self.sample_count()?;
+        let mut size_sums = Vec::with_capacity(sample_count as usize + 1);
+        let mut sum = 0u64;
+        size_sums.push(sum);
+        for sample_id in 1..=sample_count {
+            match self.sample_size(sample_id) {
+                Ok(size) => sum += size as u64,
+                Err(_) => break,
+            }
+            size_sums.push(sum);
+        }
+        if let Some(index) = self.index.as_mut()
+            && size_sums.len() == sample_count as usize + 1
+        {
+            index.size_sums = Some(size_sums);
+        }
+
+        Ok(())
+    }
+
+    pub fn drop_index(&mut self) {
+        self.index = None;
+    }
+
+    pub fn has_index(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Sum of the sizes of samples `from..to`.
+    fn sample_size_sum(&self, from: u32, to: u32) -> Result<u64> {
+        if let Some(size_sums) = self
+            .index
+            .as_ref()
+            .and_then(|index| index.size_sums.as_ref())
+            && let (Some(start), Some(end)) = (
+                size_sums.get(from as usize - 1),
+                size_sums.get(to as usize - 1),
+            )
+        {
+            return Ok(end - start);
+        }
+
+        let mut sum = 0u64;
+        for i in from..to {
+            sum = sum
+                .checked_add(self.sample_size(i)? as u64)
+                .ok_or(Error::InvalidData(
+                    "attempt to sum sample sizes with overflow",
+                ))?;
+        }
+        Ok(sum)
+    }
+
     pub fn track_id(&self) -> u32 {
         self.trak.tkhd.track_id
     }
@@ -119,15 +575,15 @@ impl Mp4Track {
     }
 
     pub fn media_type(&self) -> Result<MediaType> {
-        if self.trak.mdia.minf.stbl.stsd.avc1.is_some() {
+        if self.trak.mdia.minf.stbl.stsd.avc1().is_some() {
             Ok(MediaType::H264)
-        } else if self.trak.mdia.minf.stbl.stsd.hev1.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.hev1().is_some() {
             Ok(MediaType::H265)
-        } else if self.trak.mdia.minf.stbl.stsd.vp09.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.vp09().is_some() {
             Ok(MediaType::VP9)
-        } else if self.trak.mdia.minf.stbl.stsd.mp4a.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.mp4a().is_some() {
             Ok(MediaType::AAC)
-        } else if self.trak.mdia.minf.stbl.stsd.tx3g.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.tx3g().is_some() {
             Ok(MediaType::TTXT)
         } else {
             Err(Error::InvalidData("unsupported media type"))
@@ -135,15 +591,15 @@ impl Mp4Track {
     }
 
     pub fn box_type(&self) -> Result<FourCC> {
-        if self.trak.mdia.minf.stbl.stsd.avc1.is_some() {
+        if self.trak.mdia.minf.stbl.stsd.avc1().is_some() {
             Ok(FourCC::from(BoxType::Avc1Box))
-        } else if self.trak.mdia.minf.stbl.stsd.hev1.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.hev1().is_some() {
             Ok(FourCC::from(BoxType::Hev1Box))
-        } else if self.trak.mdia.minf.stbl.stsd.vp09.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.vp09().is_some() {
             Ok(FourCC::from(BoxType::Vp09Box))
-        } else if self.trak.mdia.minf.stbl.stsd.mp4a.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.mp4a().is_some() {
             Ok(FourCC::from(BoxType::Mp4aBox))
-        } else if self.trak.mdia.minf.stbl.stsd.tx3g.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.tx3g().is_some() {
             Ok(FourCC::from(BoxType::Tx3gBox))
         } else {
             Err(Error::InvalidData("unsupported sample entry box"))
@@ -151,7 +607,7 @@ impl Mp4Track {
     }
 
     pub fn width(&self) -> u16 {
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             avc1.width
         } else {
             self.trak.tkhd.width.value()
@@ -159,7 +615,7 @@ impl Mp4Track {
     }
 
     pub fn height(&self) -> u16 {
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             avc1.height
         } else {
             self.trak.tkhd.height.value()
@@ -168,15 +624,14 @@ impl Mp4Track {
 
     pub fn frame_rate(&self) -> f64 {
         let dur = self.duration();
-        if dur.is_zero() {
-            0.0
-        } else {
-            self.sample_count() as f64 / dur.as_secs_f64()
+        match self.sample_count() {
+            Ok(sample_count) if !dur.is_zero() => sample_count as f64 / dur.as_secs_f64(),
+            _ => 0.0,
         }
     }
 
     pub fn sample_freq_index(&self) -> Result<SampleFreqIndex> {
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
+        if let Some(mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a() {
             if let Some(ref esds) = mp4a.esds {
                 SampleFreqIndex::try_from(esds.es_desc.dec_config.dec_specific.freq_index)
             } else {
@@ -188,7 +643,7 @@ impl Mp4Track {
     }
 
     pub fn channel_config(&self) -> Result<ChannelConfig> {
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
+        if let Some(mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a() {
             if let Some(ref esds) = mp4a.esds {
                 ChannelConfig::try_from(esds.es_desc.dec_config.dec_specific.chan_conf)
             } else {
@@ -214,7 +669,7 @@ impl Mp4Track {
     }
 
     pub fn bitrate(&self) -> u32 {
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
+        if let Some(mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a() {
             if let Some(ref esds) = mp4a.esds {
                 esds.es_desc.dec_config.avg_bitrate
             } else {
@@ -232,24 +687,28 @@ impl Mp4Track {
         }
     }
 
-    pub fn sample_count(&self) -> u32 {
+    pub fn sample_count(&self) -> Result<u32> {
+        if let Some(index) = &self.index {
+            return Ok(index.sample_count);
+        }
         if !self.trafs.is_empty() {
             let mut sample_count = 0u32;
-            for traf in self.trafs.iter() {
-                if let Some(ref trun) = traf.trun {
-                    sample_count = sample_count
+            for trun in self.trafs.iter().flat_map(|traf| traf.truns.iter()) {
+                sample_count =
+                    sample_count
                         .checked_add(trun.sample_count)
-                        .expect("attempt to sum trun sample_count with overflow");
-                }
+                        .ok_or(Error::InvalidData(
+                            "attempt to sum trun sample_count with overflow",
+                        ))?;
             }
-            sample_count
+            Ok(sample_count)
         } else {
-            self.trak.mdia.minf.stbl.stsz.sample_count
+            Ok(self.trak.mdia.minf.stbl.stsz.sample_count)
         }
     }
 
     pub fn video_profile(&self) -> Result<AvcProfile> {
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             AvcProfile::try_from((
                 avc1.avcc.avc_profile_indication,
                 avc1.avcc.profile_compatibility,
@@ -260,8 +719,8 @@ impl Mp4Track {
     }
 
     pub fn sequence_parameter_set(&self) -> Result<&[u8]> {
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
-            match avc1.avcc.sequence_parameter_sets.get(0) {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
+            match avc1.avcc.sequence_parameter_sets.first() {
                 Some(nal) => Ok(nal.bytes.as_ref()),
                 None => Err(Error::EntryInStblNotFound(
                     self.track_id(),
@@ -275,8 +734,8 @@ impl Mp4Track {
     }
 
     pub fn picture_parameter_set(&self) -> Result<&[u8]> {
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
-            match avc1.avcc.picture_parameter_sets.get(0) {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
+            match avc1.avcc.picture_parameter_sets.first() {
                 Some(nal) => Ok(nal.bytes.as_ref()),
                 None => Err(Error::EntryInStblNotFound(
                     self.track_id(),
@@ -290,7 +749,7 @@ impl Mp4Track {
     }
 
     pub fn audio_profile(&self) -> Result<AudioObjectType> {
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
+        if let Some(mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a() {
             if let Some(ref esds) = mp4a.esds {
                 AudioObjectType::try_from(esds.es_desc.dec_config.dec_specific.profile)
             } else {
@@ -305,16 +764,11 @@ impl Mp4Track {
         if self.trak.mdia.minf.stbl.stsc.entries.is_empty() {
             return Err(Error::InvalidData("no stsc entries"));
         }
-        for (i, entry) in self.trak.mdia.minf.stbl.stsc.entries.iter().enumerate() {
-            if sample_id < entry.first_sample {
-                return if i == 0 {
-                    Err(Error::InvalidData("sample not found"))
-                } else {
-                    Ok(i - 1)
-                };
-            }
+        let entries = &self.trak.mdia.minf.stbl.stsc.entries;
+        match entries.partition_point(|entry| entry.first_sample <= sample_id) {
+            0 => Err(Error::InvalidData("sample not found")),
+            i => Ok(i - 1),
         }
-        Ok(self.trak.mdia.minf.stbl.stsc.entries.len() - 1)
     }
 
     fn chunk_offset(&self, chunk_id: u32) -> Result<u64> {
@@ -347,6 +801,19 @@ impl Mp4Track {
 
     fn ctts_index(&self, sample_id: u32) -> Result<(usize, u32)> {
         let ctts = self.trak.mdia.minf.stbl.ctts.as_ref().unwrap();
+        if let Some(index) = &self.index {
+            let first_samples = &index.ctts_first_samples;
+            let i = first_samples.partition_point(|first| *first <= sample_id);
+            if i > 0 && sample_id - first_samples[i - 1] < ctts.entries[i - 1].sample_count {
+                return Ok((i - 1, first_samples[i - 1]));
+            }
+            return Err(Error::EntryInStblNotFound(
+                self.track_id(),
+                BoxType::CttsBox,
+                sample_id,
+            ));
+        }
+
         let mut sample_count: u32 = 1;
         for (i, entry) in ctts.entries.iter().enumerate() {
             let next_sample_count =
@@ -368,35 +835,143 @@ impl Mp4Track {
         ))
     }
 
-    /// return `(traf_idx, sample_idx_in_trun)`
-    fn find_traf_idx_and_sample_idx(&self, sample_id: u32) -> Option<(usize, usize)> {
-        let global_idx = sample_id - 1;
+    /// return `(traf_idx, trun_idx, sample_idx_in_trun)`
+    fn find_traf_trun_and_sample_idx(&self, sample_id: u32) -> Option<(usize, usize, usize)> {
+        if self.index.is_some() {
+            let run = self.indexed_run(sample_id)?;
+            let sample_idx = sample_id - run.first_sample;
+            let trun = &self.trafs[run.traf_idx].truns[run.trun_idx];
+            return (sample_idx < trun.sample_count).then_some((
+                run.traf_idx,
+                run.trun_idx,
+                sample_idx as usize,
+            ));
+        }
+
+        let global_idx = sample_id.checked_sub(1)?;
         let mut offset = 0;
-        for traf_idx in 0..self.trafs.len() {
-            if let Some(trun) = &self.trafs[traf_idx].trun {
+        for (traf_idx, traf) in self.trafs.iter().enumerate() {
+            for (trun_idx, trun) in traf.truns.iter().enumerate() {
                 let sample_count = trun.sample_count;
                 if sample_count > (global_idx - offset) {
-                    return Some((traf_idx, (global_idx - offset) as _));
+                    return Some((traf_idx, trun_idx, (global_idx - offset) as _));
                 }
-                offset = offset
-                    .checked_add(sample_count)
-                    .expect("attempt to sum trun sample_count with overflow");
+                offset = offset.checked_add(sample_count)?;
             }
         }
         None
     }
 
+    /// The indexed trun that `sample_id` falls in, or its predecessor.
+    fn indexed_run(&self, sample_id: u32) -> Option<&RunIndex> {
+        let runs = &self.index.as_ref()?.runs;
+        let i = runs.partition_point(|run| run.first_sample <= sample_id);
+        runs.get(i.checked_sub(1)?)
+    }
+
+    /// Duration of a fragment sample, from the trun entry, the tfhd default
+    /// or the trex default, in that order.
+    fn trun_sample_duration(&self, traf: &TrafBox, trun: &TrunBox, sample_idx: usize) -> u32 {
+        if TrunBox::FLAG_SAMPLE_DURATION & trun.flags != 0
+            && let Some(duration) = trun.sample_durations.get(sample_idx)
+        {
+            *duration
+        } else if let Some(duration) = traf.tfhd.default_sample_duration {
+            duration
+        } else {
+            self.default_sample_duration
+        }
+    }
+
+    /// Size of a fragment sample, from the trun entry, the tfhd default or the
+    /// trex default, in that order.
+    fn trun_sample_size(&self, traf: &TrafBox, trun: &TrunBox, sample_idx: usize) -> Option<u32> {
+        if TrunBox::FLAG_SAMPLE_SIZE & trun.flags != 0 {
+            trun.sample_sizes.get(sample_idx).copied()
+        } else if let Some(size) = traf.tfhd.default_sample_size {
+            Some(size)
+        } else {
+            Some(self.default_sample_size)
+        }
+    }
+
+    fn trun_data_size(&self, traf: &TrafBox, trun: &TrunBox) -> Result<u64> {
+        let mut size = 0u64;
+        for sample_idx in 0..trun.sample_count as usize {
+            let sample_size =
+                self.trun_sample_size(traf, trun, sample_idx)
+                    .ok_or(Error::EntryInTrunNotFound(
+                        self.track_id(),
+                        BoxType::TrunBox,
+                        sample_idx as u32,
+                    ))?;
+            size += sample_size as u64;
+        }
+        Ok(size)
+    }
+
+    /// Offset of the first sample of a trun. Runs without a data offset
+    /// follow the data of the preceding run, or start at the base data
+    /// offset of the traf.
+    fn trun_data_offset(&self, traf_idx: usize, trun_idx: usize) -> Result<u64> {
+        let traf = &self.trafs[traf_idx];
+        let base_data_offset = match self.base_data_offsets.get(traf_idx) {
+            Some(base_data_offset) => *base_data_offset,
+            None => traf
+                .tfhd
+                .base_data_offset
+                .unwrap_or(self.moof_offsets[traf_idx]),
+        };
+
+        let mut offset = base_data_offset;
+        for (i, trun) in traf.truns.iter().enumerate() {
+            if let Some(data_offset) = trun.data_offset {
+                offset = base_data_offset
+                    .checked_add_signed(data_offset as i64)
+                    .ok_or(Error::InvalidData(
+                        "attempt to calculate trun sample offset with overflow",
+                    ))?;
+            }
+            if i == trun_idx {
+                break;
+            }
+            offset += self.trun_data_size(traf, trun)?;
+        }
+        Ok(offset)
+    }
+
+    fn trun_duration(&self, traf: &TrafBox, trun: &TrunBox) -> u64 {
+        (0..trun.sample_count as usize)
+            .map(|sample_idx| self.trun_sample_duration(traf, trun, sample_idx) as u64)
+            .sum()
+    }
+
+    /// Decode time of the first sample of a traf: its tfdt if present,
+    /// otherwise the end of the preceding traf.
+    fn traf_start_time(&self, traf_idx: usize) -> u64 {
+        let mut elapsed = 0u64;
+        for i in (0..=traf_idx).rev() {
+            if let Some(tfdt) = &self.trafs[i].tfdt {
+                return tfdt.base_media_decode_time + elapsed;
+            }
+            if i > 0 {
+                let traf = &self.trafs[i - 1];
+                for trun in traf.truns.iter() {
+                    elapsed += self.trun_duration(traf, trun);
+                }
+            }
+        }
+        elapsed
+    }
+
     fn sample_size(&self, sample_id: u32) -> Result<u32> {
         if !self.trafs.is_empty() {
-            if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                if let Some(size) = self.trafs[traf_idx]
-                    .trun
-                    .as_ref()
-                    .unwrap()
-                    .sample_sizes
-                    .get(sample_idx)
-                {
-                    Ok(*size)
+            if let Some((traf_idx, trun_idx, sample_idx)) =
+                self.find_traf_trun_and_sample_idx(sample_id)
+            {
+                let traf = &self.trafs[traf_idx];
+                if let Some(size) = self.trun_sample_size(traf, &traf.truns[trun_idx], sample_idx) {
+                    Ok(size)
                 } else {
                     Err(Error::EntryInTrunNotFound(
                         self.track_id(),
@@ -427,7 +1002,7 @@ impl Mp4Track {
     fn total_sample_size(&self) -> u64 {
         let stsz = &self.trak.mdia.minf.stbl.stsz;
         if stsz.sample_size > 0 {
-            stsz.sample_size as u64 * self.sample_count() as u64
+            stsz.sample_size as u64 * self.sample_count().unwrap_or_default() as u64
         } else {
             let mut total_size = 0;
             for size in stsz.sample_sizes.iter() {
@@ -439,30 +1014,20 @@ impl Mp4Track {
 
     pub fn sample_offset(&self, sample_id: u32) -> Result<u64> {
         if !self.trafs.is_empty() {
-            if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                let mut sample_offset = self.trafs[traf_idx]
-                    .tfhd
-                    .base_data_offset
-                    .unwrap_or(self.moof_offsets[traf_idx]);
-
-                if let Some(data_offset) = self.trafs[traf_idx]
-                    .trun
-                    .as_ref()
-                    .and_then(|trun| trun.data_offset)
-                {
-                    sample_offset = sample_offset.checked_add_signed(data_offset as i64).ok_or(
-                        Error::InvalidData("attempt to calculate trun sample offset with overflow"),
-                    )?;
-                }
+            if let Some((traf_idx, trun_idx, sample_idx)) =
+                self.find_traf_trun_and_sample_idx(sample_id)
+            {
+                let mut sample_offset = match self.indexed_run(sample_id) {
+                    Some(run) => run.data_offset,
+                    None => self.trun_data_offset(traf_idx, trun_idx)?,
+                };
 
                 let first_sample_in_trun = sample_id - sample_idx as u32;
-                for i in first_sample_in_trun..sample_id {
-                    sample_offset = sample_offset
-                        .checked_add(self.sample_size(i)? as u64)
-                        .ok_or(Error::InvalidData(
-                            "attempt to calculate trun entry sample offset with overflow",
-                        ))?;
-                }
+                sample_offset = sample_offset
+                    .checked_add(self.sample_size_sum(first_sample_in_trun, sample_id)?)
+                    .ok_or(Error::InvalidData(
+                        "attempt to calculate trun entry sample offset with overflow",
+                    ))?;
 
                 Ok(sample_offset)
             } else {
@@ -490,45 +1055,61 @@ impl Mp4Track {
 
             let first_sample_in_chunk = sample_id - (sample_id - first_sample) % samples_per_chunk;
 
-            let mut sample_offset = 0;
-            for i in first_sample_in_chunk..sample_id {
-                sample_offset += self.sample_size(i)?;
-            }
+            let sample_offset = self.sample_size_sum(first_sample_in_chunk, sample_id)?;
 
-            Ok(chunk_offset + sample_offset as u64)
+            Ok(chunk_offset + sample_offset)
         }
     }
 
     fn sample_time(&self, sample_id: u32) -> Result<(u64, u32)> {
         if !self.trafs.is_empty() {
-            let mut base_start_time = 0;
-            let mut default_sample_duration = self.default_sample_duration;
-            if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                let traf = &self.trafs[traf_idx];
-                if let Some(tfdt) = &traf.tfdt {
-                    base_start_time = tfdt.base_media_decode_time;
-                }
-                if let Some(duration) = traf.tfhd.default_sample_duration {
-                    default_sample_duration = duration;
-                }
-                if let Some(trun) = &traf.trun {
-                    if TrunBox::FLAG_SAMPLE_DURATION & trun.flags != 0 {
-                        let mut start_offset = 0u64;
-                        for duration in &trun.sample_durations[..sample_idx] {
-                            start_offset = start_offset.checked_add(*duration as u64).ok_or(
-                                Error::InvalidData("attempt to sum sample durations with overflow"),
-                            )?;
-                        }
-                        let duration = trun.sample_durations[sample_idx];
-                        return Ok((base_start_time + start_offset, duration));
-                    }
-                }
+            let Some((traf_idx, trun_idx, sample_idx)) =
+                self.find_traf_trun_and_sample_idx(sample_id)
+            else {
+                return Err(Error::BoxInTrafNotFound(self.track_id(), BoxType::TrafBox));
+            };
+            let traf = &self.trafs[traf_idx];
+            let trun = &traf.truns[trun_idx];
+            let duration = self.trun_sample_duration(traf, trun, sample_idx);
+
+            if let Some(index) = &self.index
+                && let Some(run) = self.indexed_run(sample_id)
+            {
+                let start_time = run.start_time + index.duration_sums[sample_id as usize - 1]
+                    - index.duration_sums[run.first_sample as usize - 1];
+                return Ok((start_time, duration));
+            }
+
+            let mut start_time = self.traf_start_time(traf_idx);
+            for trun in &traf.truns[..trun_idx] {
+                start_time += self.trun_duration(traf, trun);
+            }
+            for sample_idx in 0..sample_idx {
+                start_time += self.trun_sample_duration(traf, trun, sample_idx) as u64;
             }
-            let start_offset = ((sample_id - 1) * default_sample_duration) as u64;
-            Ok((base_start_time + start_offset, default_sample_duration))
+            Ok((start_time, duration))
         } else {
             let stts = &self.trak.mdia.minf.stbl.stts;
 
+            if let Some(index) = &self.index {
+                let first_samples = &index.stts_first_samples;
+                let i = first_samples.partition_point(|(first, _)| *first <= sample_id);
+                if i > 0 {
+                    let (first_sample, elapsed) = first_samples[i - 1];
+                    let entry = &stts.entries[i - 1];
+                    if sample_id - first_sample < entry.sample_count {
+                        let start_time =
+                            (sample_id - first_sample) as u64 * entry.sample_delta as u64 + elapsed;
+                        return Ok((start_time, entry.sample_delta));
+                    }
+                }
+                return Err(Error::EntryInStblNotFound(
+                    self.track_id(),
+                    BoxType::SttsBox,
+                    sample_id,
+                ));
+            }
+
             let mut sample_count: u32 = 1;
             let mut elapsed = 0;
 
@@ -559,28 +1140,83 @@ impl Mp4Track {
 
     fn sample_rendering_offset(&self, sample_id: u32) -> i32 {
         if !self.trafs.is_empty() {
-            if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                if let Some(cts) = self.trafs[traf_idx]
-                    .trun
-                    .as_ref()
-                    .and_then(|trun| trun.sample_cts.get(sample_idx))
-                {
-                    return *cts as i32;
-                }
-            }
-        } else if let Some(ref ctts) = self.trak.mdia.minf.stbl.ctts {
-            if let Ok((ctts_index, _)) = self.ctts_index(sample_id) {
-                let ctts_entry = ctts.entries.get(ctts_index).unwrap();
-                return ctts_entry.sample_offset;
+            if let Some((traf_idx, trun_idx, sample_idx)) =
+                self.find_traf_trun_and_sample_idx(sample_id)
+                && let Some(cts) = self.trafs[traf_idx].truns[trun_idx]
+                    .sample_cts
+                    .get(sample_idx)
+            {
+                return *cts as i32;
             }
+        } else if let Some(ref ctts) = self.trak.mdia.minf.stbl.ctts
+            && let Ok((ctts_index, _)) = self.ctts_index(sample_id)
+        {
+            let ctts_entry = ctts.entries.get(ctts_index).unwrap();
+            return ctts_entry.sample_offset;
         }
         0
     }
 
+    /// Resolve the flags of a fragment sample, from the trun entry, the
+    /// trun first sample flags, the tfhd default or the trex default, in
+    /// that order.
+    fn sample_flags(&self, sample_id: u32) -> Option<SampleFlags> {
+        let (traf_idx, trun_idx, sample_idx) = self.find_traf_trun_and_sample_idx(sample_id)?;
+        let traf = &self.trafs[traf_idx];
+        Some(self.trun_sample_flags(traf, &traf.truns[trun_idx], sample_idx))
+    }
+
+    /// Flags of a fragment sample, from the trun entry, the trun first sample
+    /// flags, the tfhd default or the trex default, in that order.
+    fn trun_sample_flags(&self, traf: &TrafBox, trun: &TrunBox, sample_idx: usize) -> SampleFlags {
+        let bits = if TrunBox::FLAG_SAMPLE_FLAGS & trun.flags != 0
+            && let Some(flags) = trun.sample_flags.get(sample_idx)
+        {
+            *flags
+        } else if sample_idx == 0
+            && let Some(flags) = trun.first_sample_flags
+        {
+            flags
+        } else if let Some(flags) = traf.tfhd.default_sample_flags {
+            flags
+        } else {
+            self.default_sample_flags
+        };
+        SampleFlags::from_bits(bits)
+    }
+
+    fn sample_description_index(&self, sample_id: u32) -> Result<u32> {
+        if !self.trafs.is_empty() {
+            let Some((traf_idx, _, _)) = self.find_traf_trun_and_sample_idx(sample_id) else {
+                return Err(Error::BoxInTrafNotFound(self.track_id(), BoxType::TrafBox));
+            };
+            Ok(self.trafs[traf_idx]
+                .tfhd
+                .sample_description_index
+                .unwrap_or(self.default_sample_description_index))
+        } else {
+            let stsc_index = self.stsc_index(sample_id)?;
+            let stsc_entry = &self.trak.mdia.minf.stbl.stsc.entries[stsc_index];
+            Ok(stsc_entry.sample_description_index)
+        }
+    }
+
+    /// The sample description that samples with `sample_description_index`
+    /// refer to.
+    pub fn sample_description(&self, sample_description_index: u32) -> Option<&SampleEntry> {
+        self.trak
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .entry(sample_description_index)
+    }
+
     fn is_sync_sample(&self, sample_id: u32) -> bool {
         if !self.trafs.is_empty() {
-            let sample_sizes_count = self.sample_count() / self.trafs.len() as u32;
-            return sample_id == 1 || sample_id % sample_sizes_count == 0;
+            return self
+                .sample_flags(sample_id)
+                .is_none_or(|flags| flags.is_sync());
         }
 
         if let Some(ref stss) = self.trak.mdia.minf.stbl.stss {
@@ -590,11 +1226,270 @@ impl Mp4Track {
         }
     }
 
-    pub(crate) fn read_sample<R: Read + Seek>(
+    /// Find the sample whose decode time span contains `time`, along with
+    /// the closest sync sample at or before it.
+    ///
+    /// Returns `None` if `time` is past the end of the track. Times before
+    /// the first sample resolve to the first sample.
+    pub fn seek(&self, time: Duration) -> Result<Option<SeekPosition>> {
+        let timestamp = time.as_nanos() * self.timescale() as u128 / 1_000_000_000;
+        self.seek_to_timestamp(timestamp.try_into().unwrap_or(u64::MAX))
+    }
+
+    /// Same as [Mp4Track::seek], with `timestamp` in the track timescale.
+    pub fn seek_to_timestamp(&self, timestamp: u64) -> Result<Option<SeekPosition>> {
+        let sample_count = self.sample_count()?;
+        if sample_count == 0 {
+            return Ok(None);
+        }
+
+        // Decode times only ever increase, so binary search for the last
+        // sample starting at or before the timestamp.
+        let (mut lo, mut hi) = (1, sample_count);
+        while lo < hi {
+            let mid = lo + (hi - lo).div_ceil(2);
+            let (start_time, _) = self.sample_time(mid)?;
+            if start_time <= timestamp {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        let sample_id = lo;
+
+        if sample_id == sample_count {
+            let (start_time, duration) = self.sample_time(sample_id)?;
+            if timestamp >= start_time + duration as u64 {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(SeekPosition {
+            sample_id,
+            sync_sample_id: self.sync_sample_before(sample_id),
+        }))
+    }
+
+    /// The edit list mapped to the track timescale. Without an edit list,
+    /// media time maps to presentation time as is.
+    pub fn edit_segments(&self, movie_timescale: u32) -> Result<Vec<EditSegment>> {
+        let Some(elst) = self.trak.edts.as_ref().and_then(|edts| edts.elst.as_ref()) else {
+            return Ok(vec![EditSegment {
+                presentation_time: 0,
+                duration: u64::MAX,
+                media_time: Some(0),
+                media_rate: 0x10000,
+            }]);
+        };
+        if movie_timescale == 0 {
+            return Err(Error::InvalidData("movie timescale must not be zero"));
+        }
+
+        let mut segments = Vec::with_capacity(elst.entries.len());
+        let mut presentation_time = 0u64;
+        for entry in elst.entries.iter() {
+            let media_time = (entry.media_time >= 0).then_some(entry.media_time);
+            let duration = if entry.segment_duration == 0 && media_time.is_some() {
+                u64::MAX
+            } else {
+                let duration = entry.segment_duration as u128 * self.timescale() as u128
+                    / movie_timescale as u128;
+                duration.try_into().unwrap_or(u64::MAX)
+            };
+            segments.push(EditSegment {
+                presentation_time,
+                duration,
+                media_time,
+                media_rate: (entry.media_rate as u32) << 16 | entry.media_rate_fraction as u32,
+            });
+            presentation_time = presentation_time.saturating_add(duration);
+        }
+        Ok(segments)
+    }
+
+    /// Presentation start and end of a sample in the track timescale, after
+    /// applying the edit list. Returns `None` if the edit list trims the
+    /// sample, and the first edit showing it if there are several.
+    pub fn presentation_time(
         &self,
-        reader: &mut R,
         sample_id: u32,
-    ) -> Result<Option<Mp4Sample>> {
+        movie_timescale: u32,
+    ) -> Result<Option<(u64, u64)>> {
+        let segments = self.edit_segments(movie_timescale)?;
+        self.present_sample(&segments, sample_id)
+    }
+
+    /// [Mp4Track::presentation_time] of every sample, in sample order.
+    pub fn presentation_timeline(&self, movie_timescale: u32) -> Result<Vec<SamplePresentation>> {
+        let segments = self.edit_segments(movie_timescale)?;
+        (1..=self.sample_count()?)
+            .map(|sample_id| {
+                Ok(SamplePresentation {
+                    sample_id,
+                    time: self.present_sample(&segments, sample_id)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Duration of the track after applying the edit list.
+    pub fn presentation_duration(&self, movie_timescale: u32) -> Result<Duration> {
+        let mut duration = 0u64;
+        for segment in self.edit_segments(movie_timescale)? {
+            duration = duration.saturating_add(if segment.duration != u64::MAX {
+                segment.duration
+            } else {
+                // Lasts until the end of the media.
+                let media_time = segment.media_time.unwrap_or(0) as u64;
+                let remaining = self.trak.mdia.mdhd.duration.saturating_sub(media_time);
+                (remaining as u128 * 0x10000 / segment.media_rate.max(1) as u128)
+                    .try_into()
+                    .unwrap_or(u64::MAX)
+            });
+        }
+        let micros = duration as u128 * 1_000_000 / self.timescale().max(1) as u128;
+        Ok(Duration::from_micros(micros.try_into().unwrap_or(u64::MAX)))
+    }
+
+    /// Encoder delay, padding and original length of an AAC track.
+    ///
+    /// These come from the `iTunSMPB` tag of `metadata` if it has one,
+    /// otherwise from the first edit that shows media, and otherwise from the
+    /// `roll` sample group, which only gives the delay. `None` for other
+    /// tracks or when none of these are present.
+    pub fn gapless_info<'a>(
+        &self,
+        metadata: impl Metadata<'a>,
+        movie_timescale: u32,
+    ) -> Result<Option<GaplessInfo>> {
+        if self.trak.mdia.minf.stbl.stsd.mp4a().is_none() {
+            return Ok(None);
+        }
+
+        if let Some(DataValue::Text(value)) = metadata.freeform(ITUNES_MEAN, ITUNSMPB)
+            && let Some(info) = GaplessInfo::from_itunsmpb(&value)
+        {
+            return Ok(Some(info));
+        }
+
+        let sample_count = self.sample_count()?;
+        let media_duration = if sample_count == 0 {
+            0
+        } else {
+            let (start_time, duration) = self.sample_time(sample_count)?;
+            start_time + duration as u64
+        };
+
+        if let Some(edts) = &self.trak.edts
+            && edts.elst.is_some()
+        {
+            let segments = self.edit_segments(movie_timescale)?;
+            if let Some(segment) = segments.iter().find(|segment| segment.media_time.is_some()) {
+                let encoder_delay = segment.media_time.unwrap_or(0) as u64;
+                let remaining = media_duration.saturating_sub(encoder_delay);
+                let sample_count = segment.duration.min(remaining);
+                return Ok(Some(GaplessInfo::from_media(
+                    encoder_delay,
+                    remaining - sample_count,
+                    sample_count,
+                    self.timescale(),
+                    audio_sample_rate(&self.trak),
+                )));
+            }
+        }
+
+        let stbl = &self.trak.mdia.minf.stbl;
+        let roll_distance = stbl
+            .sgpds
+            .iter()
+            .find_map(|sgpd| sgpd.roll_distances().first().copied());
+        if let Some(roll_distance) = roll_distance.filter(|distance| *distance < 0) {
+            // The samples to decode first are the priming samples.
+            let frame_duration = match stbl.stts.entries.first() {
+                Some(entry) => entry.sample_delta,
+                None => self.default_sample_duration,
+            };
+            let encoder_delay = roll_distance.unsigned_abs() as u64 * frame_duration as u64;
+            return Ok(Some(GaplessInfo::from_media(
+                encoder_delay,
+                0,
+                media_duration.saturating_sub(encoder_delay),
+                self.timescale(),
+                audio_sample_rate(&self.trak),
+            )));
+        }
+
+        Ok(None)
+    }
+
+    fn present_sample(
+        &self,
+        segments: &[EditSegment],
+        sample_id: u32,
+    ) -> Result<Option<(u64, u64)>> {
+        let (start_time, duration) = self.sample_time(sample_id)?;
+        let start = start_time as i64 + self.sample_rendering_offset(sample_id) as i64;
+        let end = start + duration as i64;
+        Ok(segments
+            .iter()
+            .find_map(|segment| segment.present(start, end)))
+    }
+
+    fn sync_sample_before(&self, sample_id: u32) -> u32 {
+        if self.trafs.is_empty() {
+            if let Some(ref stss) = self.trak.mdia.minf.stbl.stss {
+                let idx = stss.entries.partition_point(|id| *id <= sample_id);
+                return if idx == 0 { 1 } else { stss.entries[idx - 1] };
+            }
+            return sample_id;
+        }
+        if let Some(index) = &self.index {
+            let idx = index.sync_samples.partition_point(|id| *id <= sample_id);
+            return if idx == 0 {
+                1
+            } else {
+                index.sync_samples[idx - 1]
+            };
+        }
+
+        // Walk back from the sample through the truns, rather than locating
+        // every earlier sample on its own.
+        let Some((traf_idx, trun_idx, sample_idx)) = self.find_traf_trun_and_sample_idx(sample_id)
+        else {
+            return 1;
+        };
+        let runs = self.trafs[..=traf_idx]
+            .iter()
+            .enumerate()
+            .rev()
+            .flat_map(|(idx, traf)| {
+                let truns = if idx == traf_idx {
+                    &traf.truns[..=trun_idx]
+                } else {
+                    &traf.truns[..]
+                };
+                truns.iter().rev().map(move |trun| (traf, trun))
+            });
+        let mut id = sample_id;
+        for (run_idx, (traf, trun)) in runs.enumerate() {
+            let end = if run_idx == 0 {
+                sample_idx + 1
+            } else {
+                trun.sample_count as usize
+            };
+            for sample_idx in (0..end).rev() {
+                if self.trun_sample_flags(traf, trun, sample_idx).is_sync() {
+                    return id;
+                }
+                id -= 1;
+            }
+        }
+        1
+    }
+
+    /// Locate a sample, returning its offset and size along with a sample
+    /// whose `bytes` are left empty.
+    pub(crate) fn sample_location(&self, sample_id: u32) -> Result<Option<(u64, u32, Mp4Sample)>> {
         let sample_offset = match self.sample_offset(sample_id) {
             Ok(offset) => offset,
             Err(Error::EntryInStblNotFound(_, _, _)) => return Ok(None),
@@ -606,21 +1501,44 @@ impl Mp4Track {
             Err(err) => return Err(err),
         };
 
-        let mut buffer = vec![0x0u8; sample_size as usize];
-        reader.seek(SeekFrom::Start(sample_offset))?;
-        reader.read_exact(&mut buffer)?;
-
-        let (start_time, duration) = self.sample_time(sample_id).unwrap(); // XXX
+        let (start_time, duration) = self.sample_time(sample_id)?;
         let rendering_offset = self.sample_rendering_offset(sample_id);
         let is_sync = self.is_sync_sample(sample_id);
+        let flags = if self.trafs.is_empty() {
+            None
+        } else {
+            self.sample_flags(sample_id)
+        };
+        let sample_description_index = self.sample_description_index(sample_id)?;
 
-        Ok(Some(Mp4Sample {
+        let sample = Mp4Sample {
             start_time,
             duration,
             rendering_offset,
             is_sync,
-            bytes: Bytes::from(buffer),
-        }))
+            flags,
+            sample_description_index,
+            bytes: Bytes::new(),
+        };
+        Ok(Some((sample_offset, sample_size, sample)))
+    }
+
+    pub(crate) fn read_sample<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        sample_id: u32,
+    ) -> Result<Option<Mp4Sample>> {
+        let Some((sample_offset, sample_size, mut sample)) = self.sample_location(sample_id)?
+        else {
+            return Ok(None);
+        };
+
+        let mut buffer = vec![0x0u8; sample_size as usize];
+        reader.seek(SeekFrom::Start(sample_offset))?;
+        reader.read_exact(&mut buffer)?;
+        sample.bytes = Bytes::from(buffer);
+
+        Ok(Some(sample))
     }
 }
 
@@ -635,67 +1553,162 @@ pub(crate) struct Mp4TrackWriter {
     chunk_samples: u32,
     chunk_duration: u32,
     chunk_buffer: BytesMut,
+    chunk_sample_description_index: u32,
 
     samples_per_chunk: u32,
     duration_per_chunk: u32,
-}
 
-impl Mp4TrackWriter {
-    pub(crate) fn new(track_id: u32, config: &TrackConfig) -> Result<Self> {
-        let mut trak = TrakBox::default();
-        trak.tkhd.track_id = track_id;
-        trak.mdia.mdhd.timescale = config.timescale;
-        trak.mdia.mdhd.language = config.language.to_owned();
-        trak.mdia.hdlr.handler_type = config.track_type.into();
-        trak.mdia.minf.stbl.co64 = Some(Co64Box::default());
-        match config.media_conf {
-            MediaConfig::AvcConfig(ref avc_config) => {
-                trak.tkhd.set_width(avc_config.width);
-                trak.tkhd.set_height(avc_config.height);
-
-                let vmhd = VmhdBox::default();
-                trak.mdia.minf.vmhd = Some(vmhd);
+    edits: Vec<Edit>,
+}
 
-                let avc1 = Avc1Box::new(avc_config);
-                trak.mdia.minf.stbl.stsd.avc1 = Some(avc1);
-            }
-            MediaConfig::HevcConfig(ref hevc_config) => {
-                trak.tkhd.set_width(hevc_config.width);
-                trak.tkhd.set_height(hevc_config.height);
+/// Build an empty trak describing `config`, without any sample tables filled in.
+pub(crate) fn new_trak(track_id: u32, config: &TrackConfig) -> TrakBox {
+    let mut trak = TrakBox::default();
+    trak.tkhd.track_id = track_id;
+    trak.mdia.mdhd.timescale = config.timescale;
+    trak.mdia.mdhd.language = config.language.to_owned();
+    trak.mdia.hdlr.handler_type = config.track_type.into();
+    match config.media_conf {
+        MediaConfig::AvcConfig(ref avc_config) => {
+            trak.tkhd.set_width(avc_config.width);
+            trak.tkhd.set_height(avc_config.height);
+
+            let vmhd = VmhdBox::default();
+            trak.mdia.minf.vmhd = Some(vmhd);
+        }
+        MediaConfig::HevcConfig(ref hevc_config) => {
+            trak.tkhd.set_width(hevc_config.width);
+            trak.tkhd.set_height(hevc_config.height);
 
-                let vmhd = VmhdBox::default();
-                trak.mdia.minf.vmhd = Some(vmhd);
+            let vmhd = VmhdBox::default();
+            trak.mdia.minf.vmhd = Some(vmhd);
+        }
+        MediaConfig::Vp9Config(ref config) => {
+            trak.tkhd.set_width(config.width);
+            trak.tkhd.set_height(config.height);
+        }
+        MediaConfig::AacConfig(_) => {
+            let smhd = SmhdBox::default();
+            trak.mdia.minf.smhd = Some(smhd);
+        }
+        MediaConfig::TtxtConfig(_) => {}
+    }
+    trak.mdia.minf.stbl.stsd.entries.push(sample_entry(config));
+    trak
+}
 
-                let hev1 = Hev1Box::new(hevc_config);
-                trak.mdia.minf.stbl.stsd.hev1 = Some(hev1);
-            }
-            MediaConfig::Vp9Config(ref config) => {
-                trak.tkhd.set_width(config.width);
-                trak.tkhd.set_height(config.height);
+/// Add a sample description to `trak`, returning its `sample_description_index`.
+pub(crate) fn add_sample_description(trak: &mut TrakBox, config: &TrackConfig) -> Result<u32> {
+    if trak.mdia.hdlr.handler_type != config.track_type.into() {
+        return Err(Error::InvalidData(
+            "sample description does not match the track type",
+        ));
+    }
+    let stsd = &mut trak.mdia.minf.stbl.stsd;
+    stsd.entries.push(sample_entry(config));
+    Ok(stsd.entries.len() as u32)
+}
 
-                trak.mdia.minf.stbl.stsd.vp09 = Some(Vp09Box::new(config));
-            }
-            MediaConfig::AacConfig(ref aac_config) => {
-                let smhd = SmhdBox::default();
-                trak.mdia.minf.smhd = Some(smhd);
+fn sample_entry(config: &TrackConfig) -> SampleEntry {
+    match config.media_conf {
+        MediaConfig::AvcConfig(ref avc_config) => SampleEntry::Avc1(Avc1Box::new(avc_config)),
+        MediaConfig::HevcConfig(ref hevc_config) => SampleEntry::Hev1(Hev1Box::new(hevc_config)),
+        MediaConfig::Vp9Config(ref config) => SampleEntry::Vp09(Vp09Box::new(config)),
+        MediaConfig::AacConfig(ref aac_config) => SampleEntry::Mp4a(Mp4aBox::new(aac_config)),
+        MediaConfig::TtxtConfig(_) => SampleEntry::Tx3g(Tx3gBox::default()),
+    }
+}
 
-                let mp4a = Mp4aBox::new(aac_config);
-                trak.mdia.minf.stbl.stsd.mp4a = Some(mp4a);
-            }
-            MediaConfig::TtxtConfig(ref _ttxt_config) => {
-                let tx3g = Tx3gBox::default();
-                trak.mdia.minf.stbl.stsd.tx3g = Some(tx3g);
-            }
-        }
+impl Mp4TrackWriter {
+    pub(crate) fn new(track_id: u32, config: &TrackConfig) -> Result<Self> {
+        let mut trak = new_trak(track_id, config);
+        trak.mdia.minf.stbl.co64 = Some(Co64Box::default());
         Ok(Mp4TrackWriter {
             trak,
             chunk_buffer: BytesMut::new(),
             sample_id: 1,
+            chunk_sample_description_index: 1,
             duration_per_chunk: config.timescale, // 1 second
             ..Self::default()
         })
     }
 
+    /// Add a sample description, returning the index that samples using it
+    /// should carry in `sample_description_index`.
+    pub(crate) fn add_sample_description(&mut self, config: &TrackConfig) -> Result<u32> {
+        add_sample_description(&mut self.trak, config)
+    }
+
+    pub(crate) fn set_sample_entry(&mut self, entry: SampleEntry) {
+        self.trak.mdia.minf.stbl.stsd.entries[0] = entry;
+    }
+
+    pub(crate) fn set_edit_list(&mut self, edits: &[Edit]) -> Result<()> {
+        for (i, edit) in edits.iter().enumerate() {
+            if edit.duration.is_none()
+                && (i + 1 < edits.len() || edit.media_time.is_none() || edit.media_rate == 0)
+            {
+                return Err(Error::InvalidData(
+                    "only the last edit may play until the end of the media",
+                ));
+            }
+        }
+        self.edits = edits.to_vec();
+        Ok(())
+    }
+
+    /// Set an edit list that skips the delay and padding of `info`.
+    pub(crate) fn set_gapless_info(&mut self, info: &GaplessInfo) -> Result<()> {
+        let sample_rate = audio_sample_rate(&self.trak);
+        let to_media = |value| rescale(value, sample_rate, self.trak.mdia.mdhd.timescale);
+        let edit = Edit::media(
+            to_media(info.encoder_delay as u64),
+            Some(to_media(info.sample_count)),
+        );
+        self.set_edit_list(&[edit])
+    }
+
+    fn update_edit_list(&mut self, movie_timescale: u32) {
+        if self.edits.is_empty() {
+            return;
+        }
+
+        let media_duration = self.trak.mdia.mdhd.duration;
+        let timescale = self.trak.mdia.mdhd.timescale.max(1);
+        let mut elst = ElstBox::default();
+        for edit in self.edits.iter() {
+            let duration = edit
+                .duration
+                .unwrap_or_else(|| media_duration.saturating_sub(edit.media_time.unwrap_or(0)));
+            let segment_duration = duration as u128 * movie_timescale as u128 / timescale as u128;
+            elst.entries.push(ElstEntry {
+                segment_duration: segment_duration.try_into().unwrap_or(u64::MAX),
+                media_time: edit.media_time.map_or(-1, |time| time as i64),
+                media_rate: edit.media_rate,
+                media_rate_fraction: 0,
+            });
+        }
+        if elst.entries.iter().any(|entry| {
+            entry.segment_duration > u32::MAX as u64 || entry.media_time > i32::MAX as i64
+        }) {
+            elst.version = 1;
+        }
+
+        // The track duration is that of the presentation, so it follows the edits.
+        self.trak.tkhd.duration = elst.entries.iter().fold(0u64, |sum, entry| {
+            sum.saturating_add(entry.segment_duration)
+        });
+        self.trak.tkhd.version = if self.trak.tkhd.duration > u32::MAX as u64 {
+            1
+        } else {
+            0
+        };
+        self.trak.edts = Some(EdtsBox {
+            elst: Some(elst),
+            ..Default::default()
+        });
+    }
+
     fn update_sample_sizes(&mut self, size: u32) {
         if self.trak.mdia.minf.stbl.stsz.sample_count == 0 {
             if size == 0 {
@@ -731,11 +1744,11 @@ impl Mp4TrackWriter {
     }
 
     fn update_sample_times(&mut self, dur: u32) {
-        if let Some(ref mut entry) = self.trak.mdia.minf.stbl.stts.entries.last_mut() {
-            if entry.sample_delta == dur {
-                entry.sample_count += 1;
-                return;
-            }
+        if let Some(ref mut entry) = self.trak.mdia.minf.stbl.stts.entries.last_mut()
+            && entry.sample_delta == dur
+        {
+            entry.sample_count += 1;
+            return;
         }
 
         let entry = SttsEntry {
@@ -764,11 +1777,11 @@ impl Mp4TrackWriter {
             self.trak.mdia.minf.stbl.ctts.as_mut().unwrap()
         };
 
-        if let Some(ref mut entry) = ctts.entries.last_mut() {
-            if entry.sample_offset == offset {
-                entry.sample_count += 1;
-                return;
-            }
+        if let Some(ref mut entry) = ctts.entries.last_mut()
+            && entry.sample_offset == offset
+        {
+            entry.sample_count += 1;
+            return;
         }
 
         let entry = CttsEntry {
@@ -823,6 +1836,24 @@ impl Mp4TrackWriter {
         sample: &Mp4Sample,
         movie_timescale: u32,
     ) -> Result<u64> {
+        let sample_description_index = sample.sample_description_index;
+        if self
+            .trak
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .entry(sample_description_index)
+            .is_none()
+        {
+            return Err(Error::InvalidData("sample description index out of range"));
+        }
+        // A chunk only ever holds samples of a single description.
+        if sample_description_index != self.chunk_sample_description_index {
+            self.write_chunk(writer)?;
+            self.chunk_sample_description_index = sample_description_index;
+        }
+
         self.chunk_buffer.extend_from_slice(&sample.bytes);
         self.chunk_samples += 1;
         self.chunk_duration += sample.duration;
@@ -846,16 +1877,17 @@ impl Mp4TrackWriter {
     }
 
     fn update_sample_to_chunk(&mut self, chunk_id: u32) {
-        if let Some(entry) = self.trak.mdia.minf.stbl.stsc.entries.last() {
-            if entry.samples_per_chunk == self.chunk_samples {
-                return;
-            }
+        if let Some(entry) = self.trak.mdia.minf.stbl.stsc.entries.last()
+            && entry.samples_per_chunk == self.chunk_samples
+            && entry.sample_description_index == self.chunk_sample_description_index
+        {
+            return;
         }
 
         let entry = StscEntry {
             first_chunk: chunk_id,
             samples_per_chunk: self.chunk_samples,
-            sample_description_index: 1,
+            sample_description_index: self.chunk_sample_description_index,
             first_sample: self.sample_id - self.chunk_samples + 1,
         };
         self.trak.mdia.minf.stbl.stsc.entries.push(entry);
@@ -896,17 +1928,24 @@ impl Mp4TrackWriter {
         }
     }
 
-    pub(crate) fn write_end<W: Write + Seek>(&mut self, writer: &mut W) -> Result<TrakBox> {
+    pub(crate) fn write_end<W: Write + Seek>(
+        &mut self,
+        writer: &mut W,
+        movie_timescale: u32,
+    ) -> Result<TrakBox> {
         self.write_chunk(writer)?;
+        self.update_edit_list(movie_timescale);
 
         let max_sample_size = self.max_sample_size();
-        if let Some(ref mut mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
-            if let Some(ref mut esds) = mp4a.esds {
+        for entry in self.trak.mdia.minf.stbl.stsd.entries.iter_mut() {
+            if let SampleEntry::Mp4a(mp4a) = entry
+                && let Some(ref mut esds) = mp4a.esds
+            {
                 esds.es_desc.dec_config.buffer_size_db = max_sample_size;
+                // TODO
+                // mp4a.esds.es_desc.dec_config.max_bitrate
+                // mp4a.esds.es_desc.dec_config.avg_bitrate
             }
-            // TODO
-            // mp4a.esds.es_desc.dec_config.max_bitrate
-            // mp4a.esds.es_desc.dec_config.avg_bitrate
         }
         if let Ok(stco) = StcoBox::try_from(self.trak.mdia.minf.stbl.co64.as_ref().unwrap()) {
             self.trak.mdia.minf.stbl.stco = Some(stco);
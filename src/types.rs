@@ -621,6 +621,13 @@ pub struct Mp4Sample {
     pub duration: u32,
     pub rendering_offset: i32,
     pub is_sync: bool,
+
+    /// Dependency flags of a sample in a fragment, as signalled by `trun`,
+    /// `tfhd` or `trex`. `None` for samples described by `stbl`.
+    pub flags: Option<SampleFlags>,
+
+    /// 1-based index of the `stsd` entry describing this sample.
+    pub sample_description_index: u32,
     pub bytes: Bytes,
 }
 
@@ -630,10 +637,56 @@ impl PartialEq for Mp4Sample {
             && self.duration == other.duration
             && self.rendering_offset == other.rendering_offset
             && self.is_sync == other.is_sync
+            && self.flags == other.flags
+            && self.sample_description_index == other.sample_description_index
             && self.bytes.len() == other.bytes.len() // XXX for easy check
     }
 }
 
+/// Sample flags as defined in ISO/IEC 14496-12 8.8.3.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SampleFlags {
+    pub is_leading: u8,
+
+    /// 1: depends on other samples, 2: does not depend on others (I-picture).
+    pub sample_depends_on: u8,
+
+    /// 1: other samples depend on this one, 2: disposable.
+    pub sample_is_depended_on: u8,
+    pub sample_has_redundancy: u8,
+    pub sample_padding_value: u8,
+    pub is_non_sync_sample: bool,
+    pub degradation_priority: u16,
+}
+
+impl SampleFlags {
+    pub fn from_bits(bits: u32) -> Self {
+        Self {
+            is_leading: ((bits >> 26) & 0x3) as u8,
+            sample_depends_on: ((bits >> 24) & 0x3) as u8,
+            sample_is_depended_on: ((bits >> 22) & 0x3) as u8,
+            sample_has_redundancy: ((bits >> 20) & 0x3) as u8,
+            sample_padding_value: ((bits >> 17) & 0x7) as u8,
+            is_non_sync_sample: (bits >> 16) & 0x1 == 1,
+            degradation_priority: bits as u16,
+        }
+    }
+
+    pub fn to_bits(&self) -> u32 {
+        ((self.is_leading as u32 & 0x3) << 26)
+            | ((self.sample_depends_on as u32 & 0x3) << 24)
+            | ((self.sample_is_depended_on as u32 & 0x3) << 22)
+            | ((self.sample_has_redundancy as u32 & 0x3) << 20)
+            | ((self.sample_padding_value as u32 & 0x7) << 17)
+            | ((self.is_non_sync_sample as u32) << 16)
+            | self.degradation_priority as u32
+    }
+
+    pub fn is_sync(&self) -> bool {
+        !self.is_non_sync_sample
+    }
+}
+
 impl fmt::Display for Mp4Sample {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -659,10 +712,26 @@ pub fn creation_time(creation_time: u64) -> u64 {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum DataType {
-    Binary = 0x000000,
-    Text = 0x000001,
-    Image = 0x00000D,
-    TempoCpil = 0x000015,
+    Binary,
+    Text,
+    Utf16,
+    Image, // JPEG
+    Png,
+    TempoCpil,   // big-endian signed integer of 1 to 8 bytes
+    UnsignedInt, // big-endian unsigned integer of 1 to 8 bytes
+    Float32,
+    Float64,
+    Bmp,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+    /// Any other type, whose data is kept as binary.
+    Other(u32),
 }
 
 #[allow(clippy::derivable_impls)]
@@ -672,40 +741,202 @@ impl std::default::Default for DataType {
     }
 }
 
-impl TryFrom<u32> for DataType {
-    type Error = Error;
-    fn try_from(value: u32) -> Result<DataType> {
+impl From<u32> for DataType {
+    fn from(value: u32) -> DataType {
         match value {
-            0x000000 => Ok(DataType::Binary),
-            0x000001 => Ok(DataType::Text),
-            0x00000D => Ok(DataType::Image),
-            0x000015 => Ok(DataType::TempoCpil),
-            _ => Err(Error::InvalidData("invalid data type")),
+            0x000000 => DataType::Binary,
+            0x000001 => DataType::Text,
+            0x000002 => DataType::Utf16,
+            0x00000D => DataType::Image,
+            0x00000E => DataType::Png,
+            0x000015 => DataType::TempoCpil,
+            0x000016 => DataType::UnsignedInt,
+            0x000017 => DataType::Float32,
+            0x000018 => DataType::Float64,
+            0x00001B => DataType::Bmp,
+            0x000041 => DataType::Int8,
+            0x000042 => DataType::Int16,
+            0x000043 => DataType::Int32,
+            0x00004A => DataType::Int64,
+            0x00004B => DataType::Uint8,
+            0x00004C => DataType::Uint16,
+            0x00004D => DataType::Uint32,
+            0x00004E => DataType::Uint64,
+            value => DataType::Other(value),
         }
     }
 }
 
+impl From<&DataType> for u32 {
+    fn from(data_type: &DataType) -> u32 {
+        match data_type {
+            DataType::Binary => 0x000000,
+            DataType::Text => 0x000001,
+            DataType::Utf16 => 0x000002,
+            DataType::Image => 0x00000D,
+            DataType::Png => 0x00000E,
+            DataType::TempoCpil => 0x000015,
+            DataType::UnsignedInt => 0x000016,
+            DataType::Float32 => 0x000017,
+            DataType::Float64 => 0x000018,
+            DataType::Bmp => 0x00001B,
+            DataType::Int8 => 0x000041,
+            DataType::Int16 => 0x000042,
+            DataType::Int32 => 0x000043,
+            DataType::Int64 => 0x00004A,
+            DataType::Uint8 => 0x00004B,
+            DataType::Uint16 => 0x00004C,
+            DataType::Uint32 => 0x00004D,
+            DataType::Uint64 => 0x00004E,
+            DataType::Other(value) => *value,
+        }
+    }
+}
+
+/// The value of a metadata item, decoded according to its [DataType].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataValue<'a> {
+    Text(Cow<'a, str>),
+    Integer(i64),
+    UnsignedInteger(u64),
+    Float(f64),
+    /// Images, implicitly typed data, and anything that doesn't decode as
+    /// its type.
+    Binary(&'a [u8]),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum MetadataKey {
     Title,
     Year,
     Poster,
     Summary,
+    Artist,
+    AlbumArtist,
+    Album,
+    Genre,
+    GenreId,
+    TrackNumber,
+    DiscNumber,
+    Composer,
+    Comment,
+    Encoder,
+    Bpm,
+    Compilation,
+    Rating,
+    MediaKind,
+    TvShow,
+    TvEpisode,
+    TvSeason,
+    Lyrics,
+    SortTitle,
+    SortArtist,
+    SortAlbum,
+    SortAlbumArtist,
 }
 
 pub trait Metadata<'a> {
     /// The video's title
-    fn title(&self) -> Option<Cow<str>>;
+    fn title(&self) -> Option<Cow<'_, str>>;
     /// The video's release year
     fn year(&self) -> Option<u32>;
     /// The video's poster (cover art)
     fn poster(&self) -> Option<&[u8]>;
     /// The video's summary
-    fn summary(&self) -> Option<Cow<str>>;
+    fn summary(&self) -> Option<Cow<'_, str>>;
+    /// The track's artist
+    fn artist(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The album's artist
+    fn album_artist(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The album's name
+    fn album(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The genre, either as text or the name of a numeric ID3v1 genre
+    fn genre(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The track number and the total number of tracks, 0 if unknown
+    fn track_number(&self) -> Option<(u16, u16)> {
+        None
+    }
+    /// The disc number and the total number of discs, 0 if unknown
+    fn disc_number(&self) -> Option<(u16, u16)> {
+        None
+    }
+    /// The composer
+    fn composer(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// A free text comment
+    fn comment(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The tool that encoded the file
+    fn encoder(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The tempo in beats per minute
+    fn bpm(&self) -> Option<u16> {
+        None
+    }
+    /// Whether the track is part of a compilation
+    fn compilation(&self) -> Option<bool> {
+        None
+    }
+    /// The content rating: 0 none, 1 or 4 explicit, 2 clean
+    fn rating(&self) -> Option<u8> {
+        None
+    }
+    /// The iTunes media kind (`stik`), e.g. 1 music, 2 audiobook, 6 music video, 9 movie, 10 TV show
+    fn media_kind(&self) -> Option<u8> {
+        None
+    }
+    /// The TV show's name
+    fn tv_show(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The TV episode number
+    fn tv_episode(&self) -> Option<u32> {
+        None
+    }
+    /// The TV season number
+    fn tv_season(&self) -> Option<u32> {
+        None
+    }
+    /// The lyrics
+    fn lyrics(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The title to sort by
+    fn sort_title(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The artist to sort by
+    fn sort_artist(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The album to sort by
+    fn sort_album(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// The album artist to sort by
+    fn sort_album_artist(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// A freeform (`----`) item by its mean and name, e.g. `com.apple.iTunes`
+    /// and `iTunSMPB`
+    fn freeform(&self, _mean: &str, _name: &str) -> Option<DataValue<'_>> {
+        None
+    }
 }
 
 impl<'a, T: Metadata<'a>> Metadata<'a> for &'a T {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         (**self).title()
     }
 
@@ -717,13 +948,101 @@ impl<'a, T: Metadata<'a>> Metadata<'a> for &'a T {
         (**self).poster()
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         (**self).summary()
     }
+
+    fn artist(&self) -> Option<Cow<'_, str>> {
+        (**self).artist()
+    }
+
+    fn album_artist(&self) -> Option<Cow<'_, str>> {
+        (**self).album_artist()
+    }
+
+    fn album(&self) -> Option<Cow<'_, str>> {
+        (**self).album()
+    }
+
+    fn genre(&self) -> Option<Cow<'_, str>> {
+        (**self).genre()
+    }
+
+    fn track_number(&self) -> Option<(u16, u16)> {
+        (**self).track_number()
+    }
+
+    fn disc_number(&self) -> Option<(u16, u16)> {
+        (**self).disc_number()
+    }
+
+    fn composer(&self) -> Option<Cow<'_, str>> {
+        (**self).composer()
+    }
+
+    fn comment(&self) -> Option<Cow<'_, str>> {
+        (**self).comment()
+    }
+
+    fn encoder(&self) -> Option<Cow<'_, str>> {
+        (**self).encoder()
+    }
+
+    fn bpm(&self) -> Option<u16> {
+        (**self).bpm()
+    }
+
+    fn compilation(&self) -> Option<bool> {
+        (**self).compilation()
+    }
+
+    fn rating(&self) -> Option<u8> {
+        (**self).rating()
+    }
+
+    fn media_kind(&self) -> Option<u8> {
+        (**self).media_kind()
+    }
+
+    fn tv_show(&self) -> Option<Cow<'_, str>> {
+        (**self).tv_show()
+    }
+
+    fn tv_episode(&self) -> Option<u32> {
+        (**self).tv_episode()
+    }
+
+    fn tv_season(&self) -> Option<u32> {
+        (**self).tv_season()
+    }
+
+    fn lyrics(&self) -> Option<Cow<'_, str>> {
+        (**self).lyrics()
+    }
+
+    fn sort_title(&self) -> Option<Cow<'_, str>> {
+        (**self).sort_title()
+    }
+
+    fn sort_artist(&self) -> Option<Cow<'_, str>> {
+        (**self).sort_artist()
+    }
+
+    fn sort_album(&self) -> Option<Cow<'_, str>> {
+        (**self).sort_album()
+    }
+
+    fn sort_album_artist(&self) -> Option<Cow<'_, str>> {
+        (**self).sort_album_artist()
+    }
+
+    fn freeform(&self, mean: &str, name: &str) -> Option<DataValue<'_>> {
+        (**self).freeform(mean, name)
+    }
 }
 
 impl<'a, T: Metadata<'a>> Metadata<'a> for Option<T> {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         self.as_ref().and_then(|t| t.title())
     }
 
@@ -735,7 +1054,289 @@ impl<'a, T: Metadata<'a>> Metadata<'a> for Option<T> {
         self.as_ref().and_then(|t| t.poster())
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         self.as_ref().and_then(|t| t.summary())
     }
+
+    fn artist(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.artist())
+    }
+
+    fn album_artist(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.album_artist())
+    }
+
+    fn album(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.album())
+    }
+
+    fn genre(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.genre())
+    }
+
+    fn track_number(&self) -> Option<(u16, u16)> {
+        self.as_ref().and_then(|t| t.track_number())
+    }
+
+    fn disc_number(&self) -> Option<(u16, u16)> {
+        self.as_ref().and_then(|t| t.disc_number())
+    }
+
+    fn composer(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.composer())
+    }
+
+    fn comment(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.comment())
+    }
+
+    fn encoder(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.encoder())
+    }
+
+    fn bpm(&self) -> Option<u16> {
+        self.as_ref().and_then(|t| t.bpm())
+    }
+
+    fn compilation(&self) -> Option<bool> {
+        self.as_ref().and_then(|t| t.compilation())
+    }
+
+    fn rating(&self) -> Option<u8> {
+        self.as_ref().and_then(|t| t.rating())
+    }
+
+    fn media_kind(&self) -> Option<u8> {
+        self.as_ref().and_then(|t| t.media_kind())
+    }
+
+    fn tv_show(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.tv_show())
+    }
+
+    fn tv_episode(&self) -> Option<u32> {
+        self.as_ref().and_then(|t| t.tv_episode())
+    }
+
+    fn tv_season(&self) -> Option<u32> {
+        self.as_ref().and_then(|t| t.tv_season())
+    }
+
+    fn lyrics(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.lyrics())
+    }
+
+    fn sort_title(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.sort_title())
+    }
+
+    fn sort_artist(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.sort_artist())
+    }
+
+    fn sort_album(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.sort_album())
+    }
+
+    fn sort_album_artist(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().and_then(|t| t.sort_album_artist())
+    }
+
+    fn freeform(&self, mean: &str, name: &str) -> Option<DataValue<'_>> {
+        self.as_ref().and_then(|t| t.freeform(mean, name))
+    }
+}
+
+/// Editing of the metadata items read through [Metadata].
+pub trait MetadataMut {
+    /// Set the data of an item, replacing any previous value
+    fn set_item(&mut self, key: MetadataKey, data_type: DataType, data: Vec<u8>);
+    /// Remove an item, returning whether it was present
+    fn remove_item(&mut self, key: &MetadataKey) -> bool;
+    /// Set a freeform (`----`) item, replacing any with the same mean and name
+    fn set_freeform(&mut self, mean: &str, name: &str, data_type: DataType, data: Vec<u8>);
+    /// Remove a freeform item, returning whether it was present
+    fn remove_freeform(&mut self, mean: &str, name: &str) -> bool;
+
+    /// Set a text item
+    fn set_text(&mut self, key: MetadataKey, text: &str) {
+        self.set_item(key, DataType::Text, text.as_bytes().to_vec());
+    }
+    /// Set a freeform text item
+    fn set_freeform_text(&mut self, mean: &str, name: &str, text: &str) {
+        self.set_freeform(mean, name, DataType::Text, text.as_bytes().to_vec());
+    }
+    /// Set the title
+    fn set_title(&mut self, title: &str) {
+        self.set_text(MetadataKey::Title, title);
+    }
+    /// Set the release year
+    fn set_year(&mut self, year: u32) {
+        self.set_text(MetadataKey::Year, &year.to_string());
+    }
+    /// Set the poster (cover art) from JPEG or PNG data
+    fn set_poster(&mut self, image: &[u8]) {
+        let data_type = if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+            DataType::Png
+        } else {
+            DataType::Image
+        };
+        self.set_item(MetadataKey::Poster, data_type, image.to_vec());
+    }
+    /// Set the summary
+    fn set_summary(&mut self, summary: &str) {
+        self.set_text(MetadataKey::Summary, summary);
+    }
+    /// Set the artist
+    fn set_artist(&mut self, artist: &str) {
+        self.set_text(MetadataKey::Artist, artist);
+    }
+    /// Set the album's artist
+    fn set_album_artist(&mut self, album_artist: &str) {
+        self.set_text(MetadataKey::AlbumArtist, album_artist);
+    }
+    /// Set the album's name
+    fn set_album(&mut self, album: &str) {
+        self.set_text(MetadataKey::Album, album);
+    }
+    /// Set the genre as text, replacing a numeric genre
+    fn set_genre(&mut self, genre: &str) {
+        self.remove_item(&MetadataKey::GenreId);
+        self.set_text(MetadataKey::Genre, genre);
+    }
+    /// Set the track number and the total number of tracks, 0 if unknown
+    fn set_track_number(&mut self, number: u16, total: u16) {
+        let mut data = vec![0, 0];
+        data.extend(number.to_be_bytes());
+        data.extend(total.to_be_bytes());
+        data.extend([0, 0]);
+        self.set_item(MetadataKey::TrackNumber, DataType::Binary, data);
+    }
+    /// Set the disc number and the total number of discs, 0 if unknown
+    fn set_disc_number(&mut self, number: u16, total: u16) {
+        let mut data = vec![0, 0];
+        data.extend(number.to_be_bytes());
+        data.extend(total.to_be_bytes());
+        self.set_item(MetadataKey::DiscNumber, DataType::Binary, data);
+    }
+}
+
+/// The QuickTime `mdta` key holding the recording location.
+pub const QUICKTIME_LOCATION: &str = "com.apple.quicktime.location.ISO6709";
+
+/// A point on the globe, as given by an ISO 6709 string such as the
+/// `com.apple.quicktime.location.ISO6709` key.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Location {
+    /// Degrees north of the equator
+    pub latitude: f64,
+    /// Degrees east of the prime meridian
+    pub longitude: f64,
+    /// Meters above sea level
+    pub altitude: Option<f64>,
+}
+
+impl Location {
+    /// Parse an ISO 6709 string such as `+37.3349-122.0090+012.345/`, with
+    /// angles in degrees, degrees and minutes (`+3720.09`) or degrees,
+    /// minutes and seconds (`+372005.4`).
+    pub fn from_iso6709(s: &str) -> Result<Self> {
+        // Drop the terminator and any coordinate reference system.
+        let s = s.trim().trim_end_matches('/');
+        let s = s.split("CRS").next().unwrap_or(s);
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for (i, c) in s.char_indices().skip(1) {
+            if c == '+' || c == '-' {
+                parts.push(&s[start..i]);
+                start = i;
+            }
+        }
+        parts.push(&s[start..]);
+        if parts.len() != 2 && parts.len() != 3 {
+            return Err(Error::InvalidData("invalid ISO 6709 location"));
+        }
+
+        let latitude = parse_iso6709_angle(parts[0], 2)?;
+        let longitude = parse_iso6709_angle(parts[1], 3)?;
+        let altitude = match parts.get(2) {
+            Some(part) => {
+                let (sign, digits) = split_iso6709_sign(part)?;
+                Some(sign * parse_iso6709_number(digits)?)
+            }
+            None => None,
+        };
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(Error::InvalidData("ISO 6709 location is out of range"));
+        }
+
+        Ok(Location {
+            latitude,
+            longitude,
+            altitude,
+        })
+    }
+
+    /// Format as an ISO 6709 string in decimal degrees, as cameras write it.
+    pub fn to_iso6709(&self) -> String {
+        let mut s = format!("{:+08.4}{:+09.4}", self.latitude, self.longitude);
+        if let Some(altitude) = self.altitude {
+            s += &format!("{altitude:+.3}");
+        }
+        s.push('/');
+        s
+    }
+}
+
+fn split_iso6709_sign(s: &str) -> Result<(f64, &str)> {
+    if let Some(digits) = s.strip_prefix('+') {
+        Ok((1.0, digits))
+    } else if let Some(digits) = s.strip_prefix('-') {
+        Ok((-1.0, digits))
+    } else {
+        Err(Error::InvalidData("ISO 6709 value has no sign"))
+    }
+}
+
+fn parse_iso6709_number(s: &str) -> Result<f64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return Err(Error::InvalidData("invalid ISO 6709 number"));
+    }
+    s.parse()
+        .map_err(|_| Error::InvalidData("invalid ISO 6709 number"))
+}
+
+/// Parse a signed angle whose degrees take `degree_digits` digits, followed
+/// by optional minutes and seconds.
+fn parse_iso6709_angle(s: &str, degree_digits: usize) -> Result<f64> {
+    let (sign, digits) = split_iso6709_sign(s)?;
+    let integer_digits = digits.find('.').unwrap_or(digits.len());
+    let (degrees, minutes, seconds) = match integer_digits.checked_sub(degree_digits) {
+        Some(0) => (digits, None, None),
+        Some(2) => {
+            let (degrees, minutes) = digits.split_at(degree_digits);
+            (degrees, Some(minutes), None)
+        }
+        Some(4) => {
+            let (degrees, rest) = digits.split_at(degree_digits);
+            let (minutes, seconds) = rest.split_at(2);
+            (degrees, Some(minutes), Some(seconds))
+        }
+        _ => return Err(Error::InvalidData("invalid ISO 6709 angle")),
+    };
+
+    let mut angle = parse_iso6709_number(degrees)?;
+    for (part, scale) in [(minutes, 60.0), (seconds, 3600.0)] {
+        if let Some(part) = part {
+            let value = parse_iso6709_number(part)?;
+            if value >= 60.0 {
+                return Err(Error::InvalidData(
+                    "ISO 6709 minutes or seconds out of range",
+                ));
+            }
+            angle += value / scale;
+        }
+    }
+    Ok(sign * angle)
 }
@@ -1,10 +1,14 @@
 use byteorder::{BigEndian, WriteBytesExt};
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 
+use crate::meta::MetaBox;
 use crate::mp4box::*;
-use crate::track::Mp4TrackWriter;
+use crate::retag::set_moov_metadata;
+use crate::track::{ITUNES_MEAN, ITUNSMPB, Mp4TrackWriter};
 use crate::*;
 
+const FASTSTART_BUFFER_SIZE: usize = 1024 * 1024;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Mp4Config {
     pub major_brand: FourCC,
@@ -20,6 +24,8 @@ pub struct Mp4Writer<W> {
     mdat_pos: u64,
     timescale: u32,
     duration: u64,
+    metadata: IlstBox,
+    quicktime_metadata: MetaBox,
 }
 
 impl<W> Mp4Writer<W> {
@@ -65,6 +71,7 @@ impl<W: Write + Seek> Mp4Writer<W> {
             major_brand: config.major_brand,
             minor_version: config.minor_version,
             compatible_brands: config.compatible_brands.clone(),
+            ..Default::default()
         };
         ftyp.write_box(&mut writer)?;
 
@@ -82,9 +89,23 @@ impl<W: Write + Seek> Mp4Writer<W> {
             mdat_pos,
             timescale,
             duration,
+            metadata: IlstBox::default(),
+            quicktime_metadata: MetaBox::new_mdta(),
         })
     }
 
+    /// The metadata written to `moov/udta/meta/ilst` at the end, see
+    /// [MetadataMut].
+    pub fn metadata_mut(&mut self) -> &mut IlstBox {
+        &mut self.metadata
+    }
+
+    /// The QuickTime `mdta` metadata written to `moov/meta` at the end, if
+    /// it has any values.
+    pub fn quicktime_metadata_mut(&mut self) -> &mut MetaBox {
+        &mut self.quicktime_metadata
+    }
+
     pub fn add_track(&mut self, config: &TrackConfig) -> Result<()> {
         let track_id = self.tracks.len() as u32 + 1;
         let track = Mp4TrackWriter::new(track_id, config)?;
@@ -92,6 +113,61 @@ impl<W: Write + Seek> Mp4Writer<W> {
         Ok(())
     }
 
+    /// Add a sample description to a track, such as a new resolution or
+    /// codec configuration. Samples written with the returned
+    /// `sample_description_index` use it.
+    pub fn add_sample_description(&mut self, track_id: u32, config: &TrackConfig) -> Result<u32> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        if let Some(track) = self.tracks.get_mut(track_id as usize - 1) {
+            track.add_sample_description(config)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// Replace the sample description of a track with one taken from another
+    /// file, keeping codec configuration that [TrackConfig] can't hold.
+    pub(crate) fn set_sample_entry(&mut self, track_id: u32, entry: SampleEntry) -> Result<()> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        if let Some(track) = self.tracks.get_mut(track_id as usize - 1) {
+            track.set_sample_entry(entry);
+            Ok(())
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// Set the edit list of a track, written out by `write_end`.
+    pub fn set_edit_list(&mut self, track_id: u32, edits: &[Edit]) -> Result<()> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        if let Some(track) = self.tracks.get_mut(track_id as usize - 1) {
+            track.set_edit_list(edits)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// Record the gapless playback parameters of a track, both as an
+    /// `iTunSMPB` tag and as an edit list that skips the delay and padding.
+    pub fn set_gapless_info(&mut self, track_id: u32, info: &GaplessInfo) -> Result<()> {
+        if track_id == 0 {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        let Some(track) = self.tracks.get_mut(track_id as usize - 1) else {
+            return Err(Error::TrakNotFound(track_id));
+        };
+        track.set_gapless_info(info)?;
+        self.metadata
+            .set_freeform_text(ITUNES_MEAN, ITUNSMPB, &info.to_itunsmpb());
+        Ok(())
+    }
+
     fn update_durations(&mut self, track_dur: u64) {
         if track_dur > self.duration {
             self.duration = track_dur;
@@ -117,7 +193,7 @@ impl<W: Write + Seek> Mp4Writer<W> {
     fn update_mdat_size(&mut self) -> Result<()> {
         let mdat_end = self.writer.stream_position()?;
         let mdat_size = mdat_end - self.mdat_pos;
-        if mdat_size > std::u32::MAX as u64 {
+        if mdat_size > u32::MAX as u64 {
             self.writer.seek(SeekFrom::Start(self.mdat_pos))?;
             self.writer.write_u32::<BigEndian>(1)?;
             self.writer.seek(SeekFrom::Start(self.mdat_pos + 8))?;
@@ -130,20 +206,103 @@ impl<W: Write + Seek> Mp4Writer<W> {
         Ok(())
     }
 
-    pub fn write_end(&mut self) -> Result<()> {
+    fn finish_moov(&mut self) -> Result<MoovBox> {
         let mut moov = MoovBox::default();
 
         for track in self.tracks.iter_mut() {
-            moov.traks.push(track.write_end(&mut self.writer)?);
+            moov.traks
+                .push(track.write_end(&mut self.writer, self.timescale)?);
         }
         self.update_mdat_size()?;
 
+        // Edit lists can change track durations, so take them from the traks.
+        self.duration = moov
+            .traks
+            .iter()
+            .map(|trak| trak.tkhd.duration)
+            .max()
+            .unwrap_or(0);
         moov.mvhd.timescale = self.timescale;
         moov.mvhd.duration = self.duration;
         if moov.mvhd.duration > (u32::MAX as u64) {
             moov.mvhd.version = 1
         }
+        set_moov_metadata(&mut moov, &self.metadata)?;
+        if !self.quicktime_metadata.mdta_entries().is_empty() {
+            moov.meta = Some(self.quicktime_metadata.clone());
+        }
+        Ok(moov)
+    }
+
+    pub fn write_end(&mut self) -> Result<()> {
+        let moov = self.finish_moov()?;
+        moov.write_box(&mut self.writer)?;
+        Ok(())
+    }
+}
+
+impl<W: Read + Write + Seek> Mp4Writer<W> {
+    /// Finish the file with `moov` placed ahead of `mdat` ("faststart"), so
+    /// it can be played progressively before it is fully downloaded.
+    ///
+    /// The sample data is moved forward in place by the size of `moov`, and
+    /// all chunk offsets are adjusted to match.
+    pub fn write_end_faststart(&mut self) -> Result<()> {
+        let mut moov = self.finish_moov()?;
+        let mdat_end = self.writer.stream_position()?;
+
+        // Shifting the chunk offsets can push some past 32 bits, and upgrading
+        // stco to co64 grows moov in turn, so repeat until the size settles.
+        let mut shift = moov.box_size();
+        loop {
+            for trak in moov.traks.iter_mut() {
+                let stbl = &mut trak.mdia.minf.stbl;
+                if let Some(stco) = &stbl.stco
+                    && stco
+                        .entries
+                        .iter()
+                        .any(|offset| *offset as u64 + shift > u32::MAX as u64)
+                {
+                    stbl.co64 = Some(Co64Box::from(stco));
+                    stbl.stco = None;
+                }
+            }
+            let moov_size = moov.box_size();
+            if moov_size == shift {
+                break;
+            }
+            shift = moov_size;
+        }
+
+        for trak in moov.traks.iter_mut() {
+            let stbl = &mut trak.mdia.minf.stbl;
+            if let Some(stco) = &mut stbl.stco {
+                for offset in stco.entries.iter_mut() {
+                    *offset += shift as u32;
+                }
+            }
+            if let Some(co64) = &mut stbl.co64 {
+                for offset in co64.entries.iter_mut() {
+                    *offset += shift;
+                }
+            }
+        }
+
+        // Move mdat back to front, as the source and destination overlap.
+        let mut buf = vec![0u8; FASTSTART_BUFFER_SIZE.min((mdat_end - self.mdat_pos) as usize)];
+        let mut pos = mdat_end;
+        while pos > self.mdat_pos {
+            let len = buf.len().min((pos - self.mdat_pos) as usize);
+            pos -= len as u64;
+            self.writer.seek(SeekFrom::Start(pos))?;
+            self.writer.read_exact(&mut buf[..len])?;
+            self.writer.seek(SeekFrom::Start(pos + shift))?;
+            self.writer.write_all(&buf[..len])?;
+        }
+
+        self.writer.seek(SeekFrom::Start(self.mdat_pos))?;
         moov.write_box(&mut self.writer)?;
+        self.writer.seek(SeekFrom::Start(mdat_end + shift))?;
         Ok(())
     }
 }
@@ -1,6 +1,6 @@
 use mp4::{
-    AudioObjectType, AvcProfile, ChannelConfig, MediaType, Metadata, Mp4Reader, SampleFreqIndex,
-    TrackType,
+    AudioObjectType, AvcProfile, ChannelConfig, MediaType, Metadata, MetadataMut, Mp4Box,
+    Mp4Reader, SampleFreqIndex, TrackType,
 };
 use std::fs::{self, File};
 use std::io::BufReader;
@@ -43,6 +43,8 @@ fn test_read_mp4() {
             duration: 512,
             rendering_offset: 0,
             is_sync: true,
+            flags: None,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 751]),
         }
     );
@@ -60,6 +62,8 @@ fn test_read_mp4() {
             duration: 1024,
             rendering_offset: 0,
             is_sync: true,
+            flags: None,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 179]),
         }
     );
@@ -72,6 +76,8 @@ fn test_read_mp4() {
             duration: 1024,
             rendering_offset: 0,
             is_sync: true,
+            flags: None,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 180]),
         }
     );
@@ -84,6 +90,8 @@ fn test_read_mp4() {
             duration: 896,
             rendering_offset: 0,
             is_sync: true,
+            flags: None,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 160]),
         }
     );
@@ -137,8 +145,7 @@ fn test_read_extended_audio_object_type() {
             .minf
             .stbl
             .stsd
-            .mp4a
-            .as_ref()
+            .mp4a()
             .unwrap()
             .esds
             .as_ref()
@@ -203,9 +210,2172 @@ fn test_read_fragments() {
             duration: 512,
             rendering_offset: 0,
             is_sync: true,
+            flags: Some(mp4::SampleFlags {
+                sample_depends_on: 2,
+                ..Default::default()
+            }),
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 751]),
         }
     );
     let eos = mp4_fragment.read_sample(1, 2);
     assert!(eos.is_err());
 }
+
+#[test]
+fn test_write_fragmented() {
+    let config = mp4::FragmentConfig {
+        compatible_brands: vec![str::parse("iso6").unwrap(), str::parse("cmfc").unwrap()],
+        ..fragment_config()
+    };
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &config).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+    writer.add_track(&aac_track_config()).unwrap();
+
+    let video = avc_samples();
+    let audio: Vec<mp4::Mp4Sample> = (0..40u64)
+        .map(|i| mp4::Mp4Sample {
+            start_time: i * 1024,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            flags: None,
+            sample_description_index: 1,
+            bytes: mp4::Bytes::from(vec![0x80 | i as u8; 20]),
+        })
+        .collect();
+    for (i, sample) in video.iter().enumerate() {
+        writer.write_sample(1, sample).unwrap();
+        writer.write_sample(2, &audio[i * 2]).unwrap();
+        writer.write_sample(2, &audio[i * 2 + 1]).unwrap();
+    }
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer();
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+
+    assert!(mp4.is_fragmented());
+    assert_eq!(mp4.moofs.len(), 4);
+    for (i, moof) in mp4.moofs.iter().enumerate() {
+        assert_eq!(moof.mfhd.sequence_number, i as u32 + 1);
+    }
+    assert_eq!(mp4.moov.mvhd.next_track_id, 3);
+    assert_eq!(mp4.sample_count(1).unwrap(), 20);
+    assert_eq!(mp4.sample_count(2).unwrap(), 40);
+
+    for (track_id, samples) in [(1, &video), (2, &audio)] {
+        for (i, want) in samples.iter().enumerate() {
+            let got = mp4.read_sample(track_id, i as u32 + 1).unwrap().unwrap();
+            assert_eq!(got.start_time, want.start_time);
+            assert_eq!(got.duration, want.duration);
+            assert_eq!(got.is_sync, want.is_sync);
+            assert_eq!(got.bytes, want.bytes);
+        }
+    }
+}
+
+#[test]
+fn test_write_faststart() {
+    let mut writer = progressive_writer(&[aac_track_config()]);
+    let samples = aac_samples(100, |i| 200 + i as usize);
+    for sample in samples.iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end_faststart().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+
+    // ftyp, then moov, then mdat.
+    let ftyp_size = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+    assert_eq!(&data[ftyp_size + 4..ftyp_size + 8], b"moov");
+    let moov_size = u32::from_be_bytes(data[ftyp_size..ftyp_size + 4].try_into().unwrap());
+    let mdat_pos = ftyp_size + moov_size as usize;
+    assert_eq!(&data[mdat_pos + 4..mdat_pos + 8], b"mdat");
+
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert_eq!(mp4.sample_count(1).unwrap(), 100);
+    for (i, want) in samples.iter().enumerate() {
+        let got = mp4.read_sample(1, i as u32 + 1).unwrap().unwrap();
+        assert_eq!(got.start_time, want.start_time);
+        assert_eq!(got.bytes, want.bytes);
+    }
+}
+
+#[test]
+fn test_stream_read() {
+    let data = fs::read("tests/samples/minimal.mp4").unwrap();
+    let mut mp4 = get_reader("tests/samples/minimal.mp4");
+    let mut stream = mp4::Mp4StreamReader::read_header(&data[..]).unwrap();
+    assert_eq!(stream.moov, mp4.moov);
+
+    let mut want = Vec::new();
+    for track_id in 1..=2 {
+        for sample_id in 1..=mp4.sample_count(track_id).unwrap() {
+            let offset = mp4.sample_offset(track_id, sample_id).unwrap();
+            let sample = mp4.read_sample(track_id, sample_id).unwrap().unwrap();
+            want.push((offset, track_id, sample));
+        }
+    }
+    want.sort_by_key(|(offset, _, _)| *offset);
+
+    for (_, want_track_id, want_sample) in want {
+        let (track_id, sample) = stream.next_sample().unwrap().unwrap();
+        assert_eq!(track_id, want_track_id);
+        assert_eq!(sample.start_time, want_sample.start_time);
+        assert_eq!(sample.bytes, want_sample.bytes);
+    }
+    assert!(stream.next_sample().unwrap().is_none());
+    assert_eq!(stream.position(), data.len() as u64);
+}
+
+#[test]
+fn test_stream_read_fragments() {
+    let mut data = fs::read("tests/samples/minimal_init.mp4").unwrap();
+    data.extend(fs::read("tests/samples/minimal_fragment.m4s").unwrap());
+
+    let mut stream = mp4::Mp4StreamReader::read_header(&data[..]).unwrap();
+    assert_eq!(stream.tracks().len(), 1);
+
+    let (track_id, sample) = stream.next_sample().unwrap().unwrap();
+    assert_eq!(track_id, 1);
+    assert_eq!(sample.start_time, 0);
+    assert_eq!(sample.duration, 512);
+    assert_eq!(sample.bytes.len(), 751);
+    assert!(stream.next_sample().unwrap().is_none());
+}
+
+#[test]
+fn test_stream_read_mdat_before_moov() {
+    let data = write_progressive(&aac_track_config(), &aac_samples(10, |i| 10 + i as usize));
+
+    let mut stream = mp4::Mp4StreamReader::read_header(&data[..]).unwrap();
+    for i in 0..10u64 {
+        let (track_id, sample) = stream.next_sample().unwrap().unwrap();
+        assert_eq!(track_id, 1);
+        assert_eq!(sample.start_time, i * 1024);
+        assert_eq!(sample.bytes, vec![i as u8; 10 + i as usize]);
+    }
+    assert!(stream.next_sample().unwrap().is_none());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_async_read_mp4() {
+    let data = fs::read("tests/samples/minimal.mp4").unwrap();
+    let size = data.len() as u64;
+    let mut mp4 = get_reader("tests/samples/minimal.mp4");
+    let mut async_mp4 = mp4::AsyncMp4Reader::read_header(std::io::Cursor::new(data), size)
+        .await
+        .unwrap();
+
+    assert_eq!(async_mp4.size(), mp4.size());
+    assert_eq!(async_mp4.moov, mp4.moov);
+    for track_id in 1..=2 {
+        let sample_count = async_mp4.sample_count(track_id).unwrap();
+        assert_eq!(sample_count, mp4.sample_count(track_id).unwrap());
+        for sample_id in 1..=sample_count {
+            let want = mp4.read_sample(track_id, sample_id).unwrap().unwrap();
+            let got = async_mp4
+                .read_sample(track_id, sample_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(got.start_time, want.start_time);
+            assert_eq!(got.bytes, want.bytes);
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_async_write_mp4() {
+    let data = std::io::Cursor::new(Vec::<u8>::new());
+    let mut writer = mp4::AsyncMp4Writer::write_start(data, &progressive_config())
+        .await
+        .unwrap();
+    writer.add_track(&aac_track_config()).unwrap();
+    for sample in aac_samples(10, |i| 10 + i as usize).iter() {
+        writer.write_sample(1, sample).await.unwrap();
+    }
+    writer.write_end().await.unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert_eq!(mp4.sample_count(1).unwrap(), 10);
+    for i in 0..10u64 {
+        let sample = mp4.read_sample(1, i as u32 + 1).unwrap().unwrap();
+        assert_eq!(sample.start_time, i * 1024);
+        assert_eq!(sample.bytes, vec![i as u8; 10 + i as usize]);
+    }
+}
+
+#[test]
+fn test_read_samples_interleaved() {
+    let mut mp4 = get_reader("tests/samples/minimal.mp4");
+    let timescales: Vec<(u32, u32)> = mp4
+        .tracks()
+        .values()
+        .map(|track| (track.track_id(), track.timescale()))
+        .collect();
+    let total: u32 = timescales
+        .iter()
+        .map(|(track_id, _)| mp4.sample_count(*track_id).unwrap())
+        .sum();
+
+    let mut last_time = 0f64;
+    let mut count = 0;
+    for result in mp4.samples(mp4::SampleOrder::DecodeTime).unwrap() {
+        let (track_id, sample) = result.unwrap();
+        let timescale = timescales.iter().find(|(id, _)| *id == track_id).unwrap().1;
+        let time = sample.start_time as f64 / timescale as f64;
+        assert!(time >= last_time);
+        last_time = time;
+        count += 1;
+    }
+    assert_eq!(count, total);
+
+    let mut order = Vec::new();
+    let mut next_ids = [0u32; 3];
+    for result in mp4.samples(mp4::SampleOrder::FileOffset).unwrap() {
+        let (track_id, _) = result.unwrap();
+        next_ids[track_id as usize] += 1;
+        order.push((track_id, next_ids[track_id as usize]));
+    }
+    let offsets: Vec<u64> = order
+        .iter()
+        .map(|(track_id, sample_id)| mp4.sample_offset(*track_id, *sample_id).unwrap())
+        .collect();
+    assert_eq!(offsets.len() as u32, total);
+    assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_write_metadata() {
+    let mut writer = progressive_writer(&[avc_track_config()]);
+    let samples = avc_samples();
+    for sample in samples.iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    let png = b"\x89PNG\r\n\x1a\nimage".to_vec();
+    let metadata = writer.metadata_mut();
+    metadata.set_title("Title");
+    metadata.set_artist("Artist");
+    metadata.set_year(2024);
+    metadata.set_poster(&png);
+    metadata.set_track_number(2, 9);
+    metadata.set_text(mp4::MetadataKey::Comment, "first take");
+    writer.write_end_faststart().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    let metadata = mp4.metadata();
+    assert_eq!(metadata.title(), Some("Title".into()));
+    assert_eq!(metadata.artist(), Some("Artist".into()));
+    assert_eq!(metadata.year(), Some(2024));
+    assert_eq!(metadata.poster(), Some(png.as_slice()));
+    assert_eq!(metadata.track_number(), Some((2, 9)));
+    assert_eq!(metadata.comment(), Some("first take".into()));
+
+    // moov comes first, so retagging moves the sample data.
+    let retagged = mp4::retag_mp4(
+        std::io::Cursor::new(data),
+        size,
+        std::io::Cursor::new(Vec::new()),
+        |ilst| {
+            ilst.set_title("A much longer title than before");
+            ilst.set_album("Album");
+            ilst.remove_item(&mp4::MetadataKey::Poster);
+        },
+    )
+    .unwrap()
+    .into_inner();
+    let size = retagged.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(retagged.clone()), size).unwrap();
+    for (i, want) in samples.iter().enumerate() {
+        let got = mp4.read_sample(1, i as u32 + 1).unwrap().unwrap();
+        assert_eq!(got.bytes, want.bytes);
+    }
+    let metadata = mp4.metadata();
+    assert_eq!(
+        metadata.title(),
+        Some("A much longer title than before".into())
+    );
+    assert_eq!(metadata.album(), Some("Album".into()));
+    assert_eq!(metadata.artist(), Some("Artist".into()));
+    assert_eq!(metadata.poster(), None);
+
+    // Removing every tag drops udta altogether.
+    let cleared = mp4::retag_mp4(
+        std::io::Cursor::new(retagged),
+        size,
+        std::io::Cursor::new(Vec::new()),
+        |ilst| ilst.items.clear(),
+    )
+    .unwrap()
+    .into_inner();
+    let size = cleared.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(cleared), size).unwrap();
+    assert!(mp4.moov.udta.is_none());
+    assert_eq!(mp4.metadata().title(), None);
+}
+
+#[test]
+fn test_write_common_metadata() {
+    use mp4::MetadataKey;
+
+    let mut writer = progressive_writer(&[aac_track_config()]);
+    for sample in aac_samples(10, |_| 10).iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    let metadata = writer.metadata_mut();
+    metadata.set_artist("Artist");
+    metadata.set_album_artist("Album Artist");
+    metadata.set_album("Album");
+    metadata.set_track_number(3, 12);
+    metadata.set_disc_number(1, 2);
+    metadata.set_text(MetadataKey::Composer, "Composer");
+    metadata.set_text(MetadataKey::Comment, "Comment");
+    metadata.set_text(MetadataKey::Encoder, "Encoder");
+    metadata.set_text(MetadataKey::TvShow, "Show");
+    metadata.set_text(MetadataKey::Lyrics, "La la la");
+    metadata.set_text(MetadataKey::SortTitle, "Title, The");
+    metadata.set_text(MetadataKey::SortArtist, "Artist, The");
+    metadata.set_text(MetadataKey::SortAlbum, "Album, The");
+    metadata.set_text(MetadataKey::SortAlbumArtist, "Album Artist, The");
+    // gnre holds the ID3v1 genre plus one, so 18 is Rock.
+    metadata.set_item(MetadataKey::GenreId, mp4::DataType::Binary, vec![0, 18]);
+    metadata.set_item(MetadataKey::Bpm, mp4::DataType::TempoCpil, vec![0, 120]);
+    metadata.set_item(MetadataKey::Compilation, mp4::DataType::TempoCpil, vec![1]);
+    metadata.set_item(MetadataKey::Rating, mp4::DataType::TempoCpil, vec![2]);
+    metadata.set_item(MetadataKey::MediaKind, mp4::DataType::TempoCpil, vec![10]);
+    metadata.set_item(
+        MetadataKey::TvEpisode,
+        mp4::DataType::TempoCpil,
+        vec![0, 0, 0, 7],
+    );
+    metadata.set_item(
+        MetadataKey::TvSeason,
+        mp4::DataType::TempoCpil,
+        vec![0, 0, 0, 2],
+    );
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    let metadata = mp4.metadata();
+    assert_eq!(metadata.artist(), Some("Artist".into()));
+    assert_eq!(metadata.album_artist(), Some("Album Artist".into()));
+    assert_eq!(metadata.album(), Some("Album".into()));
+    assert_eq!(metadata.genre(), Some("Rock".into()));
+    assert_eq!(metadata.track_number(), Some((3, 12)));
+    assert_eq!(metadata.disc_number(), Some((1, 2)));
+    assert_eq!(metadata.composer(), Some("Composer".into()));
+    assert_eq!(metadata.comment(), Some("Comment".into()));
+    assert_eq!(metadata.encoder(), Some("Encoder".into()));
+    assert_eq!(metadata.bpm(), Some(120));
+    assert_eq!(metadata.compilation(), Some(true));
+    assert_eq!(metadata.rating(), Some(2));
+    assert_eq!(metadata.media_kind(), Some(10));
+    assert_eq!(metadata.tv_show(), Some("Show".into()));
+    assert_eq!(metadata.tv_episode(), Some(7));
+    assert_eq!(metadata.tv_season(), Some(2));
+    assert_eq!(metadata.lyrics(), Some("La la la".into()));
+    assert_eq!(metadata.sort_title(), Some("Title, The".into()));
+    assert_eq!(metadata.sort_artist(), Some("Artist, The".into()));
+    assert_eq!(metadata.sort_album(), Some("Album, The".into()));
+    assert_eq!(
+        metadata.sort_album_artist(),
+        Some("Album Artist, The".into())
+    );
+
+    // A text genre replaces the numeric one.
+    let retagged = mp4::retag_mp4(
+        std::io::Cursor::new(data),
+        size,
+        std::io::Cursor::new(Vec::new()),
+        |ilst| ilst.set_genre("Shoegaze"),
+    )
+    .unwrap()
+    .into_inner();
+    let size = retagged.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(retagged), size).unwrap();
+    assert_eq!(mp4.metadata().genre(), Some("Shoegaze".into()));
+    assert_eq!(mp4.metadata().bpm(), Some(120));
+}
+
+#[test]
+fn test_retag_keeps_unknown_boxes() {
+    let mut writer = progressive_writer(&[avc_track_config()]);
+    for sample in avc_samples().iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.metadata_mut().set_title("Title");
+    // moov goes last, so growing it moves no sample data.
+    writer.write_end().unwrap();
+    let mut data = writer.into_writer().into_inner();
+
+    // Splice vendor boxes onto the end of moov, trak and udta, growing every
+    // box that contains them. Going from the back of the file keeps the
+    // offsets still to come valid. udta ends where moov does, so the moov box
+    // goes in first and the udta one lands in front of it.
+    let size = data.len() as u64;
+    let boxes = mp4::read_box_tree(&mut std::io::Cursor::new(&data), size).unwrap();
+    let node = |path| mp4::find_box(&boxes, path).unwrap();
+    let (moov, trak, udta) = (node("moov"), node("moov/trak"), node("moov/udta"));
+    let mut inserts = vec![
+        (moov.end(), vec![moov.offset], &b"uuid"[..], vec![3; 24]),
+        (
+            trak.end(),
+            vec![trak.offset, moov.offset],
+            b"tapt",
+            vec![2; 20],
+        ),
+        (
+            udta.end(),
+            vec![udta.offset, moov.offset],
+            b"\xa9xyz",
+            vec![1; 10],
+        ),
+    ];
+    inserts.sort_by_key(|insert| std::cmp::Reverse(insert.0));
+    for (at, parents, box_type, payload) in inserts {
+        let mut unknown = (8 + payload.len() as u32).to_be_bytes().to_vec();
+        unknown.extend_from_slice(box_type);
+        unknown.extend_from_slice(&payload);
+        for parent in parents {
+            let parent = parent as usize;
+            let parent_size = u32::from_be_bytes(data[parent..parent + 4].try_into().unwrap());
+            data[parent..parent + 4]
+                .copy_from_slice(&(parent_size + unknown.len() as u32).to_be_bytes());
+        }
+        data.splice(at as usize..at as usize, unknown);
+    }
+
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    assert_eq!(mp4.moov.unknown_boxes.len(), 1);
+    assert_eq!(mp4.moov.traks[0].unknown_boxes.len(), 1);
+    assert_eq!(mp4.moov.udta.as_ref().unwrap().unknown_boxes.len(), 1);
+
+    let retagged = mp4::retag_mp4(
+        std::io::Cursor::new(data.clone()),
+        size,
+        std::io::Cursor::new(Vec::new()),
+        |_| {},
+    )
+    .unwrap()
+    .into_inner();
+    assert_eq!(retagged, data);
+}
+
+#[test]
+fn test_retag_largesize_moov() {
+    use mp4::WriteBox;
+
+    let mut writer = progressive_writer(&[avc_track_config()]);
+    let samples = avc_samples();
+    for sample in samples.iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.metadata_mut().set_title("Title");
+    writer.write_end_faststart().unwrap();
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+
+    // Give moov a 64-bit size, which moves the sample data 8 bytes on.
+    let mut moov = mp4.moov.clone();
+    for offset in moov.traks[0]
+        .mdia
+        .minf
+        .stbl
+        .stco
+        .as_mut()
+        .unwrap()
+        .entries
+        .iter_mut()
+    {
+        *offset += 8;
+    }
+    let mut moov_data = Vec::new();
+    moov.write_box(&mut moov_data).unwrap();
+    let moov_offset = mp4.ftyp.box_size() as usize;
+    let mut largesize = data[..moov_offset].to_vec();
+    largesize.extend_from_slice(&1u32.to_be_bytes());
+    largesize.extend_from_slice(b"moov");
+    largesize.extend_from_slice(&(moov_data.len() as u64 + 8).to_be_bytes());
+    largesize.extend_from_slice(&moov_data[8..]);
+    largesize.extend_from_slice(&data[moov_offset + mp4.moov.box_size() as usize..]);
+
+    let retag = |data: Vec<u8>| {
+        let size = data.len() as u64;
+        mp4::retag_mp4(
+            std::io::Cursor::new(data),
+            size,
+            std::io::Cursor::new(Vec::new()),
+            |ilst| ilst.set_title("A much longer title than before"),
+        )
+        .unwrap()
+        .into_inner()
+    };
+    // Both come out with the same 32-bit moov.
+    let retagged = retag(largesize);
+    assert_eq!(retagged, retag(data));
+    let size = retagged.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(retagged), size).unwrap();
+    for (i, want) in samples.iter().enumerate() {
+        let got = mp4.read_sample(1, i as u32 + 1).unwrap().unwrap();
+        assert_eq!(got.bytes, want.bytes);
+    }
+    assert_eq!(
+        mp4.metadata().title(),
+        Some("A much longer title than before".into())
+    );
+}
+
+#[test]
+fn test_gapless_info() {
+    let mut writer = progressive_writer(&[aac_track_config()]);
+    for sample in aac_samples(100, |_| 10).iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    let info = mp4::GaplessInfo {
+        encoder_delay: 2112,
+        padding: 288,
+        sample_count: 100 * 1024 - 2112 - 288,
+    };
+    writer.set_gapless_info(1, &info).unwrap();
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    assert_eq!(mp4.gapless_info(1).unwrap(), Some(info));
+    assert_eq!(
+        mp4.metadata().freeform("com.apple.iTunes", "iTunSMPB"),
+        Some(mp4::DataValue::Text(info.to_itunsmpb().into()))
+    );
+
+    // Without the tag, the edit list gives the same. The movie timescale
+    // rounds the edit duration, so allow for that in the padding.
+    let untagged = mp4::retag_mp4(
+        std::io::Cursor::new(data),
+        size,
+        std::io::Cursor::new(Vec::new()),
+        |ilst| {
+            ilst.remove_freeform("com.apple.iTunes", "iTunSMPB");
+        },
+    )
+    .unwrap()
+    .into_inner();
+    let size = untagged.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(untagged), size).unwrap();
+    let from_elst = mp4.gapless_info(1).unwrap().unwrap();
+    assert_eq!(from_elst.encoder_delay, info.encoder_delay);
+    assert!(from_elst.sample_count.abs_diff(info.sample_count) < 48);
+    assert_eq!(
+        from_elst.encoder_delay as u64 + from_elst.sample_count + from_elst.padding as u64,
+        100 * 1024
+    );
+
+    assert_eq!(
+        mp4::GaplessInfo::from_itunsmpb(&info.to_itunsmpb()),
+        Some(info)
+    );
+}
+
+#[test]
+fn test_gapless_info_timescale_differs_from_sample_rate() {
+    // Like HE-AAC: a 24 kHz core timescale and 48 kHz decoded audio, so each
+    // 1024 tick frame decodes to 2048 samples.
+    let mut writer = progressive_writer(&[mp4::TrackConfig {
+        timescale: 24000,
+        ..aac_track_config()
+    }]);
+    for sample in aac_samples(100, |_| 10).iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    let info = mp4::GaplessInfo {
+        encoder_delay: 4224,
+        padding: 32,
+        sample_count: 100 * 2048 - 4224 - 32,
+    };
+    writer.set_gapless_info(1, &info).unwrap();
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    let elst = mp4.tracks()[&1]
+        .trak
+        .edts
+        .as_ref()
+        .unwrap()
+        .elst
+        .as_ref()
+        .unwrap();
+    assert_eq!(elst.entries[0].media_time, 2112);
+    // In the 1000 movie timescale.
+    assert_eq!(
+        elst.entries[0].segment_duration,
+        (100 * 2048 - 4224 - 32) / 48
+    );
+
+    let untagged = mp4::retag_mp4(
+        std::io::Cursor::new(data),
+        size,
+        std::io::Cursor::new(Vec::new()),
+        |ilst| {
+            ilst.remove_freeform("com.apple.iTunes", "iTunSMPB");
+        },
+    )
+    .unwrap()
+    .into_inner();
+    let size = untagged.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(untagged), size).unwrap();
+    assert_eq!(mp4.gapless_info(1).unwrap(), Some(info));
+}
+
+#[test]
+fn test_quicktime_metadata() {
+    let mut writer = progressive_writer(&[avc_track_config()]);
+    for sample in avc_samples().iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    let location = mp4::Location {
+        latitude: 37.3349,
+        longitude: -122.009,
+        altitude: Some(12.345),
+    };
+    let metadata = writer.quicktime_metadata_mut();
+    metadata
+        .set_mdta_value(
+            "com.apple.quicktime.model",
+            mp4::DataType::Text,
+            b"iPhone".to_vec(),
+        )
+        .unwrap();
+    metadata
+        .set_mdta_value(
+            mp4::QUICKTIME_LOCATION,
+            mp4::DataType::Text,
+            location.to_iso6709().into_bytes(),
+        )
+        .unwrap();
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert_eq!(
+        mp4.quicktime_value("com.apple.quicktime.model"),
+        Some(mp4::DataValue::Text("iPhone".into()))
+    );
+    assert_eq!(mp4.quicktime_value("com.apple.quicktime.make"), None);
+    assert_eq!(mp4.location(), Some(location));
+    assert_eq!(mp4.metadata().title(), None);
+}
+
+#[test]
+fn test_write_freeform_and_mdta_metadata() {
+    use mp4::{DataType, DataValue};
+
+    let items = [
+        (
+            "Text",
+            DataType::Text,
+            b"text".to_vec(),
+            DataValue::Text("text".into()),
+        ),
+        (
+            "Utf16",
+            DataType::Utf16,
+            vec![0, b'h', 0, b'i'],
+            DataValue::Text("hi".into()),
+        ),
+        (
+            "Int16",
+            DataType::Int16,
+            vec![0xff, 0xfe],
+            DataValue::Integer(-2),
+        ),
+        (
+            "Uint32",
+            DataType::Uint32,
+            vec![0, 1, 0, 0],
+            DataValue::UnsignedInteger(65536),
+        ),
+        (
+            "Float32",
+            DataType::Float32,
+            1.5f32.to_be_bytes().to_vec(),
+            DataValue::Float(1.5),
+        ),
+        (
+            "Float64",
+            DataType::Float64,
+            (-0.25f64).to_be_bytes().to_vec(),
+            DataValue::Float(-0.25),
+        ),
+        (
+            "Png",
+            DataType::Png,
+            b"\x89PNG".to_vec(),
+            DataValue::Binary(b"\x89PNG"),
+        ),
+        (
+            "Other",
+            DataType::Other(99),
+            vec![1, 2],
+            DataValue::Binary(&[1, 2]),
+        ),
+    ];
+
+    let mut writer = progressive_writer(&[aac_track_config()]);
+    for sample in aac_samples(10, |_| 10).iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    for (name, data_type, data, _) in items.iter() {
+        writer
+            .metadata_mut()
+            .set_freeform("org.example", name, data_type.clone(), data.clone());
+        writer
+            .quicktime_metadata_mut()
+            .set_mdta_value(
+                &format!("org.example.{name}"),
+                data_type.clone(),
+                data.clone(),
+            )
+            .unwrap();
+    }
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    for (name, _, _, value) in items.iter() {
+        assert_eq!(
+            mp4.metadata().freeform("org.example", name).as_ref(),
+            Some(value),
+            "{name}"
+        );
+        assert_eq!(
+            mp4.quicktime_value(&format!("org.example.{name}")).as_ref(),
+            Some(value),
+            "{name}"
+        );
+    }
+    assert_eq!(mp4.metadata().freeform("org.example", "Missing"), None);
+    assert_eq!(mp4.metadata().freeform("com.apple.iTunes", "Text"), None);
+}
+
+#[test]
+fn test_iso6709_location() {
+    let location = mp4::Location::from_iso6709("+48.8577+002.2950/").unwrap();
+    assert_eq!(location.latitude, 48.8577);
+    assert_eq!(location.longitude, 2.295);
+    assert_eq!(location.altitude, None);
+    assert_eq!(location.to_iso6709(), "+48.8577+002.2950/");
+
+    // Degrees and minutes, then degrees, minutes and seconds.
+    let location = mp4::Location::from_iso6709("+4851.462-07400.5+10CRSWGS_84/").unwrap();
+    assert!((location.latitude - (48.0 + 51.462 / 60.0)).abs() < 1e-9);
+    assert!((location.longitude + (74.0 + 0.5 / 60.0)).abs() < 1e-9);
+    assert_eq!(location.altitude, Some(10.0));
+    let location = mp4::Location::from_iso6709("-334512.5+1510930/").unwrap();
+    assert!((location.latitude + (33.0 + 45.0 / 60.0 + 12.5 / 3600.0)).abs() < 1e-9);
+    assert!((location.longitude - (151.0 + 9.0 / 60.0 + 30.0 / 3600.0)).abs() < 1e-9);
+
+    for invalid in [
+        "",
+        "+48.85",
+        "48.85+002.29/",
+        "+95.0+002.29/",
+        "+4875.0+002.29/",
+    ] {
+        assert!(mp4::Location::from_iso6709(invalid).is_err(), "{invalid}");
+    }
+}
+
+fn avc_track_config() -> mp4::TrackConfig {
+    mp4::TrackConfig {
+        track_type: TrackType::Video,
+        timescale: 1000,
+        language: String::from("und"),
+        media_conf: mp4::MediaConfig::AvcConfig(mp4::AvcConfig {
+            width: 320,
+            height: 240,
+            seq_param_set: vec![0x67, 0x64, 0x00, 0x1f, 0xac],
+            pic_param_set: vec![0x68, 0xeb, 0xe3, 0xcb],
+        }),
+    }
+}
+
+/// 20 samples of 40ms, with a sync sample every 5.
+fn avc_samples() -> Vec<mp4::Mp4Sample> {
+    (0..20u64)
+        .map(|i| mp4::Mp4Sample {
+            start_time: i * 40,
+            duration: 40,
+            rendering_offset: 0,
+            is_sync: i % 5 == 0,
+            flags: None,
+            sample_description_index: 1,
+            bytes: mp4::Bytes::from(vec![i as u8; 100 + i as usize]),
+        })
+        .collect()
+}
+
+fn aac_track_config() -> mp4::TrackConfig {
+    mp4::TrackConfig {
+        track_type: TrackType::Audio,
+        timescale: 48000,
+        language: String::from("und"),
+        media_conf: mp4::MediaConfig::AacConfig(mp4::AacConfig::default()),
+    }
+}
+
+/// `count` frames of 1024 samples, frame `i` filled with `i` and `size(i)`
+/// bytes long.
+fn aac_samples(count: u64, size: impl Fn(u64) -> usize) -> Vec<mp4::Mp4Sample> {
+    (0..count)
+        .map(|i| mp4::Mp4Sample {
+            start_time: i * 1024,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            flags: None,
+            sample_description_index: 1,
+            bytes: mp4::Bytes::from(vec![i as u8; size(i)]),
+        })
+        .collect()
+}
+
+fn progressive_config() -> mp4::Mp4Config {
+    mp4::Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    }
+}
+
+fn fragment_config() -> mp4::FragmentConfig {
+    mp4::FragmentConfig {
+        major_brand: str::parse("iso6").unwrap(),
+        minor_version: 0,
+        compatible_brands: vec![str::parse("iso6").unwrap()],
+        timescale: 1000,
+        fragment_duration: Duration::from_millis(200),
+    }
+}
+
+/// A progressive writer with `tracks` added, numbered from 1.
+fn progressive_writer(tracks: &[mp4::TrackConfig]) -> mp4::Mp4Writer<std::io::Cursor<Vec<u8>>> {
+    let data = std::io::Cursor::new(Vec::<u8>::new());
+    let mut writer = mp4::Mp4Writer::write_start(data, &progressive_config()).unwrap();
+    for track in tracks {
+        writer.add_track(track).unwrap();
+    }
+    writer
+}
+
+/// A progressive file with a single track of `samples`.
+fn write_progressive(track: &mp4::TrackConfig, samples: &[mp4::Mp4Sample]) -> Vec<u8> {
+    let mut writer = progressive_writer(std::slice::from_ref(track));
+    for sample in samples.iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    writer.into_writer().into_inner()
+}
+
+#[test]
+fn test_seek() {
+    let data = write_progressive(&avc_track_config(), &avc_samples());
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+
+    let seek = |ms| mp4.seek(1, Duration::from_millis(ms)).unwrap();
+    let position = |sample_id, sync_sample_id| {
+        Some(mp4::SeekPosition {
+            sample_id,
+            sync_sample_id,
+        })
+    };
+    assert_eq!(seek(0), position(1, 1));
+    assert_eq!(seek(39), position(1, 1));
+    assert_eq!(seek(40), position(2, 1));
+    assert_eq!(seek(290), position(8, 6));
+    assert_eq!(seek(400), position(11, 11));
+    assert_eq!(seek(799), position(20, 16));
+    assert_eq!(seek(800), None);
+    assert_eq!(mp4.seek_to_timestamp(1, 280).unwrap(), position(8, 6));
+    assert!(mp4.seek(2, Duration::ZERO).is_err());
+}
+
+#[test]
+fn test_seek_fragmented() {
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+    for sample in avc_samples().iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer();
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+
+    for indexed in [false, true] {
+        if indexed {
+            mp4.build_sample_index().unwrap();
+        }
+        let seek = |ms| {
+            mp4.seek(1, Duration::from_millis(ms))
+                .unwrap()
+                .map(|position| (position.sample_id, position.sync_sample_id))
+        };
+        assert_eq!(seek(0), Some((1, 1)));
+        assert_eq!(seek(199), Some((5, 1)));
+        assert_eq!(seek(200), Some((6, 6)));
+        assert_eq!(seek(290), Some((8, 6)));
+        assert_eq!(seek(799), Some((20, 16)));
+        assert_eq!(seek(800), None);
+    }
+}
+
+#[test]
+fn test_fragment_random_access() {
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+    // Start on non-sync samples, so the first fragment doesn't start with its
+    // random access point.
+    for sample in avc_samples()[2..].iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer();
+    let size = data.len() as u64;
+    let mut cursor = std::io::Cursor::new(data);
+    let mfra = mp4::MfraBox::read_from_end(&mut cursor, size)
+        .unwrap()
+        .unwrap();
+    cursor.set_position(0);
+    let mut mp4 = Mp4Reader::read_header(cursor, size).unwrap();
+
+    assert_eq!(mp4.mfra.as_ref(), Some(&mfra));
+    assert_eq!(mfra.mfro.mfra_size as u64, mfra.box_size());
+    assert_eq!(mfra.byte_offset(), Some(size - mfra.box_size()));
+
+    let points: Vec<(u64, u64, u32)> = mp4
+        .random_access_points(1)
+        .iter()
+        .map(|entry| (entry.time, entry.moof_offset, entry.sample_number))
+        .collect();
+    let moof_offsets: Vec<u64> = mp4
+        .moofs
+        .iter()
+        .map(|moof| moof.byte_offset().unwrap())
+        .collect();
+    assert_eq!(
+        points,
+        vec![
+            (120, moof_offsets[0], 4),
+            (320, moof_offsets[1], 1),
+            (520, moof_offsets[2], 1),
+        ]
+    );
+    assert!(mp4.random_access_points(2).is_empty());
+
+    // Every entry points at a sync sample starting at its time.
+    for (i, entry) in mp4.random_access_points(1).to_vec().iter().enumerate() {
+        let earlier: u32 = mp4.moofs[..i]
+            .iter()
+            .map(|moof| moof.trafs[0].truns[0].sample_count)
+            .sum();
+        let sample_id = earlier + entry.sample_number;
+        let sample = mp4.read_sample(1, sample_id).unwrap().unwrap();
+        assert!(sample.is_sync);
+        assert_eq!(sample.start_time, entry.time);
+    }
+}
+
+#[test]
+fn test_fragment_write_end_twice() {
+    let write = |end_calls| {
+        let mut writer =
+            mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+        writer.add_track(&avc_track_config()).unwrap();
+        for sample in avc_samples().iter() {
+            writer.write_sample(1, sample).unwrap();
+        }
+        for _ in 0..end_calls {
+            writer.write_end().unwrap();
+        }
+        assert!(matches!(
+            writer.write_sample(1, &avc_samples()[0]),
+            Err(mp4::Error::InvalidData(_))
+        ));
+        writer.into_writer()
+    };
+    let data = write(2);
+    assert_eq!(data, write(1));
+
+    let size = data.len() as u64;
+    let mut cursor = std::io::Cursor::new(data);
+    let mfra = mp4::MfraBox::read_from_end(&mut cursor, size)
+        .unwrap()
+        .unwrap();
+    assert_eq!(mfra.byte_offset(), Some(size - mfra.box_size()));
+}
+
+#[test]
+fn test_lazy_read_fragments() {
+    use mp4::WriteBox;
+
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+    for sample in avc_samples().iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer();
+
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    let want: Vec<mp4::Mp4Sample> = (1..=20)
+        .map(|sample_id| mp4.read_sample(1, sample_id).unwrap().unwrap())
+        .collect();
+    let moof_offsets: Vec<u64> = mp4
+        .moofs
+        .iter()
+        .map(|moof| moof.byte_offset().unwrap())
+        .collect();
+
+    let check = |mut lazy: mp4::Mp4LazyReader<std::io::Cursor<Vec<u8>>>| {
+        assert_eq!(lazy.fragments().len(), 4);
+        assert_eq!(lazy.cached_fragments(), 0);
+
+        let samples = lazy.read_samples(1, 200, 440).unwrap();
+        assert_eq!(samples, want[5..11]);
+        for (got, want) in samples.iter().zip(want[5..11].iter()) {
+            assert_eq!(got.bytes, want.bytes);
+        }
+        assert!(lazy.cached_fragments() <= 2);
+
+        assert_eq!(lazy.read_samples(1, 0, 800).unwrap(), want);
+        assert_eq!(lazy.cached_fragments(), 2);
+        assert_eq!(lazy.read_samples(1, 800, 1000).unwrap(), []);
+        assert!(matches!(
+            lazy.read_samples(2, 0, 800),
+            Err(mp4::Error::TrakNotFound(2))
+        ));
+
+        assert_eq!(lazy.find_fragment(1, 0).unwrap(), Some(0));
+        assert_eq!(lazy.find_fragment(1, 199).unwrap(), Some(0));
+        assert_eq!(lazy.find_fragment(1, 205).unwrap(), Some(1));
+        assert_eq!(lazy.find_fragment(1, 799).unwrap(), Some(3));
+        assert_eq!(lazy.find_fragment(1, 800).unwrap(), None);
+    };
+
+    let lazy =
+        mp4::Mp4LazyReader::read_header(std::io::Cursor::new(data.clone()), size, 2).unwrap();
+    let offsets: Vec<u64> = lazy.fragments().iter().map(|f| f.moof_offset).collect();
+    assert_eq!(offsets, moof_offsets);
+    assert_eq!(lazy.mfra, mp4.mfra);
+    check(lazy);
+
+    // With a sidx whose times are a little later than the decode times, as
+    // presentation times can be.
+    let mut ends = moof_offsets[1..].to_vec();
+    ends.push(mp4.mfra.as_ref().unwrap().byte_offset().unwrap());
+    let sidx = mp4::SidxBox {
+        reference_id: 1,
+        timescale: 2000,
+        earliest_presentation_time: 40,
+        references: moof_offsets
+            .iter()
+            .zip(ends.iter())
+            .map(|(start, end)| mp4::sidx::SidxReference {
+                referenced_size: (end - start) as u32,
+                subsegment_duration: 400,
+                starts_with_sap: true,
+                sap_type: 1,
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let mut with_sidx = data[..moof_offsets[0] as usize].to_vec();
+    sidx.write_box(&mut with_sidx).unwrap();
+    with_sidx.extend_from_slice(&data[moof_offsets[0] as usize..]);
+    let size = with_sidx.len() as u64;
+    // The sidx moves the fragments away from where the mfra says they are,
+    // so only its own times are used.
+    let lazy = mp4::Mp4LazyReader::read_header(std::io::Cursor::new(with_sidx), size, 2).unwrap();
+    assert_eq!(lazy.sidx.len(), 1);
+    assert!(lazy.mfra.is_some());
+    check(lazy);
+}
+
+#[test]
+fn test_lazy_read_fragments_without_random_access_point() {
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+    writer
+        .add_sample_description(1, &avc_track_config())
+        .unwrap();
+
+    // The description switch at sample 3 cuts a fragment without a sync
+    // sample, which the mfra doesn't list.
+    let mut samples = avc_samples();
+    for (i, sample) in samples.iter_mut().enumerate() {
+        sample.is_sync = i % 10 == 0;
+        if i >= 2 {
+            sample.sample_description_index = 2;
+        }
+    }
+    for sample in samples.iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer();
+
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    assert_eq!(mp4.moofs.len(), 3);
+    assert_eq!(mp4.mfra.as_ref().unwrap().tfras[0].entries.len(), 2);
+    let want: Vec<mp4::Mp4Sample> = (1..=20)
+        .map(|sample_id| mp4.read_sample(1, sample_id).unwrap().unwrap())
+        .collect();
+
+    let mut lazy = mp4::Mp4LazyReader::read_header(std::io::Cursor::new(data), size, 2).unwrap();
+    assert_eq!(lazy.fragments().len(), 3);
+    assert_eq!(lazy.read_samples(1, 120, 360).unwrap(), want[3..9]);
+    assert_eq!(lazy.read_samples(1, 0, 800).unwrap(), want);
+}
+
+#[test]
+fn test_segment_index() {
+    use mp4::WriteBox;
+
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+    for sample in avc_samples().iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    assert!(mp4.segment_index().is_empty());
+
+    // Split the fragments in two halves, each with its own sidx, and point a
+    // top-level sidx at both.
+    let mut bounds: Vec<usize> = mp4
+        .moofs
+        .iter()
+        .map(|moof| moof.byte_offset().unwrap() as usize)
+        .collect();
+    bounds.push(mp4.mfra.as_ref().unwrap().byte_offset().unwrap() as usize);
+    let reference = |reference_type, size: usize, duration| mp4::sidx::SidxReference {
+        reference_type,
+        referenced_size: size as u32,
+        subsegment_duration: duration,
+        starts_with_sap: true,
+        sap_type: 1,
+        ..Default::default()
+    };
+    let halves: Vec<Vec<u8>> = [0, 2]
+        .iter()
+        .map(|first| {
+            let sidx = mp4::SidxBox {
+                reference_id: 1,
+                timescale: 1000,
+                earliest_presentation_time: *first as u64 * 200,
+                references: (*first..first + 2)
+                    .map(|i| reference(0, bounds[i + 1] - bounds[i], 200))
+                    .collect(),
+                ..Default::default()
+            };
+            let mut half = Vec::new();
+            sidx.write_box(&mut half).unwrap();
+            half.extend_from_slice(&data[bounds[*first]..bounds[first + 2]]);
+            half
+        })
+        .collect();
+    let root = mp4::SidxBox {
+        reference_id: 1,
+        timescale: 1000,
+        references: halves
+            .iter()
+            .map(|half| reference(1, half.len(), 400))
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut data = data[..bounds[0]].to_vec();
+    root.write_box(&mut data).unwrap();
+    for half in halves.iter() {
+        data.extend_from_slice(half);
+    }
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert_eq!(mp4.sidx.len(), 3);
+
+    let segments = mp4.segment_index().segments();
+    let moofs: Vec<std::ops::Range<usize>> = segments.iter().map(|s| s.moofs.clone()).collect();
+    assert_eq!(moofs, vec![0..1, 1..2, 2..3, 3..4]);
+    for (i, segment) in segments.iter().enumerate() {
+        assert_eq!(segment.track_id, 1);
+        assert_eq!(segment.start(), Duration::from_millis(i as u64 * 200));
+        assert_eq!(
+            segment.offset,
+            mp4.moofs[i].byte_offset().unwrap(),
+            "segment {i}"
+        );
+    }
+
+    let segment_at = |ms| {
+        mp4.segment_at(1, Duration::from_millis(ms))
+            .map(|segment| segment.moofs.start)
+    };
+    assert_eq!(segment_at(0), Some(0));
+    assert_eq!(segment_at(399), Some(1));
+    assert_eq!(segment_at(400), Some(2));
+    assert_eq!(segment_at(799), Some(3));
+    assert_eq!(segment_at(800), None);
+    assert!(mp4.segment_at(2, Duration::ZERO).is_none());
+}
+
+fn assert_same_samples_with_index(mut mp4: Mp4Reader<std::io::Cursor<Vec<u8>>>) {
+    let mut want = Vec::new();
+    for track_id in 1..=mp4.tracks().len() as u32 {
+        for sample_id in 1..=mp4.sample_count(track_id).unwrap() {
+            let offset = mp4.sample_offset(track_id, sample_id).unwrap();
+            let sample = mp4.read_sample(track_id, sample_id).unwrap().unwrap();
+            want.push((track_id, sample_id, offset, sample));
+        }
+    }
+
+    mp4.build_sample_index().unwrap();
+    assert!(mp4.tracks().values().all(|track| track.has_index()));
+    for (track_id, sample_id, offset, sample) in want {
+        assert_eq!(mp4.sample_offset(track_id, sample_id).unwrap(), offset);
+        let got = mp4.read_sample(track_id, sample_id).unwrap().unwrap();
+        assert_eq!(got.start_time, sample.start_time);
+        assert_eq!(got.duration, sample.duration);
+        assert_eq!(got.rendering_offset, sample.rendering_offset);
+        assert_eq!(got.is_sync, sample.is_sync);
+        assert_eq!(got.bytes, sample.bytes);
+    }
+}
+
+#[test]
+fn test_sample_index() {
+    let data = fs::read("tests/samples/minimal.mp4").unwrap();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert_same_samples_with_index(mp4);
+
+    let mut samples = avc_samples();
+    for (i, sample) in samples.iter_mut().enumerate() {
+        sample.rendering_offset = (i as i32 % 3) * 40;
+    }
+    let data = write_progressive(&avc_track_config(), &samples);
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert!(mp4.moov.traks[0].mdia.minf.stbl.ctts.is_some());
+    assert_same_samples_with_index(mp4);
+
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+    for sample in avc_samples().iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert_same_samples_with_index(mp4);
+}
+
+#[test]
+fn test_sample_flags() {
+    let flags = mp4::SampleFlags::from_bits(0x0101_0000);
+    assert_eq!(flags.sample_depends_on, 1);
+    assert!(flags.is_non_sync_sample);
+    assert!(!flags.is_sync());
+    assert_eq!(flags.to_bits(), 0x0101_0000);
+
+    let flags = mp4::SampleFlags {
+        is_leading: 2,
+        sample_depends_on: 2,
+        sample_is_depended_on: 1,
+        sample_has_redundancy: 2,
+        sample_padding_value: 5,
+        is_non_sync_sample: false,
+        degradation_priority: 0x1234,
+    };
+    assert_eq!(mp4::SampleFlags::from_bits(flags.to_bits()), flags);
+}
+
+fn fragmented_init(tracks: u32) -> (mp4::FtypBox, mp4::MoovBox) {
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &fragment_config()).unwrap();
+    for _ in 0..tracks {
+        writer.add_track(&avc_track_config()).unwrap();
+    }
+    writer.write_init_segment().unwrap();
+    let data = writer.into_writer();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    (mp4.ftyp, mp4.moov)
+}
+
+fn write_fragmented(
+    ftyp: &mp4::FtypBox,
+    moov: &mp4::MoovBox,
+    moof: &mp4::MoofBox,
+    mdat: &[u8],
+) -> Mp4Reader<std::io::Cursor<Vec<u8>>> {
+    use mp4::WriteBox;
+
+    let mut data = Vec::new();
+    ftyp.write_box(&mut data).unwrap();
+    moov.write_box(&mut data).unwrap();
+    moof.write_box(&mut data).unwrap();
+    mp4::BoxHeader::new(mp4::BoxType::MdatBox, 8 + mdat.len() as u64)
+        .write(&mut data)
+        .unwrap();
+    data.extend_from_slice(mdat);
+    let size = data.len() as u64;
+    Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap()
+}
+
+#[test]
+fn test_read_multiple_truns_and_trexs() {
+    let (ftyp, mut moov) = fragmented_init(2);
+    let mvex = moov.mvex.as_mut().unwrap();
+    assert_eq!(mvex.trexs.len(), 2);
+    mvex.trexs[0].default_sample_duration = 1000;
+    mvex.trexs[1].default_sample_duration = 500;
+
+    let trun = |sizes: Vec<u32>, durations: Vec<u32>| mp4::TrunBox {
+        flags: mp4::TrunBox::FLAG_DATA_OFFSET
+            | mp4::TrunBox::FLAG_SAMPLE_SIZE
+            | if durations.is_empty() {
+                0
+            } else {
+                mp4::TrunBox::FLAG_SAMPLE_DURATION
+            },
+        sample_count: sizes.len() as u32,
+        data_offset: Some(0),
+        sample_sizes: sizes,
+        sample_durations: durations,
+        ..Default::default()
+    };
+    let traf = |track_id, truns| mp4::TrafBox {
+        tfhd: mp4::TfhdBox {
+            flags: mp4::TfhdBox::FLAG_DEFAULT_BASE_IS_MOOF,
+            track_id,
+            ..Default::default()
+        },
+        tfdt: Some(mp4::TfdtBox::default()),
+        truns,
+        ..Default::default()
+    };
+    let mut moof = mp4::MoofBox {
+        trafs: vec![
+            traf(1, vec![trun(vec![3, 4], vec![]), trun(vec![5], vec![2000])]),
+            traf(2, vec![trun(vec![1, 2], vec![])]),
+        ],
+        ..Default::default()
+    };
+    let mut data_offset = moof.get_size() as i32 + 8;
+    for trun in moof.trafs.iter_mut().flat_map(|traf| traf.truns.iter_mut()) {
+        trun.data_offset = Some(data_offset);
+        data_offset += trun.sample_sizes.iter().sum::<u32>() as i32;
+    }
+    let mdat: Vec<u8> = (0..15).collect();
+
+    let mut mp4 = write_fragmented(&ftyp, &moov, &moof, &mdat);
+    assert_eq!(mp4.moofs[0].trafs[0].truns.len(), 2);
+    assert_eq!(mp4.sample_count(1).unwrap(), 3);
+    assert_eq!(mp4.sample_count(2).unwrap(), 2);
+
+    let sample = |mp4: &mut Mp4Reader<_>, track_id, sample_id| {
+        let sample: mp4::Mp4Sample = mp4.read_sample(track_id, sample_id).unwrap().unwrap();
+        (sample.start_time, sample.duration, sample.bytes.to_vec())
+    };
+    assert_eq!(sample(&mut mp4, 1, 1), (0, 1000, vec![0, 1, 2]));
+    assert_eq!(sample(&mut mp4, 1, 2), (1000, 1000, vec![3, 4, 5, 6]));
+    assert_eq!(sample(&mut mp4, 1, 3), (2000, 2000, vec![7, 8, 9, 10, 11]));
+    assert_eq!(sample(&mut mp4, 2, 1), (0, 500, vec![12]));
+    assert_eq!(sample(&mut mp4, 2, 2), (500, 500, vec![13, 14]));
+
+    assert_same_samples_with_index(mp4);
+}
+
+#[test]
+fn test_read_fragment_defaults() {
+    let (ftyp, mut moov) = fragmented_init(2);
+    let mvex = moov.mvex.as_mut().unwrap();
+    mvex.trexs[0].default_sample_duration = 1000;
+    mvex.trexs[0].default_sample_size = 2;
+    mvex.trexs[1].default_sample_flags = 0x0101_0000;
+
+    // No base data offsets: the first traf starts at the moof, the second
+    // follows the data of the first.
+    let mut moof = mp4::MoofBox {
+        trafs: vec![
+            mp4::TrafBox {
+                tfhd: mp4::TfhdBox {
+                    track_id: 1,
+                    ..Default::default()
+                },
+                tfdt: Some(mp4::TfdtBox::default()),
+                truns: vec![
+                    mp4::TrunBox {
+                        flags: mp4::TrunBox::FLAG_DATA_OFFSET,
+                        sample_count: 2,
+                        ..Default::default()
+                    },
+                    mp4::TrunBox {
+                        flags: mp4::TrunBox::FLAG_SAMPLE_SIZE,
+                        sample_count: 1,
+                        sample_sizes: vec![3],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            mp4::TrafBox {
+                tfhd: mp4::TfhdBox {
+                    flags: mp4::TfhdBox::FLAG_DEFAULT_SAMPLE_DURATION
+                        | mp4::TfhdBox::FLAG_DEFAULT_SAMPLE_SIZE,
+                    track_id: 2,
+                    default_sample_duration: Some(500),
+                    default_sample_size: Some(4),
+                    ..Default::default()
+                },
+                tfdt: Some(mp4::TfdtBox::default()),
+                truns: vec![mp4::TrunBox {
+                    flags: mp4::TrunBox::FLAG_FIRST_SAMPLE_FLAGS,
+                    sample_count: 2,
+                    first_sample_flags: Some(0x0200_0000),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    moof.trafs[0].truns[0].data_offset = Some(moof.get_size() as i32 + 8);
+    let mdat: Vec<u8> = (0..15).collect();
+
+    let mut mp4 = write_fragmented(&ftyp, &moov, &moof, &mdat);
+    let sample = |mp4: &mut Mp4Reader<_>, track_id, sample_id| {
+        let sample: mp4::Mp4Sample = mp4.read_sample(track_id, sample_id).unwrap().unwrap();
+        (
+            sample.start_time,
+            sample.duration,
+            sample.is_sync,
+            sample.bytes.to_vec(),
+        )
+    };
+    assert_eq!(sample(&mut mp4, 1, 1), (0, 1000, true, vec![0, 1]));
+    assert_eq!(sample(&mut mp4, 1, 2), (1000, 1000, true, vec![2, 3]));
+    assert_eq!(sample(&mut mp4, 1, 3), (2000, 1000, true, vec![4, 5, 6]));
+    assert_eq!(sample(&mut mp4, 2, 1), (0, 500, true, vec![7, 8, 9, 10]));
+    assert_eq!(
+        sample(&mut mp4, 2, 2),
+        (500, 500, false, vec![11, 12, 13, 14])
+    );
+
+    assert_same_samples_with_index(mp4);
+}
+
+#[test]
+fn test_sample_descriptions() {
+    let mut writer = progressive_writer(&[avc_track_config()]);
+
+    let mut hd_config = avc_track_config();
+    if let mp4::MediaConfig::AvcConfig(ref mut avc_config) = hd_config.media_conf {
+        avc_config.width = 1280;
+        avc_config.height = 720;
+    }
+    assert_eq!(writer.add_sample_description(1, &hd_config).unwrap(), 2);
+    let mut aac_config = hd_config.clone();
+    aac_config.track_type = TrackType::Audio;
+    assert!(writer.add_sample_description(1, &aac_config).is_err());
+
+    // Switch to the second description at sample 6, and back at sample 16.
+    let mut samples = avc_samples();
+    for sample in samples[5..15].iter_mut() {
+        sample.sample_description_index = 2;
+    }
+    for sample in samples.iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    let mut sample = avc_samples().remove(0);
+    sample.sample_description_index = 3;
+    assert!(writer.write_sample(1, &sample).is_err());
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    let track = mp4.tracks().get(&1).unwrap();
+    assert_eq!(track.trak.mdia.minf.stbl.stsd.entries.len(), 2);
+    assert_eq!(track.width(), 320);
+    match track.sample_description(2) {
+        Some(mp4::SampleEntry::Avc1(avc1)) => assert_eq!((avc1.width, avc1.height), (1280, 720)),
+        entry => panic!("unexpected sample description {entry:?}"),
+    }
+
+    for (i, want) in samples.iter().enumerate() {
+        let sample = mp4.read_sample(1, i as u32 + 1).unwrap().unwrap();
+        assert_eq!(
+            sample.sample_description_index,
+            want.sample_description_index
+        );
+        assert_eq!(sample.bytes, want.bytes);
+    }
+}
+
+#[test]
+fn test_fragmented_sample_descriptions() {
+    let config = mp4::FragmentConfig {
+        fragment_duration: Duration::from_secs(10),
+        ..fragment_config()
+    };
+    let mut writer = mp4::Mp4FragmentWriter::write_start(Vec::new(), &config).unwrap();
+    writer.add_track(&avc_track_config()).unwrap();
+
+    let mut hd_config = avc_track_config();
+    if let mp4::MediaConfig::AvcConfig(ref mut avc_config) = hd_config.media_conf {
+        avc_config.width = 1280;
+        avc_config.height = 720;
+    }
+    assert_eq!(writer.add_sample_description(1, &hd_config).unwrap(), 2);
+
+    // Switch to the second description at sample 6, and back at sample 16.
+    let mut samples = avc_samples();
+    for sample in samples[5..15].iter_mut() {
+        sample.sample_description_index = 2;
+    }
+    for sample in samples.iter() {
+        writer.write_sample(1, sample).unwrap();
+    }
+    let mut sample = avc_samples().remove(0);
+    sample.sample_description_index = 3;
+    assert!(writer.write_sample(1, &sample).is_err());
+    assert!(writer.add_sample_description(1, &hd_config).is_err());
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer();
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+    assert_eq!(mp4.moov.traks[0].mdia.minf.stbl.stsd.entries.len(), 2);
+
+    // Each switch starts a new fragment, as a traf holds a single description.
+    let indexes: Vec<Option<u32>> = mp4
+        .moofs
+        .iter()
+        .map(|moof| moof.trafs[0].tfhd.sample_description_index)
+        .collect();
+    assert_eq!(indexes, [Some(1), Some(2), Some(1)]);
+
+    for (i, want) in samples.iter().enumerate() {
+        let sample = mp4.read_sample(1, i as u32 + 1).unwrap().unwrap();
+        assert_eq!(
+            sample.sample_description_index,
+            want.sample_description_index
+        );
+        assert_eq!(sample.bytes, want.bytes);
+    }
+}
+
+#[test]
+fn test_presentation_timeline() {
+    use mp4::WriteBox;
+    use mp4::mp4box::elst::ElstEntry;
+
+    let mut data = write_progressive(&avc_track_config(), &avc_samples());
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+
+    let timeline = mp4.presentation_timeline(1).unwrap();
+    assert_eq!(timeline.len(), 20);
+    assert_eq!(timeline[0].time, Some((0, 40)));
+    assert_eq!(timeline[19].time, Some((760, 800)));
+
+    // Start with 100ms of nothing, then show media from 60ms on for 400ms,
+    // then hold the first sample for 50ms.
+    let mut moov = mp4.moov.clone();
+    let entry = |segment_duration, media_time, media_rate| ElstEntry {
+        segment_duration,
+        media_time,
+        media_rate,
+        media_rate_fraction: 0,
+    };
+    moov.traks[0].edts = Some(mp4::EdtsBox {
+        elst: Some(mp4::ElstBox {
+            entries: vec![entry(100, -1, 1), entry(400, 60, 1), entry(50, 0, 0)],
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    data.truncate(data.len() - mp4.moov.get_size() as usize);
+    moov.write_box(&mut data).unwrap();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap();
+
+    let track = mp4.tracks().get(&1).unwrap();
+    assert_eq!(
+        track.presentation_duration(mp4.timescale()).unwrap(),
+        Duration::from_millis(550)
+    );
+    let timeline = mp4.presentation_timeline(1).unwrap();
+    assert_eq!(timeline[0].time, Some((500, 550)));
+    assert_eq!(timeline[1].time, Some((100, 120)));
+    assert_eq!(timeline[2].time, Some((120, 160)));
+    assert_eq!(timeline[11].time, Some((480, 500)));
+    assert!(timeline[12..].iter().all(|sample| sample.is_trimmed()));
+    assert_eq!(
+        track.presentation_time(3, mp4.timescale()).unwrap(),
+        Some((120, 160))
+    );
+}
+
+#[test]
+fn test_write_edit_list() {
+    let write = |edits: &[mp4::Edit]| {
+        let mut writer = progressive_writer(&[avc_track_config()]);
+        writer.set_edit_list(1, edits).unwrap();
+        for sample in avc_samples().iter() {
+            writer.write_sample(1, sample).unwrap();
+        }
+        writer.write_end().unwrap();
+        let data = writer.into_writer().into_inner();
+        let size = data.len() as u64;
+        Mp4Reader::read_header(std::io::Cursor::new(data), size).unwrap()
+    };
+
+    // Delay by 100ms and hide the first 80ms of media.
+    let mp4 = write(&[mp4::Edit::empty(100), mp4::Edit::media(80, None)]);
+    let elst = mp4.moov.traks[0]
+        .edts
+        .as_ref()
+        .unwrap()
+        .elst
+        .as_ref()
+        .unwrap();
+    assert_eq!(elst.version, 0);
+    assert_eq!(
+        elst.entries
+            .iter()
+            .map(|entry| (entry.segment_duration, entry.media_time))
+            .collect::<Vec<_>>(),
+        vec![(100, -1), (720, 80)]
+    );
+    assert_eq!(mp4.moov.traks[0].tkhd.duration, 820);
+    assert_eq!(mp4.duration(), Duration::from_millis(820));
+    let timeline = mp4.presentation_timeline(1).unwrap();
+    assert!(timeline[..2].iter().all(|sample| sample.is_trimmed()));
+    assert_eq!(timeline[2].time, Some((100, 140)));
+    assert_eq!(timeline[19].time, Some((780, 820)));
+
+    // Segments that do not fit 32 bits need a version 1 elst.
+    let long = u32::MAX as u64 + 1;
+    let mp4 = write(&[mp4::Edit::empty(long), mp4::Edit::media(0, Some(400))]);
+    let elst = mp4.moov.traks[0]
+        .edts
+        .as_ref()
+        .unwrap()
+        .elst
+        .as_ref()
+        .unwrap();
+    assert_eq!(elst.version, 1);
+    assert_eq!(elst.entries[0].segment_duration, long);
+    assert_eq!(elst.entries[0].media_time, -1);
+    assert_eq!(mp4.moov.traks[0].tkhd.duration, long + 400);
+
+    let mut writer = progressive_writer(&[avc_track_config()]);
+    let edits = [mp4::Edit::media(0, None), mp4::Edit::empty(100)];
+    assert!(writer.set_edit_list(1, &edits).is_err());
+    assert!(writer.set_edit_list(2, &[]).is_err());
+}
+
+#[test]
+fn test_read_header_lenient() {
+    let mut writer = progressive_writer(&[avc_track_config(), avc_track_config()]);
+    for sample in avc_samples().iter() {
+        writer.write_sample(1, sample).unwrap();
+        writer.write_sample(2, sample).unwrap();
+    }
+    writer.metadata_mut().set_title("After the broken trak");
+    writer.write_end().unwrap();
+    let mut data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+
+    // Give the second tkhd an unsupported version.
+    let tkhd = data
+        .windows(4)
+        .enumerate()
+        .filter(|(_, fourcc)| fourcc == b"tkhd")
+        .nth(1)
+        .unwrap()
+        .0;
+    data[tkhd + 4] = 5;
+
+    let cursor = std::io::Cursor::new(data.clone());
+    assert!(Mp4Reader::read_header(cursor, size).is_err());
+
+    let cursor = std::io::Cursor::new(data);
+    let (mut mp4, warnings) = Mp4Reader::read_header_lenient(cursor, size).unwrap();
+    assert_eq!(mp4.tracks().len(), 1);
+    assert_eq!(mp4.sample_count(1).unwrap(), 20);
+    assert!(mp4.read_sample(1, 20).unwrap().is_some());
+    // The boxes after the broken one are still read.
+    assert_eq!(
+        mp4.metadata().title().as_deref(),
+        Some("After the broken trak")
+    );
+
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        warnings[0].path,
+        vec![
+            mp4::BoxType::MoovBox,
+            mp4::BoxType::TrakBox,
+            mp4::BoxType::TkhdBox
+        ]
+    );
+    assert_eq!(warnings[0].offset, tkhd as u64 - 4);
+    assert_eq!(
+        warnings[0].to_string(),
+        format!(
+            "moov.trak.tkhd at offset {}: version must be 0 or 1",
+            tkhd - 4
+        )
+    );
+    assert_eq!(
+        warnings[1].path,
+        vec![mp4::BoxType::MoovBox, mp4::BoxType::TrakBox]
+    );
+    assert!(matches!(
+        warnings[1].reason,
+        mp4::Error::BoxNotFound(mp4::BoxType::TkhdBox)
+    ));
+}
+
+#[test]
+fn test_read_largesize_boxes() {
+    let data = fs::read("tests/samples/minimal.mp4").unwrap();
+    let ftyp_size = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+
+    // A free box with a 64-bit size right after ftyp.
+    let mut largesize = data[..ftyp_size].to_vec();
+    largesize.extend_from_slice(&1u32.to_be_bytes());
+    largesize.extend_from_slice(b"free");
+    largesize.extend_from_slice(&16u64.to_be_bytes());
+    largesize.extend_from_slice(&data[ftyp_size..]);
+    let size = largesize.len() as u64;
+
+    let mp4 = Mp4Reader::read_header(std::io::Cursor::new(largesize.clone()), size).unwrap();
+    assert_eq!(mp4.size(), size);
+    assert_eq!(mp4.tracks().len(), 2);
+    let (_, warnings) =
+        Mp4Reader::read_header_lenient(std::io::Cursor::new(largesize.clone()), size).unwrap();
+    assert!(warnings.is_empty());
+    let lazy = mp4::Mp4LazyReader::read_header(std::io::Cursor::new(largesize), size, 1).unwrap();
+    assert_eq!(lazy.size(), size);
+
+    // A broken trak with a 64-bit size inside moov is skipped as a whole.
+    let mut data = write_progressive(&avc_track_config(), &avc_samples());
+    let moov = data
+        .windows(4)
+        .position(|fourcc| fourcc == b"moov")
+        .unwrap()
+        - 4;
+    let mvhd_size = u32::from_be_bytes(data[moov + 8..moov + 12].try_into().unwrap()) as usize;
+    let mut trak = 1u32.to_be_bytes().to_vec();
+    trak.extend_from_slice(b"trak");
+    trak.extend_from_slice(&24u64.to_be_bytes());
+    trak.extend_from_slice(&[0; 8]);
+    data.splice(moov + 8 + mvhd_size..moov + 8 + mvhd_size, trak);
+    let moov_size = u32::from_be_bytes(data[moov..moov + 4].try_into().unwrap()) + 24;
+    data[moov..moov + 4].copy_from_slice(&moov_size.to_be_bytes());
+    let size = data.len() as u64;
+
+    let cursor = std::io::Cursor::new(data);
+    let (mut mp4, warnings) = Mp4Reader::read_header_lenient(cursor, size).unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        warnings[1].path,
+        vec![mp4::BoxType::MoovBox, mp4::BoxType::TrakBox]
+    );
+    assert_eq!(warnings[1].offset, (moov + 8 + mvhd_size) as u64);
+    assert_eq!(mp4.tracks().len(), 1);
+    assert_eq!(mp4.sample_count(1).unwrap(), 20);
+    assert!(mp4.read_sample(1, 20).unwrap().is_some());
+}
+
+#[test]
+fn test_recover_truncated_moov() {
+    // Length-prefixed access units: an SPS ahead of every IDR slice, and a
+    // second slice in every other picture.
+    let nal = |header: &[u8], len: usize| {
+        let mut nal = (len as u32 + header.len() as u32).to_be_bytes().to_vec();
+        nal.extend_from_slice(header);
+        nal.resize(nal.len() + len, 0xaa);
+        nal
+    };
+    let video: Vec<mp4::Mp4Sample> = (0..60u64)
+        .map(|i| {
+            let mut bytes = Vec::new();
+            if i % 5 == 0 {
+                bytes.extend(nal(&[0x67, 0x64, 0x00, 0x1f], 2));
+                bytes.extend(nal(&[0x65, 0x88], 100 + i as usize));
+            } else {
+                bytes.extend(nal(&[0x41, 0x9a], 50 + i as usize));
+            }
+            if i % 2 == 0 {
+                bytes.extend(nal(&[0x41, 0x40], 20));
+            }
+            mp4::Mp4Sample {
+                start_time: i * 40,
+                duration: 40,
+                rendering_offset: 0,
+                is_sync: i % 5 == 0,
+                flags: None,
+                sample_description_index: 1,
+                bytes: mp4::Bytes::from(bytes),
+            }
+        })
+        .collect();
+    // Variable bitrate AAC frames: a CPE with a common window over 49 bands
+    // in a few sections, noise and an END element, or every 16th frame, a
+    // silent one with no bands.
+    let mut seed = 1u32;
+    let mut random = move || {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (seed >> 16) as u8
+    };
+    let put = |bits: &mut Vec<bool>, value: u32, count: u32| {
+        bits.extend((0..count).rev().map(|bit| (value >> bit) & 1 == 1));
+    };
+    let audio: Vec<mp4::Mp4Sample> = (0..112u64)
+        .map(|i| {
+            let max_sfb = if i % 16 == 15 { 0 } else { 49 };
+            let mut bits = Vec::new();
+            put(&mut bits, 0x21, 8); // CPE 0, common_window
+            put(&mut bits, max_sfb << 1, 11); // ics_info
+            put(&mut bits, 0, 2); // ms_mask_present
+            put(&mut bits, random() as u32, 8); // global_gain
+            if max_sfb == 0 {
+                // No pulse, tns or gain control data in either channel.
+                put(&mut bits, 0, 3);
+                put(&mut bits, random() as u32, 8);
+                put(&mut bits, 0, 3);
+                put(&mut bits, 7, 3); // END
+            } else {
+                let sections = 3 + random() as u32 % 6;
+                for section in 0..sections {
+                    put(&mut bits, 1 + random() as u32 % 11, 4);
+                    let length = max_sfb / sections;
+                    if section == sections - 1 {
+                        put(&mut bits, max_sfb - length * (sections - 1), 5);
+                    } else {
+                        put(&mut bits, length, 5);
+                    }
+                }
+            }
+            let mut bytes: Vec<u8> = bits
+                .chunks(8)
+                .map(|byte| {
+                    let value = byte.iter().fold(0, |value, bit| (value << 1) | *bit as u8);
+                    value << (8 - byte.len())
+                })
+                .collect();
+            if max_sfb > 0 {
+                let size = 150 + random() as usize;
+                bytes.extend((bytes.len()..size - 1).map(|_| random()));
+                bytes.push(0x70 | (random() & 0x80));
+            }
+            mp4::Mp4Sample {
+                start_time: i * 1024,
+                duration: 1024,
+                rendering_offset: 0,
+                is_sync: true,
+                flags: None,
+                sample_description_index: 1,
+                bytes: mp4::Bytes::from(bytes),
+            }
+        })
+        .collect();
+
+    let mut writer = progressive_writer(&[avc_track_config(), aac_track_config()]);
+    let mut audio_samples = audio.iter().peekable();
+    for sample in video.iter() {
+        writer.write_sample(1, sample).unwrap();
+        let video_end = (sample.start_time + 40) * 48;
+        while let Some(sample) = audio_samples.next_if(|audio| audio.start_time < video_end) {
+            writer.write_sample(2, sample).unwrap();
+        }
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let reference = Mp4Reader::read_header(std::io::Cursor::new(data.clone()), size).unwrap();
+    let recovery = mp4::RecoveryConfig::from_reference(&reference).unwrap();
+    assert_eq!(recovery.tracks[0].sample_duration, 40);
+    assert_eq!(recovery.tracks[0].framing, mp4::SampleFraming::NalUnits);
+    assert_eq!(recovery.tracks[1].sample_duration, 1024);
+    let mp4::SampleFraming::AacChunks {
+        frames_per_chunk, ..
+    } = &recovery.tracks[1].framing
+    else {
+        panic!("expected AAC chunks");
+    };
+    assert_eq!(frames_per_chunk, &(47..=47));
+
+    // Cut the file off early in moov, leaving the mdat size unset as a
+    // recorder would.
+    let mdat = data
+        .windows(4)
+        .position(|fourcc| fourcc == b"mdat")
+        .unwrap()
+        - 4;
+    let moov = data
+        .windows(4)
+        .position(|fourcc| fourcc == b"moov")
+        .unwrap()
+        - 4;
+    let mut damaged = data[..moov + 12].to_vec();
+    damaged[mdat..mdat + 4].copy_from_slice(&[0, 0, 0, 0]);
+    let size = damaged.len() as u64;
+    assert!(Mp4Reader::read_header(std::io::Cursor::new(damaged.clone()), size).is_err());
+
+    let recovered = mp4::recover_mp4(
+        std::io::Cursor::new(damaged),
+        size,
+        std::io::Cursor::new(Vec::<u8>::new()),
+        &recovery,
+    )
+    .unwrap()
+    .into_inner();
+    let size = recovered.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(recovered), size).unwrap();
+    for (track_id, samples) in [(1, &video), (2, &audio)] {
+        assert_eq!(mp4.sample_count(track_id).unwrap(), samples.len() as u32);
+        for (i, expected) in samples.iter().enumerate() {
+            let sample = mp4.read_sample(track_id, i as u32 + 1).unwrap().unwrap();
+            assert_eq!(&sample, expected);
+        }
+    }
+    assert_eq!(
+        mp4.tracks()[&1].sequence_parameter_set().unwrap(),
+        reference.tracks()[&1].sequence_parameter_set().unwrap()
+    );
+}
+
+#[test]
+fn test_recover_hevc_with_reference_sample_entry() {
+    // Access units of 2 byte length-prefixed NAL units: an IDR picture,
+    // then two trailing pictures.
+    let samples: Vec<mp4::Mp4Sample> = (0..9u64)
+        .map(|i| {
+            let header: &[u8] = if i % 3 == 0 {
+                &[0x26, 0x01]
+            } else {
+                &[0x02, 0x01]
+            };
+            let mut bytes = (50 + i as u16 + 2).to_be_bytes().to_vec();
+            bytes.extend_from_slice(header);
+            bytes.push(0x80); // first_slice_segment_in_pic_flag
+            bytes.resize(bytes.len() + 49 + i as usize, 0xaa);
+            mp4::Mp4Sample {
+                start_time: i * 40,
+                duration: 40,
+                rendering_offset: 0,
+                is_sync: i % 3 == 0,
+                flags: None,
+                sample_description_index: 1,
+                bytes: mp4::Bytes::from(bytes),
+            }
+        })
+        .collect();
+
+    let config = mp4::TrackConfig {
+        track_type: TrackType::Video,
+        timescale: 1000,
+        language: String::from("und"),
+        media_conf: mp4::MediaConfig::HevcConfig(mp4::HevcConfig {
+            width: 320,
+            height: 240,
+        }),
+    };
+    let data = write_progressive(&config, &samples);
+    let moov = data
+        .windows(4)
+        .position(|fourcc| fourcc == b"moov")
+        .unwrap()
+        - 4;
+    let damaged = data[..moov].to_vec();
+    let size = damaged.len() as u64;
+
+    let mut hev1 = mp4::Hev1Box::new(&mp4::HevcConfig {
+        width: 320,
+        height: 240,
+    });
+    hev1.hvcc.length_size_minus_one = 1;
+    hev1.hvcc.arrays.push(mp4::hev1::HvcCArray {
+        completeness: true,
+        nal_unit_type: 32,
+        nalus: vec![mp4::hev1::HvcCArrayNalu {
+            size: 4,
+            data: vec![0x40, 0x01, 0x0c, 0x01],
+        }],
+    });
+    let mut track = mp4::RecoveryTrack {
+        config,
+        sample_duration: 40,
+        framing: mp4::SampleFraming::NalUnits,
+        sample_entry: None,
+    };
+    let recover = |track: &mp4::RecoveryTrack| {
+        let recovery = mp4::RecoveryConfig {
+            mp4: progressive_config(),
+            tracks: vec![track.clone()],
+        };
+        mp4::recover_mp4(
+            std::io::Cursor::new(damaged.clone()),
+            size,
+            std::io::Cursor::new(Vec::<u8>::new()),
+            &recovery,
+        )
+    };
+    // Without hvcC, neither the parameter sets nor the prefix size are known.
+    assert!(recover(&track).is_err());
+
+    track.sample_entry = Some(mp4::SampleEntry::Hev1(hev1.clone()));
+    let recovered = recover(&track).unwrap().into_inner();
+    let size = recovered.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(std::io::Cursor::new(recovered), size).unwrap();
+    let hvcc = &mp4.tracks()[&1]
+        .trak
+        .mdia
+        .minf
+        .stbl
+        .stsd
+        .hev1()
+        .unwrap()
+        .hvcc;
+    assert_eq!(hvcc.length_size_minus_one, 1);
+    assert_eq!(hvcc.arrays, hev1.hvcc.arrays);
+    assert_eq!(mp4.sample_count(1).unwrap(), samples.len() as u32);
+    for (i, expected) in samples.iter().enumerate() {
+        let sample = mp4.read_sample(1, i as u32 + 1).unwrap().unwrap();
+        assert_eq!(&sample, expected);
+    }
+}
+
+#[test]
+fn test_box_tree() {
+    let f = File::open("tests/samples/minimal.mp4").unwrap();
+    let size = f.metadata().unwrap().len();
+    let mut reader = BufReader::new(f);
+    let boxes = mp4::read_box_tree(&mut reader, size).unwrap();
+    let mp4 = get_reader("tests/samples/minimal.mp4");
+
+    let top: Vec<(String, u64, u64)> = boxes
+        .iter()
+        .map(|b| (b.box_type.to_string(), b.offset, b.size))
+        .collect();
+    assert_eq!(
+        top,
+        vec![
+            ("ftyp".to_string(), 0, 32),
+            ("moov".to_string(), 32, 1273),
+            ("free".to_string(), 1305, 8),
+            ("mdat".to_string(), 1313, 1278),
+        ]
+    );
+    assert!(matches!(boxes[2].status, mp4::BoxStatus::Unknown));
+    assert_eq!(mp4.ftyp.byte_offset(), Some(0));
+    assert_eq!(mp4.moov.byte_offset(), Some(32));
+
+    let mut nodes = Vec::new();
+    for b in boxes.iter() {
+        b.walk(&mut |node| nodes.push(node));
+    }
+    assert!(
+        nodes
+            .iter()
+            .all(|node| matches!(node.status, mp4::BoxStatus::Parsed) || node.depth == 0)
+    );
+
+    // The typed boxes record the same offsets as the walker finds.
+    let offsets = |box_type: mp4::BoxType| -> Vec<u64> {
+        nodes
+            .iter()
+            .filter(|node| node.box_type == box_type)
+            .map(|node| node.offset)
+            .collect()
+    };
+    let mut traks: Vec<_> = mp4.moov.traks.iter().collect();
+    traks.sort_by_key(|trak| trak.byte_offset());
+    let typed: Vec<u64> = traks
+        .iter()
+        .map(|trak| trak.mdia.minf.stbl.stsz.byte_offset().unwrap())
+        .collect();
+    assert_eq!(offsets(mp4::BoxType::StszBox), typed);
+    let avc1 = traks[0].mdia.minf.stbl.stsd.avc1().unwrap();
+    assert_eq!(
+        offsets(mp4::BoxType::Avc1Box),
+        vec![avc1.byte_offset().unwrap()]
+    );
+    let mp4a = traks[1].mdia.minf.stbl.stsd.mp4a().unwrap();
+    assert_eq!(
+        offsets(mp4::BoxType::Mp4aBox),
+        vec![mp4a.byte_offset().unwrap()]
+    );
+
+    let meta = mp4::find_box(&boxes, "moov/udta/meta").unwrap();
+    assert_eq!(meta.children[0].box_type, mp4::BoxType::HdlrBox);
+    assert_eq!(meta.children[0].offset, meta.payload_offset() + 4);
+    let item = &meta.children[1].children[0];
+    assert_eq!(item.depth, 4);
+    assert_eq!(item.children[0].box_type, mp4::BoxType::DataBox);
+}
+
+#[test]
+fn test_box_tree_damaged() {
+    let mut data = fs::read("tests/samples/minimal.mp4").unwrap();
+    let tkhd = data
+        .windows(4)
+        .position(|fourcc| fourcc == b"tkhd")
+        .unwrap();
+    data[tkhd + 4] = 5;
+    // Cut the file off in the middle of mdat.
+    data.truncate(data.len() - 100);
+
+    let size = data.len() as u64;
+    let boxes = mp4::read_box_tree(&mut std::io::Cursor::new(data), size).unwrap();
+    let tkhd = mp4::find_box(&boxes, "moov/trak/tkhd").unwrap();
+    assert_eq!(tkhd.offset, boxes[1].children[1].children[0].offset);
+    assert!(matches!(
+        tkhd.status,
+        mp4::BoxStatus::Invalid(mp4::Error::InvalidData(_))
+    ));
+    assert_eq!(boxes[3].box_type, mp4::BoxType::MdatBox);
+    assert!(matches!(boxes[3].status, mp4::BoxStatus::Truncated));
+    assert_eq!(boxes[3].end(), size + 100);
+}